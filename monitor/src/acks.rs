@@ -0,0 +1,121 @@
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::collision_monitor::Robot;
+
+/// [AckTracker] checks that robots acknowledge the states the monitor sends them. Each reply is
+/// stamped with its round, and a robot that has applied it echoes the round back as `acked_round`
+/// in its next report. Robots missing `deadline_rounds` acknowledgements in a row are unacked.
+#[derive(Debug, Default)]
+pub(crate) struct AckTracker {
+    // round of the last state sent to each device
+    last_sent: HashMap<String, u64>,
+    // number of consecutive rounds each device failed to acknowledge
+    missed: HashMap<String, u64>,
+}
+
+impl AckTracker {
+    /// `sent` records that the state of `round` was sent to a device.
+    pub(crate) fn sent(&mut self, device_id: &str, round: u64) {
+        self.last_sent.insert(device_id.to_string(), round);
+    }
+
+    /// `observe` checks whether an incoming report acknowledges the last state sent to its device.
+    pub(crate) fn observe(&mut self, robot: &Robot) {
+        let expected = match self.last_sent.get(&robot.device_id) {
+            Some(&round) => round,
+            None => return,
+        };
+
+        let missed = self.missed.entry(robot.device_id.clone()).or_insert(0);
+        if robot.acked_round == Some(expected) {
+            *missed = 0;
+        } else {
+            *missed += 1;
+        }
+    }
+
+    /// `unacked` returns the devices that missed at least `deadline_rounds` acknowledgements in a row.
+    pub(crate) fn unacked(&self, deadline_rounds: u64) -> HashSet<String> {
+        self.missed
+            .iter()
+            .filter(|(_, &missed)| missed >= deadline_rounds.max(1))
+            .map(|(device_id, _)| device_id.clone())
+            .collect()
+    }
+
+    /// `report` lists the consecutive missed acknowledgements of every unacked device.
+    pub(crate) fn report(&self, deadline_rounds: u64) -> BTreeMap<String, u64> {
+        self.unacked(deadline_rounds)
+            .into_iter()
+            .map(|device_id| {
+                let missed = self.missed[&device_id];
+                (device_id, missed)
+            })
+            .collect()
+    }
+}
+
+/// [UnackedRobot] defines a robot that stopped acknowledging the states sent to it.
+#[derive(Debug, Serialize)]
+pub(crate) struct UnackedRobot {
+    pub device_id: String,
+    pub missed_rounds: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision_monitor::{CollisionMonitor, MotionState};
+    use crate::config::{test_config, CollisionMonitorConfig};
+
+    fn robot(device_id: &str, x: f64, acked_round: Option<u64>) -> Robot {
+        Robot {
+            x,
+            device_id: device_id.to_string(),
+            acked_round,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ack_tracker_escalates_missed_deadline() {
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            ack_escalation_radius: 3.0,
+            ..test_config()
+        });
+        let mut tracker = AckTracker::default();
+
+        // robot1 keeps acknowledging, robot2 stops after the first round.
+        tracker.sent("robot1", 0);
+        tracker.sent("robot2", 0);
+        tracker.observe(&robot("robot1", 0.0, Some(0)));
+        tracker.observe(&robot("robot2", 2.0, Some(0)));
+        for round in 1..3 {
+            tracker.sent("robot1", round);
+            tracker.sent("robot2", round);
+            tracker.observe(&robot("robot1", 0.0, Some(round)));
+            tracker.observe(&robot("robot2", 2.0, Some(0)));
+        }
+        assert!(tracker.unacked(3).is_empty());
+        assert_eq!(tracker.unacked(2), HashSet::from(["robot2".to_string()]));
+        assert_eq!(
+            tracker.report(2),
+            BTreeMap::from([("robot2".to_string(), 2)])
+        );
+
+        let mut robots = vec![
+            robot("robot1", 0.0, Some(2)),
+            robot("robot2", 2.0, Some(0)),
+            robot("robot3", 10.0, Some(2)),
+        ];
+        collision_monitor.escalate_unacked(&mut robots, &tracker.unacked(2));
+        assert_eq!(robots[0].state, MotionState::Pause);
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!(robots[2].state, MotionState::Resume);
+
+        // acknowledging again clears the escalation.
+        tracker.observe(&robot("robot2", 2.0, Some(2)));
+        assert!(tracker.unacked(2).is_empty());
+    }
+}
@@ -1,40 +1,231 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{de, Deserialize as _, Deserializer};
 use serde_derive::{Deserialize, Serialize};
-use std::{collections::HashSet, f64};
-
-use crate::config::CollisionMonitorConfig;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    f64, fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::config::{
+    parse_version, CollisionMonitorConfig, DeadlockDetector, ResolutionStrategy,
+    SeedCollisionPolicy,
+};
+use crate::downsampling::CheckDownsampler;
+use crate::geometry;
+use crate::metrics::Metrics;
+use crate::spatial_grid::{BoundingBox, SpatialGrid};
+
+/// `BOUNDING_BOX_PADDING` widens the robot bounding boxes used by the spatial grid.
+const BOUNDING_BOX_PADDING: f64 = 1e-6;
 
 /// [CollisionMonitor] defines the struct for the collision monitoring system.
 #[derive(Debug)]
 pub(crate) struct CollisionMonitor {
     // current Collision Monitor configuration
     pub config: CollisionMonitorConfig,
+    // skips collision checks of far robots between full checks, if enabled
+    downsampler: Option<Mutex<CheckDownsampler>>,
+    // operational counters updated by every round, if attached
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl CollisionMonitor {
     /// `new` creates a new instance of `CollisionMonitor`.
     pub(crate) fn new(config: CollisionMonitorConfig) -> Self {
-        CollisionMonitor { config }
+        let downsampler = config.enable_check_downsampling.then(|| {
+            Mutex::new(CheckDownsampler::new(
+                config.far_check_every,
+                config.far_distance,
+            ))
+        });
+
+        CollisionMonitor {
+            config,
+            downsampler,
+            metrics: None,
+        }
+    }
+
+    /// `with_metrics` attaches the shared metrics updated by every round.
+    pub(crate) fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
     }
 
     /// `trigger_collision_monitor` triggeres the collision detection and deadock detection methods
     /// once all the agents are done
     pub(crate) fn trigger_collision_monitor(
+        &self,
+        robots: Vec<Robot>,
+    ) -> Result<Vec<Robot>, MonitorError> {
+        self.check_agent_set(&robots)?;
+
+        self.resolve_robot_states(robots)
+    }
+
+    /// `resolve_robot_states` runs collision and deadlock detection over `robots` without checking
+    /// the agent set, for rounds whose agents were checked before obstacles were added to them.
+    pub(crate) fn resolve_robot_states(
         &self,
         mut robots: Vec<Robot>,
-    ) -> Result<Vec<Robot>, String> {
-        if robots.len() != self.config.num_agents {
-            return Err("Not yet received all agent records".to_string());
-        }
+    ) -> Result<Vec<Robot>, MonitorError> {
+        let skipped = match &self.downsampler {
+            Some(downsampler) => downsampler
+                .lock()
+                .expect("downsampler lock poisoned")
+                .skipped(&robots),
+            None => HashSet::new(),
+        };
+        self.resolve_round(&mut robots, &skipped);
+
+        // a round leaving the whole fleet paused while robots still collide cannot make progress.
+        let conflicts = self.detect_collisions(&robots);
+        let standstill = robots
+            .iter()
+            .all(|robot| matches!(robot.state, MotionState::Pause | MotionState::Stopped));
+        if !conflicts.is_empty() && standstill {
+            let mut device_ids: Vec<String> = conflicts
+                .iter()
+                .flat_map(|&(i, j)| [robots[i].device_id.clone(), robots[j].device_id.clone()])
+                .collect();
+            device_ids.sort();
+            device_ids.dedup();
 
-        self.update_robot_state(&mut robots);
+            return Err(MonitorError::DeadlockUnresolvable { device_ids });
+        }
 
         Ok(robots)
     }
 
+    /// `check_agent_set` fails until a record has been received from every configured agent. With a
+    /// batch window the round is whatever arrived within the window, so any set of agents passes.
+    pub(crate) fn check_agent_set(&self, robots: &[Robot]) -> Result<(), MonitorError> {
+        match (self.config.batch_window_ms, self.config.num_agents) {
+            (None, Some(num_agents)) if robots.len() != num_agents => {
+                Err(MonitorError::IncompleteAgentSet {
+                    received: robots.len(),
+                    expected: num_agents,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `hold_robot_states` pauses every robot in place once all the agents are done, without
+    /// running collision detection. It is used while the fleet is frozen for maintenance.
+    pub(crate) fn hold_robot_states(&self, robots: Vec<Robot>) -> Result<Vec<Robot>, MonitorError> {
+        self.check_agent_set(&robots)?;
+
+        Ok(Self::pause_robot_states(robots))
+    }
+
+    /// `pause_robot_states` pauses every robot in place without checking the agent set.
+    pub(crate) fn pause_robot_states(mut robots: Vec<Robot>) -> Vec<Robot> {
+        for robot in robots.iter_mut() {
+            transition(robot, MotionState::Pause, TransitionReason::Freeze);
+        }
+
+        robots
+    }
+
+    /// `check_client_version` rejects robots whose reported client version is older than the
+    /// configured `min_client_version`.
+    pub(crate) fn check_client_version(&self, robot: &Robot) -> Result<(), String> {
+        let min_client_version = match &self.config.min_client_version {
+            Some(version) => version,
+            None => return Ok(()),
+        };
+
+        let client_version = robot.client_version.as_ref().ok_or_else(|| {
+            format!(
+                "Robot {} does not report a client version, {} or newer is required",
+                robot.device_id, min_client_version
+            )
+        })?;
+
+        let client_release = parse_version(client_version).map_err(|error| {
+            format!(
+                "Robot {} reports a client version {}",
+                robot.device_id, error
+            )
+        })?;
+        let min_release = parse_version(min_client_version)
+            .map_err(|error| format!("min_client_version {}", error))?;
+
+        if client_release < min_release {
+            return Err(format!(
+                "Robot {} runs client version {}, {} or newer is required",
+                robot.device_id, client_version, min_client_version
+            ));
+        }
+
+        Ok(())
+    }
+
     /// `update_robot_state` updates states of robots after detecting conflicts and deadlocks.
     pub(crate) fn update_robot_state(&self, robots: &mut [Robot]) {
-        let mut conflicts = self.detect_collisions(robots);
-        let mut deadlock = !conflicts.is_empty();
+        self.resolve_round(robots, &HashSet::new());
+    }
+
+    /// `resolve_round` updates states of robots after detecting conflicts and deadlocks, leaving the
+    /// robots at the `skipped` indices out of the initial collision checks. The robots of each zone
+    /// are resolved on their own, so a deadlock in one zone never pauses another.
+    fn resolve_round(&self, robots: &mut [Robot], skipped: &HashSet<usize>) {
+        let mut zones: Vec<Option<String>> = Vec::new();
+        for robot in robots.iter() {
+            if !zones.contains(&robot.zone) {
+                zones.push(robot.zone.clone());
+            }
+        }
+        if zones.len() <= 1 {
+            return self.resolve_zone(robots, skipped);
+        }
+
+        for zone in zones {
+            let indices: Vec<usize> = (0..robots.len())
+                .filter(|&idx| robots[idx].zone == zone)
+                .collect();
+            let mut members: Vec<Robot> = indices.iter().map(|&idx| robots[idx].clone()).collect();
+            let members_skipped: HashSet<usize> = indices
+                .iter()
+                .enumerate()
+                .filter(|(_, idx)| skipped.contains(idx))
+                .map(|(member, _)| member)
+                .collect();
+
+            self.resolve_zone(&mut members, &members_skipped);
+
+            for (idx, robot) in indices.into_iter().zip(members) {
+                robots[idx] = robot;
+            }
+        }
+    }
+
+    /// `resolve_zone` updates states of the robots of a single zone after detecting conflicts and
+    /// deadlocks, leaving the robots at the `skipped` indices out of the initial collision checks.
+    fn resolve_zone(&self, robots: &mut [Robot], skipped: &HashSet<usize>) {
+        self.pause_low_battery(robots);
+        self.pause_pathless(robots);
+        self.pause_out_of_bounds(robots);
+
+        if !self.config.zones.is_empty() {
+            self.enforce_zone_occupancy(robots);
+        }
+
+        let mut conflicts = self.detect_collisions_skipping(
+            robots,
+            skipped,
+            self.config.lookahead_steps.unwrap_or(0),
+        );
+        let initial_conflicts = conflicts.clone();
+        let mut deadlock = false;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_collisions(initial_conflicts.len());
+        }
 
         // if conflicts are empty simply update next state and move
         // robot to mext coordinate
@@ -44,636 +235,3788 @@ impl CollisionMonitor {
             }
         }
 
-        while !conflicts.is_empty() && !deadlock {
-            // Define the conflict resolution order
-            let conflict_order: Vec<usize> = conflicts.iter().map(|&(i, _)| i).collect();
+        let holding = |state| matches!(state, MotionState::Pause | MotionState::Stopped);
+        // a pass made progress when it moved a robot.
+        let positions = |robots: &[Robot]| {
+            robots
+                .iter()
+                .map(|robot| (robot.x, robot.y, robot.path_index))
+                .collect::<Vec<_>>()
+        };
+        // robots already moved this round, so a robot in several conflicts advances only once.
+        let mut advanced: HashSet<usize> = HashSet::new();
+        let mut iterations = 0;
+
+        while !conflicts.is_empty() {
+            if iterations == self.config.max_resolution_iterations {
+                log::warn!(
+                    "Conflicts {} still unresolved after {} resolution passes, pausing all robots",
+                    Self::describe_conflicts(robots, &conflicts),
+                    iterations
+                );
+                deadlock = true;
+                break;
+            }
+            iterations += 1;
 
-            for &idx in &conflict_order {
-                let (first_conflict_idx, second_conflict_idx) = conflicts[idx];
+            let before = positions(robots);
 
-                if robots[first_conflict_idx].state == MotionState::Pause.to_string()
-                    || robots[second_conflict_idx].state == MotionState::Pause.to_string()
+            // every detected pair is decided once per pass, in the order they were detected.
+            for &(first_conflict_idx, second_conflict_idx) in &conflicts {
+                if holding(robots[first_conflict_idx].state)
+                    || holding(robots[second_conflict_idx].state)
                 {
                     continue;
                 }
 
-                let (new_state_i, new_state_j) = self.resolve_collision();
-
-                if new_state_i == MotionState::Pause && new_state_j == MotionState::Pause {
-                    deadlock = true;
-                    break;
-                }
-
-                if new_state_i == MotionState::Resume {
-                    self.update_motion_coordinates(&mut robots[first_conflict_idx]);
-                }
-
-                if new_state_j == MotionState::Resume {
-                    self.update_motion_coordinates(&mut robots[second_conflict_idx]);
+                let (new_state_i, new_state_j) =
+                    self.resolve_collision(robots, first_conflict_idx, second_conflict_idx);
+
+                transition(
+                    &mut robots[first_conflict_idx],
+                    new_state_i,
+                    TransitionReason::Collision,
+                );
+                transition(
+                    &mut robots[second_conflict_idx],
+                    new_state_j,
+                    TransitionReason::Collision,
+                );
+                for idx in [first_conflict_idx, second_conflict_idx] {
+                    // a robot which just stepped aside stays there for the rest of the round.
+                    if robots[idx].state == MotionState::Reroute {
+                        advanced.insert(idx);
+                    } else if !holding(robots[idx].state) && advanced.insert(idx) {
+                        self.update_motion_coordinates(&mut robots[idx]);
+                    }
                 }
-
-                robots[first_conflict_idx].state = new_state_i.to_string();
-                robots[second_conflict_idx].state = new_state_j.to_string();
             }
 
             conflicts = self.detect_collisions(robots);
 
-            if !conflicts.is_empty() {
+            if !conflicts.is_empty() && self.config.deadlock_detector == DeadlockDetector::Conflicts
+            {
                 self.resolve_deadlock(robots, &conflicts);
+                conflicts = self.detect_collisions(robots);
+            }
+
+            // robots still colliding after a pass which moved none of them are stuck.
+            if !conflicts.is_empty() && positions(robots) == before {
+                deadlock = true;
+                break;
+            }
+
+            // once every remaining conflict involves a held robot there is nothing left to decide.
+            if conflicts
+                .iter()
+                .all(|&(i, j)| holding(robots[i].state) || holding(robots[j].state))
+            {
+                break;
             }
         }
 
         if deadlock {
-            for robot in robots {
-                robot.state = MotionState::Pause.to_string();
+            for robot in robots.iter_mut() {
+                transition(robot, MotionState::Pause, TransitionReason::Deadlock);
             }
         }
+
+        if !self.config.no_stop_regions.is_empty() {
+            self.honour_commit_points(robots, &initial_conflicts);
+        }
+
+        // robots that just moved may now be one step away from the edge.
+        self.pause_out_of_bounds(robots);
+        self.enforce_emergency_stops(robots);
     }
 
-    /// `detect_collisions` detects collission between all robots at current timestamp.
-    fn detect_collisions(&self, robots: &[Robot]) -> Vec<(usize, usize)> {
-        let mut conflicts: Vec<(usize, usize)> = Vec::new();
+    /// `pause_low_battery` pauses every robot whose battery level is below `min_operating_soc`, so a
+    /// dying robot stops advancing along its path.
+    fn pause_low_battery(&self, robots: &mut [Robot]) {
+        for robot in robots
+            .iter_mut()
+            .filter(|robot| robot.battery_level < self.config.min_operating_soc)
+        {
+            if robot.state != MotionState::Pause {
+                log::warn!(
+                    "{} is at {}% battery, below the minimum operating level, pausing it",
+                    robot.device_id,
+                    robot.battery_level
+                );
+            }
+            transition(robot, MotionState::Pause, TransitionReason::Battery);
+        }
+    }
 
-        for idx in 0..robots.len() {
-            for jdx in (idx + 1)..robots.len() {
-                if self.will_collision_occur(&robots[idx], &robots[jdx]) {
-                    conflicts.push((idx, jdx));
-                }
+    /// `pause_pathless` holds robots without a path in place: with nowhere to go they are treated
+    /// as stationary obstacles.
+    fn pause_pathless(&self, robots: &mut [Robot]) {
+        for robot in robots.iter_mut().filter(|robot| robot.path.is_empty()) {
+            if robot.state != MotionState::Pause {
+                log::warn!("{} has an empty path, pausing it", robot.device_id);
             }
+            transition(robot, MotionState::Pause, TransitionReason::Path);
         }
+    }
 
-        conflicts
+    /// `pause_out_of_bounds` pauses every moving robot whose next path point lies outside the
+    /// configured grid bounds, so bad path data can't lead it off the warehouse floor.
+    fn pause_out_of_bounds(&self, robots: &mut [Robot]) {
+        for robot in robots.iter_mut() {
+            if !matches!(robot.state, MotionState::Resume | MotionState::Reroute) {
+                continue;
+            }
+            let next_point = match self.next_waypoint(robot) {
+                Some(point) if !self.within_grid(point) => point,
+                _ => continue,
+            };
+
+            log::warn!(
+                "{} would leave the grid at ({}, {}), pausing it at ({}, {})",
+                robot.device_id,
+                next_point.x,
+                next_point.y,
+                robot.x,
+                robot.y
+            );
+            transition(robot, MotionState::Pause, TransitionReason::Bounds);
+        }
     }
 
-    /// `resolve_collision` resolves the collision between two robots we assume both agents stop (Pause) to avoid collision
-    fn resolve_collision(&self) -> (MotionState, MotionState) {
-        (MotionState::Pause, MotionState::Pause)
+    /// `within_grid` checks whether a point lies within the configured grid bounds, inclusive. A
+    /// bound that is not configured does not constrain the point.
+    fn within_grid(&self, point: &Path) -> bool {
+        let config = &self.config;
+
+        !(config.grid_min_x.is_some_and(|min| point.x < min)
+            || config.grid_max_x.is_some_and(|max| point.x > max)
+            || config.grid_min_y.is_some_and(|min| point.y < min)
+            || config.grid_max_y.is_some_and(|max| point.y > max))
     }
 
-    /// `resolve_deadlock` resolves deadlocks in case conflicts occur
-    fn resolve_deadlock(&self, robots: &mut [Robot], conflicts: &[(usize, usize)]) {
-        let mut handled_conflicts: HashSet<(usize, usize)> = HashSet::new();
+    /// `enforce_emergency_stops` pauses every robot reporting an emergency stop, along with the robots
+    /// within `emergency_stop_radius` of it, regardless of the collision results.
+    fn enforce_emergency_stops(&self, robots: &mut [Robot]) {
+        let stops: HashSet<String> = robots
+            .iter()
+            .filter(|robot| robot.emergency_stop)
+            .map(|robot| {
+                log::error!(
+                    "Emergency stop raised by {} at ({}, {})",
+                    robot.device_id,
+                    robot.x,
+                    robot.y
+                );
+                robot.device_id.clone()
+            })
+            .collect();
+
+        Self::pause_around(
+            robots,
+            &stops,
+            self.config.emergency_stop_radius,
+            TransitionReason::EmergencyStop,
+        );
+    }
 
-        for &(first_conflict_idx, second_conflict_idx) in conflicts {
-            if handled_conflicts.contains(&(first_conflict_idx, second_conflict_idx)) {
+    /// `escalate_unacked` pauses robots that stopped acknowledging their states, along with the robots
+    /// within `ack_escalation_radius` of them, since their real position is unknown.
+    pub(crate) fn escalate_unacked(&self, robots: &mut [Robot], unacked: &HashSet<String>) {
+        for device_id in unacked {
+            log::error!(
+                "{} missed its acknowledgement deadline, pausing robots around it",
+                device_id
+            );
+        }
+
+        Self::pause_around(
+            robots,
+            unacked,
+            self.config.ack_escalation_radius,
+            TransitionReason::Unacked,
+        );
+    }
+
+    /// `pause_around` pauses the given devices and every robot within `radius` of one of them, for
+    /// `reason`.
+    fn pause_around(
+        robots: &mut [Robot],
+        device_ids: &HashSet<String>,
+        radius: f64,
+        reason: TransitionReason,
+    ) {
+        let centres: Vec<(f64, f64)> = robots
+            .iter()
+            .filter(|robot| device_ids.contains(&robot.device_id))
+            .map(|robot| (robot.x, robot.y))
+            .collect();
+
+        for robot in robots.iter_mut() {
+            let paused = device_ids.contains(&robot.device_id)
+                || centres
+                    .iter()
+                    .any(|(x, y)| (robot.x - x).hypot(robot.y - y) <= radius);
+
+            if paused {
+                transition(robot, MotionState::Pause, reason);
+            }
+        }
+    }
+
+    /// `honour_commit_points` lets conflicting robots already inside a no-stop region complete their
+    /// crossing instead of pausing there, while the robots they conflict with keep waiting.
+    fn honour_commit_points(&self, robots: &mut [Robot], conflicts: &[(usize, usize)]) {
+        let mut committed: Vec<usize> = Vec::new();
+
+        for (idx, robot) in robots.iter_mut().enumerate() {
+            let in_conflict = conflicts.iter().any(|&(i, j)| i == idx || j == idx);
+            if !in_conflict || robot.state != MotionState::Pause || !self.in_no_stop_region(robot) {
                 continue;
             }
 
-            let robot_a = &robots[first_conflict_idx];
-            let robot_b = &robots[second_conflict_idx];
+            // two committed robots crossing into each other would collide, so the first one wins.
+            let blocked_by_committed = conflicts.iter().any(|&(i, j)| {
+                (i == idx && committed.contains(&j)) || (j == idx && committed.contains(&i))
+            });
+            if blocked_by_committed {
+                continue;
+            }
 
-            let (new_state_i, new_state_j) = if robot_a.state == MotionState::Pause.to_string() {
-                self.update_motion_coordinates(&mut robots[second_conflict_idx]);
+            transition(robot, MotionState::Resume, TransitionReason::CommitPoint);
+            self.update_motion_coordinates(robot);
+            committed.push(idx);
+        }
+    }
 
-                (MotionState::Pause, MotionState::Resume)
-            } else if robot_b.state == MotionState::Pause.to_string() {
-                self.update_motion_coordinates(&mut robots[first_conflict_idx]);
+    /// `enforce_zone_occupancy` holds robots whose next waypoint enters a zone already at its
+    /// maximum occupancy, and releases held robots once the zone has room for them again.
+    fn enforce_zone_occupancy(&self, robots: &mut [Robot]) {
+        let mut occupancy = self.zone_occupancy(robots);
 
-                (MotionState::Resume, MotionState::Pause)
-            } else {
-                self.resolve_collision()
+        for robot in robots.iter_mut() {
+            let next_point = match self.next_waypoint(robot) {
+                Some(next_point) => next_point.clone(),
+                None => continue,
             };
 
-            robots[first_conflict_idx].state = new_state_i.to_string();
-            robots[second_conflict_idx].state = new_state_j.to_string();
+            let entering: Vec<usize> = self
+                .config
+                .zones
+                .iter()
+                .enumerate()
+                .filter(|(_, zone)| {
+                    zone.area.contains(next_point.x, next_point.y)
+                        && !zone.area.contains(robot.x, robot.y)
+                })
+                .map(|(zone_idx, _)| zone_idx)
+                .collect();
+
+            if entering.is_empty() {
+                continue;
+            }
+
+            if entering
+                .iter()
+                .any(|&zone_idx| occupancy[zone_idx].occupancy >= occupancy[zone_idx].max_occupancy)
+            {
+                transition(robot, MotionState::Pause, TransitionReason::Zone);
+                continue;
+            }
+
+            // reserve a place in every zone the robot is entering.
+            for &zone_idx in &entering {
+                occupancy[zone_idx].occupancy += 1;
+            }
+            transition(robot, MotionState::Resume, TransitionReason::Zone);
+        }
+    }
+
+    /// `zone_occupancy` counts the robots currently inside each configured zone.
+    pub(crate) fn zone_occupancy(&self, robots: &[Robot]) -> Vec<ZoneOccupancy> {
+        self.config
+            .zones
+            .iter()
+            .map(|zone| ZoneOccupancy {
+                name: zone.name.clone(),
+                occupancy: robots
+                    .iter()
+                    .filter(|robot| zone.area.contains(robot.x, robot.y))
+                    .count(),
+                max_occupancy: zone.max_occupancy,
+            })
+            .collect()
+    }
+
+    /// `in_no_stop_region` checks if the robot is currently inside one of the configured no-stop regions.
+    fn in_no_stop_region(&self, robot: &Robot) -> bool {
+        self.config
+            .no_stop_regions
+            .iter()
+            .any(|region| region.contains(robot.x, robot.y))
+    }
+
+    /// `check_seed_states` runs collision detection over the states seeded at startup. Depending on
+    /// `seed_collision_policy` it either refuses them or nudges the offending robots along their paths.
+    pub(crate) fn check_seed_states(&self, robots: &mut [Robot]) -> Result<(), String> {
+        let conflicts = self.detect_collisions(robots);
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        let offending_pairs = Self::describe_conflicts(robots, &conflicts);
+
+        match self.config.seed_collision_policy {
+            SeedCollisionPolicy::Refuse => Err(format!(
+                "Seed states are already colliding: {}",
+                offending_pairs
+            )),
+            SeedCollisionPolicy::Nudge => {
+                log::warn!(
+                    "Seed states are already colliding: {}. Nudging robots along their paths.",
+                    offending_pairs
+                );
+
+                // the second robot is nudged first; if its path runs out before the pair is clear,
+                // the first one gets its turn.
+                for &(first_conflict_idx, second_conflict_idx) in &conflicts {
+                    self.nudge_along_path(robots, second_conflict_idx);
+                    if self.will_collision_occur(
+                        &robots[first_conflict_idx],
+                        &robots[second_conflict_idx],
+                    ) {
+                        self.nudge_along_path(robots, first_conflict_idx);
+                    }
+                }
 
-            handled_conflicts.insert((first_conflict_idx, second_conflict_idx));
+                let remaining_conflicts = self.detect_collisions(robots);
+                if remaining_conflicts.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "Seed states are still colliding after nudging: {}",
+                        Self::describe_conflicts(robots, &remaining_conflicts)
+                    ))
+                }
+            }
         }
     }
 
-    /// `update_motion_coordinates` updates the current position if the current state of the robot is set to `Resume`.
-    fn update_motion_coordinates(&self, robot: &mut Robot) {
-        if robot.state == MotionState::Resume.to_string() {
-            if let Some(current_index) = robot
-                .path
+    /// `check_obstacles` refuses robots whose path has a waypoint inside one of the configured static
+    /// obstacles, reporting every offending device and waypoint.
+    pub(crate) fn check_obstacles(&self, robots: &[Robot]) -> Result<(), String> {
+        let violations: Vec<String> = robots
+            .iter()
+            .flat_map(|robot| {
+                robot
+                    .path
+                    .iter()
+                    .filter(|point| {
+                        self.config
+                            .obstacles
+                            .iter()
+                            .any(|obstacle| obstacle.contains(point.x, point.y))
+                    })
+                    .map(move |point| format!("{} at ({}, {})", robot.device_id, point.x, point.y))
+            })
+            .collect();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Paths pass through static obstacles: {}",
+                violations.join(", ")
+            ))
+        }
+    }
+
+    /// `nudge_along_path` advances a robot waypoint by waypoint along its own path until it no longer
+    /// collides with any other robot or its path runs out.
+    fn nudge_along_path(&self, robots: &mut [Robot], idx: usize) {
+        let collides = |robots: &[Robot]| {
+            (0..robots.len())
+                .any(|jdx| jdx != idx && self.will_collision_occur(&robots[idx], &robots[jdx]))
+        };
+
+        // the index advances along with the position, so a path revisiting a point is still
+        // followed to its end, at most one step per waypoint.
+        for _ in 0..robots[idx].path.len() {
+            if !collides(robots) {
+                return;
+            }
+
+            let robot = &mut robots[idx];
+            let next_index = match robot.current_path_index(self.config.position_epsilon) {
+                Some(current_index) if current_index + 1 < robot.path.len() => current_index + 1,
+                _ => return,
+            };
+
+            robot.x = robot.path[next_index].x;
+            robot.y = robot.path[next_index].y;
+            robot.path_index = next_index;
+        }
+    }
+
+    /// `conflicting_pairs` returns the device ids of every pair of robots colliding at current timestamp.
+    pub(crate) fn conflicting_pairs(&self, robots: &[Robot]) -> Vec<(String, String)> {
+        self.detect_collisions(robots)
+            .into_iter()
+            .map(|(i, j)| (robots[i].device_id.clone(), robots[j].device_id.clone()))
+            .collect()
+    }
+
+    /// `near_misses` returns the device ids and separation of every pair of robots closer than
+    /// `near_miss_distance` without colliding, or none when it is not configured.
+    pub(crate) fn near_misses(&self, robots: &[Robot]) -> Vec<(String, String, f64)> {
+        let near_miss_distance = match self.config.near_miss_distance {
+            Some(near_miss_distance) => near_miss_distance,
+            None => return Vec::new(),
+        };
+
+        let mut near_misses = Vec::new();
+        for (i, robot_a) in robots.iter().enumerate() {
+            for robot_b in robots[i + 1..]
                 .iter()
-                .position(|point| point.x == robot.x && point.y == robot.y)
+                .filter(|robot_b| robot_b.zone == robot_a.zone)
             {
-                if let Some(next_point) = robot.path.get(current_index + 1) {
-                    robot.x = next_point.x;
-                    robot.y = next_point.y;
+                let separation = self.min_separation(robot_a, robot_b);
+                // touching footprints already collide.
+                if separation > 0.0 && separation < near_miss_distance {
+                    near_misses.push((
+                        robot_a.device_id.clone(),
+                        robot_b.device_id.clone(),
+                        separation,
+                    ));
                 }
             }
         }
+
+        near_misses
     }
 
-    /// `will_collision_occur` checks if current robot will collide with others.
-    fn will_collision_occur(&self, robot_a: &Robot, robot_b: &Robot) -> bool {
-        if robot_a.device_id == robot_b.device_id {
-            return false;
+    /// `analyze` runs collision detection and one round of resolution over a fleet snapshot,
+    /// reporting the colliding pairs, the resulting deadlock cycles and the resolved states.
+    pub fn analyze(&self, robots: &[Robot]) -> CollisionReport {
+        let colliding_pairs = self.conflicting_pairs(robots);
+        let mut resolved_states = robots.to_vec();
+        self.update_robot_state(&mut resolved_states);
+
+        CollisionReport {
+            deadlock_cycles: Self::deadlock_cycles(&colliding_pairs, &resolved_states),
+            colliding_pairs,
+            resolved_states,
         }
-        if self.collision_check_helper(robot_a, robot_b) {
-            return true;
+    }
+
+    /// `run_to_completion` plays the fleet forward one round per tick, feeding each round's states
+    /// back in as the robots would, until every robot has reached the end of its path or `max_ticks`
+    /// rounds have been played. `before_tick` may adjust the states ahead of every round, the way a
+    /// robot would before publishing. A round bringing the fleet back to a state it was already in
+    /// can never complete, so it is reported as a permanent deadlock instead of being played on.
+    pub(crate) fn run_to_completion(
+        &self,
+        mut robots: Vec<Robot>,
+        max_ticks: usize,
+        mut before_tick: impl FnMut(&mut [Robot]),
+    ) -> SimResult {
+        let fingerprint =
+            |robots: &[Robot]| serde_json::to_string(robots).expect("Could not serialize");
+        let mut seen: HashSet<String> = HashSet::from([fingerprint(&robots)]);
+        let mut snapshots = Vec::new();
+        let mut deadlocked = false;
+
+        while snapshots.len() < max_ticks && !robots.iter().all(Robot::path_complete) {
+            before_tick(&mut robots);
+            self.update_robot_state(&mut robots);
+            snapshots.push(robots.clone());
+
+            if !seen.insert(fingerprint(&robots)) {
+                deadlocked = true;
+                break;
+            }
         }
 
-        false
+        SimResult {
+            completed: robots.iter().all(Robot::path_complete),
+            deadlocked,
+            snapshots,
+        }
     }
 
-    /// `collision_check_helper` checks collision between two robots based on their dimension and
-    /// respective position in the grid.
-    fn collision_check_helper(&self, robot: &Robot, other_robot: &Robot) -> bool {
-        let robot_x_min = robot.x - self.config.width / 2.0;
-        let robot_x_max = robot.x + self.config.width / 2.0;
-        let robot_y_min = robot.y - self.config.height / 2.0;
-        let robot_y_max = robot.y + self.config.height / 2.0;
+    /// `deadlock_cycles` returns the connected groups of conflicting devices that all ended up paused.
+    pub(crate) fn deadlock_cycles(
+        conflicting_pairs: &[(String, String)],
+        updated_states: &[Robot],
+    ) -> Vec<Vec<String>> {
+        let paused: HashSet<&str> = updated_states
+            .iter()
+            .filter(|robot| robot.state == MotionState::Pause)
+            .map(|robot| robot.device_id.as_str())
+            .collect();
+
+        let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (device_a, device_b) in conflicting_pairs {
+            neighbours.entry(device_a).or_default().push(device_b);
+            neighbours.entry(device_b).or_default().push(device_a);
+        }
 
-        let other_robot_x_min = other_robot.x - self.config.width / 2.0;
-        let other_robot_x_max = other_robot.x + self.config.width / 2.0;
-        let other_robot_y_min = other_robot.y - self.config.height / 2.0;
-        let other_robot_y_max = other_robot.y + self.config.height / 2.0;
+        let mut devices: Vec<&str> = neighbours.keys().copied().collect();
+        devices.sort_unstable();
 
-        // adjust the bounding box coordinates based on the robot's rotation
-        let (robot_x_min, robot_y_min) =
-            self.rotate_bounding_box(robot_x_min, robot_y_min, robot.theta, robot.x, robot.y);
-        let (robot_x_max, robot_y_max) =
-            self.rotate_bounding_box(robot_x_max, robot_y_max, robot.theta, robot.x, robot.y);
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut groups = Vec::new();
+        for device in devices {
+            if !visited.insert(device) {
+                continue;
+            }
 
-        let (other_robot_x_min, other_robot_y_min) = self.rotate_bounding_box(
-            other_robot_x_min,
-            other_robot_y_min,
-            other_robot.theta,
-            other_robot.x,
-            other_robot.y,
-        );
-        let (other_robot_x_max, other_robot_y_max) = self.rotate_bounding_box(
-            other_robot_x_max,
-            other_robot_y_max,
-            other_robot.theta,
-            other_robot.x,
-            other_robot.y,
-        );
+            let mut group = vec![device.to_string()];
+            let mut stack = vec![device];
+            while let Some(current) = stack.pop() {
+                for &neighbour in &neighbours[current] {
+                    if visited.insert(neighbour) {
+                        group.push(neighbour.to_string());
+                        stack.push(neighbour);
+                    }
+                }
+            }
 
-        // check if the rotated bounding boxes of the robots intersect
-        if robot_x_max < other_robot_x_min || robot_x_min > other_robot_x_max {
-            return false;
+            if group
+                .iter()
+                .all(|device_id| paused.contains(device_id.as_str()))
+            {
+                group.sort();
+                groups.push(group);
+            }
         }
 
-        if robot_y_max < other_robot_y_min || robot_y_min > other_robot_y_max {
-            return false;
+        groups
+    }
+
+    /// `conflicts_with` returns the device ids of the robots in `fleet` a hypothetical robot state
+    /// would collide with, without modifying anything.
+    pub(crate) fn conflicts_with(&self, candidate: &Robot, fleet: &[Robot]) -> Vec<String> {
+        fleet
+            .iter()
+            .filter(|robot| self.will_collision_occur(candidate, robot))
+            .map(|robot| robot.device_id.clone())
+            .collect()
+    }
+
+    /// `conflict_graph_dot` renders the robots and the conflicts between them as an undirected
+    /// Graphviz graph, one node per device labelled and coloured by its motion state.
+    pub(crate) fn conflict_graph_dot(&self, robots: &[Robot]) -> String {
+        let mut dot = "graph conflicts {\n".to_string();
+        for robot in robots {
+            let color = match robot.state {
+                MotionState::Pause => "orange",
+                MotionState::Resume => "green",
+                MotionState::Reroute => "blue",
+                MotionState::Stopped => "red",
+            };
+            dot.push_str(&format!(
+                "    {:?} [label=\"{}\\n{}\", color={}];\n",
+                robot.device_id,
+                robot.device_id.escape_default(),
+                robot.state,
+                color
+            ));
+        }
+        for (first_device_id, second_device_id) in self.conflicting_pairs(robots) {
+            dot.push_str(&format!(
+                "    {:?} -- {:?};\n",
+                first_device_id, second_device_id
+            ));
         }
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// `describe_conflicts` renders conflicting index pairs as device id pairs for logs and errors.
+    fn describe_conflicts(robots: &[Robot], conflicts: &[(usize, usize)]) -> String {
+        conflicts
+            .iter()
+            .map(|&(i, j)| format!("{} <-> {}", robots[i].device_id, robots[j].device_id))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
 
-        true
+    /// `detect_collisions` detects collission between all robots at current timestamp.
+    fn detect_collisions(&self, robots: &[Robot]) -> Vec<(usize, usize)> {
+        self.detect_collisions_skipping(robots, &HashSet::new(), 0)
     }
 
-    /// `rotate_bounding_box` corrects the point (x, y) around the origin (origin_x, origin_y) by angle `theta`
-    fn rotate_bounding_box(
+    /// `detect_collisions_skipping` detects collisions between all robots except the `skipped` ones,
+    /// now or within `lookahead` steps along their paths.
+    fn detect_collisions_skipping(
         &self,
-        x: f64,
-        y: f64,
-        theta: f64,
-        origin_x: f64,
-        origin_y: f64,
-    ) -> (f64, f64) {
-        let translated_x = x - origin_x;
-        let translated_y = y - origin_y;
-        let rotated_x = translated_x * theta.cos() - translated_y * theta.sin();
-        let rotated_y = translated_x * theta.sin() + translated_y * theta.cos();
-        let final_x = rotated_x + origin_x;
-        let final_y = rotated_y + origin_y;
-
-        (final_x, final_y)
+        robots: &[Robot],
+        skipped: &HashSet<usize>,
+        lookahead: usize,
+    ) -> Vec<(usize, usize)> {
+        let boxes: Vec<(usize, BoundingBox)> = (0..robots.len())
+            .filter(|idx| !skipped.contains(idx))
+            .map(|idx| (idx, self.bounding_box(&robots[idx], lookahead)))
+            .collect();
+        // a cell as large as the largest robot keeps most robots within a few cells.
+        let cell_size = boxes
+            .iter()
+            .map(|&(idx, _)| {
+                let (width, height) = self.footprint(&robots[idx]);
+                width.max(height)
+            })
+            .fold(0.0, f64::max);
+        let cell_size = if cell_size.is_normal() {
+            cell_size
+        } else {
+            1.0
+        };
+
+        let candidates = SpatialGrid::new(&boxes, cell_size).candidate_pairs();
+        // with the `parallel` feature the candidates are checked across threads, in the same order.
+        #[cfg(feature = "parallel")]
+        let candidates = candidates.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let candidates = candidates.into_iter();
+
+        candidates
+            .filter(|&(idx, jdx)| {
+                self.will_collision_occur_within(&robots[idx], &robots[jdx], lookahead)
+            })
+            .collect()
     }
-}
 
-/// [Robot] defines attributes which define the
-/// current state of each robot.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Robot {
-    /// x-coordinate of the robot
-    pub x: f64,
-    /// y-coordinate of the robot
-    pub y: f64,
-    /// angle of inclination to y-axis in radians
-    pub theta: f64,
-    /// loading status of the robot: true | false
-    pub loaded: bool,
-    /// current timestamp of the robot
-    pub timestamp: i64,
-    /// path of the robot
-    pub path: Vec<Path>,
-    /// device id of the robot
-    pub device_id: String,
-    /// state of the robot: resume | pending
-    pub state: String,
-    /// current battery level of the robot
-    pub battery_level: f64,
-}
+    /// `bounding_box` returns an axis-aligned box holding every footprint `robot` may take within
+    /// the next tick, now and at each of the next `lookahead` steps along its path. Robots whose
+    /// boxes don't meet can't collide.
+    fn bounding_box(&self, robot: &Robot, lookahead: usize) -> BoundingBox {
+        let tick_interval = self.config.tick_interval_ms as f64 / 1000.0;
+        let (shift_x, shift_y) = (robot.vx * tick_interval, robot.vy * tick_interval);
+
+        let (min_x, min_y, max_x, max_y) = self
+            .corners(robot)
+            .into_iter()
+            .chain((1..=lookahead).flat_map(|step| self.corners(&self.ahead(robot, step))))
+            .flat_map(|(x, y)| [(x, y), (x + shift_x, y + shift_y)])
+            .fold(
+                (
+                    f64::INFINITY,
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                ),
+                |(min_x, min_y, max_x, max_y), (x, y)| {
+                    (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+                },
+            );
+
+        // padded so rounding never splits robots which only touch.
+        (
+            min_x - BOUNDING_BOX_PADDING,
+            min_y - BOUNDING_BOX_PADDING,
+            max_x + BOUNDING_BOX_PADDING,
+            max_y + BOUNDING_BOX_PADDING,
+        )
+    }
 
-/// [Path] defines attributes which define a
-/// location of the robot.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Path {
-    /// x-coordinate of the robot
-    pub x: f64,
-    /// y-coordinate of the robot
-    pub y: f64,
-    /// angle of inclination to y-axis in radians
-    pub theta: f64,
-}
+    /// `resolve_collision` decides the states of two conflicting robots. A loaded robot keeps moving
+    /// while an empty one pauses; otherwise the configured `resolution_strategy` decides. A robot
+    /// told to yield is moved aside before its state is returned.
+    fn resolve_collision(
+        &self,
+        robots: &mut [Robot],
+        first: usize,
+        second: usize,
+    ) -> (MotionState, MotionState) {
+        let (pause_first, pause_second) = (
+            (MotionState::Pause, MotionState::Resume),
+            (MotionState::Resume, MotionState::Pause),
+        );
 
-/// [MotionState] defines current state of
-/// motion of the robot.
-#[derive(Debug, PartialEq)]
-enum MotionState {
-    Pause,
-    Resume,
-}
+        match (robots[first].loaded, robots[second].loaded) {
+            (true, false) => return pause_second,
+            (false, true) => return pause_first,
+            _ => {}
+        }
 
-// impl for converting enums to string
-impl MotionState {
-    fn to_string(&self) -> String {
-        match self {
-            MotionState::Pause => "Pause".to_string(),
-            MotionState::Resume => "Resume".to_string(),
+        match self.config.resolution_strategy {
+            ResolutionStrategy::PauseBoth => (MotionState::Pause, MotionState::Pause),
+            ResolutionStrategy::PauseLowerPriority => {
+                match robots[first].priority.cmp(&robots[second].priority) {
+                    Ordering::Less => pause_first,
+                    Ordering::Greater => pause_second,
+                    Ordering::Equal => (MotionState::Pause, MotionState::Pause),
+                }
+            }
+            ResolutionStrategy::PauseNewer => {
+                match robots[first].timestamp.cmp(&robots[second].timestamp) {
+                    Ordering::Greater => pause_first,
+                    Ordering::Less => pause_second,
+                    Ordering::Equal => (MotionState::Pause, MotionState::Pause),
+                }
+            }
+            ResolutionStrategy::Yield => {
+                let yielding = match robots[first].priority.cmp(&robots[second].priority) {
+                    Ordering::Less => first,
+                    Ordering::Greater => second,
+                    Ordering::Equal => {
+                        if robots[first].device_id > robots[second].device_id {
+                            first
+                        } else {
+                            second
+                        }
+                    }
+                };
+
+                match self.free_adjacent_cell(robots, yielding) {
+                    Some((x, y)) => {
+                        log::info!(
+                            "{} yields by stepping aside to ({}, {})",
+                            robots[yielding].device_id,
+                            x,
+                            y
+                        );
+                        robots[yielding].x = x;
+                        robots[yielding].y = y;
+                        robots[yielding].smoothed_position = None;
+
+                        if yielding == first {
+                            (MotionState::Reroute, MotionState::Resume)
+                        } else {
+                            (MotionState::Resume, MotionState::Reroute)
+                        }
+                    }
+                    None => (MotionState::Pause, MotionState::Pause),
+                }
+            }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// `free_adjacent_cell` returns the first cell next to the robot at `idx`, one footprint away
+    /// along either axis, where it would collide with no other robot. Cells off the grid, inside a
+    /// static obstacle or inside a no-stop region are never offered.
+    fn free_adjacent_cell(&self, robots: &[Robot], idx: usize) -> Option<(f64, f64)> {
+        let (width, height) = self.footprint(&robots[idx]);
+        let (x, y) = (robots[idx].x, robots[idx].y);
+
+        [(width, 0.0), (-width, 0.0), (0.0, height), (0.0, -height)]
+            .iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(x, y)| {
+                self.within_grid(&Path { x, y, theta: 0.0 })
+                    && !self
+                        .config
+                        .obstacles
+                        .iter()
+                        .chain(&self.config.no_stop_regions)
+                        .any(|region| region.contains(x, y))
+            })
+            .find(|&(x, y)| {
+                let mut ghost = robots[idx].clone();
+                ghost.x = x;
+                ghost.y = y;
+                ghost.smoothed_position = None;
+
+                robots
+                    .iter()
+                    .enumerate()
+                    .all(|(jdx, other)| jdx == idx || !self.will_collision_occur(&ghost, other))
+            })
+    }
 
-    #[test]
-    fn test_collision_monitor_update_robot_state() {
-        let robot1 = Robot {
-            x: 0.0,
-            y: 0.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
-            path: vec![
-                Path {
-                    x: 0.0,
-                    y: 0.0,
-                    theta: 0.0,
-                },
-                Path {
-                    x: 1.0,
-                    y: 1.0,
-                    theta: 0.0,
-                },
+    /// `resolve_deadlock` resolves deadlocks in case conflicts occur. Circular waits among more than
+    /// two robots are broken by pausing their lowest-priority member and letting the rest proceed.
+    /// For the remaining pairs, when both robots are moving the higher-priority one keeps going
+    /// while the other pauses; ties pause both. When both are paused one of them is let go. A
+    /// loaded robot always takes precedence over an empty one, priority deciding between equals.
+    /// Each pair is resolved once whichever way round it is listed, and a robot moved by one pair
+    /// leaves the other pairs it is part of alone, so that it advances at most once.
+    fn resolve_deadlock(&self, robots: &mut [Robot], conflicts: &[(usize, usize)]) {
+        let normalized = |(i, j): (usize, usize)| (i.min(j), i.max(j));
+        let mut handled_conflicts: HashSet<(usize, usize)> = HashSet::new();
+        // robots advanced while resolving this deadlock.
+        let mut moved: HashSet<usize> = HashSet::new();
+        // pairs the resolver actually changed a state or position for.
+        let mut resolved_conflicts = 0;
+        let snapshot = |robot: &Robot| (robot.state, robot.x, robot.y, robot.path_index);
+
+        for cycle in self.wait_for_cycles(robots, conflicts) {
+            if cycle.len() <= 2 {
+                continue;
+            }
+
+            let lowest = cycle
+                .iter()
+                .copied()
+                .min_by(|&i, &j| {
+                    (robots[i].loaded, robots[i].priority)
+                        .cmp(&(robots[j].loaded, robots[j].priority))
+                        .then_with(|| robots[i].device_id.cmp(&robots[j].device_id))
+                })
+                .expect("cycles are never empty");
+            let before: HashMap<usize, _> = cycle
+                .iter()
+                .map(|&idx| (idx, snapshot(&robots[idx])))
+                .collect();
+
+            for &idx in &cycle {
+                if idx == lowest {
+                    transition(
+                        &mut robots[idx],
+                        MotionState::Pause,
+                        TransitionReason::Deadlock,
+                    );
+                } else {
+                    transition(
+                        &mut robots[idx],
+                        MotionState::Resume,
+                        TransitionReason::Deadlock,
+                    );
+                    self.update_motion_coordinates(&mut robots[idx]);
+                    moved.insert(idx);
+                }
+            }
+
+            let cycle_conflicts: HashSet<(usize, usize)> = conflicts
+                .iter()
+                .filter(|(i, j)| cycle.contains(i) && cycle.contains(j))
+                .map(|&pair| normalized(pair))
+                .collect();
+            resolved_conflicts += cycle_conflicts
+                .iter()
+                .filter(|(i, j)| {
+                    snapshot(&robots[*i]) != before[i] || snapshot(&robots[*j]) != before[j]
+                })
+                .count();
+            handled_conflicts.extend(cycle_conflicts);
+        }
+
+        let position = |robot: &Robot| (robot.x, robot.y, robot.path_index);
+
+        for &(first_conflict_idx, second_conflict_idx) in conflicts {
+            if handled_conflicts.contains(&normalized((first_conflict_idx, second_conflict_idx)))
+                || moved.contains(&first_conflict_idx)
+                || moved.contains(&second_conflict_idx)
+            {
+                continue;
+            }
+
+            let before = [
+                position(&robots[first_conflict_idx]),
+                position(&robots[second_conflict_idx]),
+            ];
+            let before_snapshots = [
+                snapshot(&robots[first_conflict_idx]),
+                snapshot(&robots[second_conflict_idx]),
+            ];
+
+            let state_a = robots[first_conflict_idx].state;
+            let state_b = robots[second_conflict_idx].state;
+
+            let (new_state_i, new_state_j) = match (state_a, state_b) {
+                (MotionState::Pause, MotionState::Pause) => {
+                    // a loaded robot goes on, then the higher-priority one, then one committed to
+                    // a no-stop region.
+                    let precedence = |robot: &Robot| {
+                        (robot.loaded, robot.priority, self.in_no_stop_region(robot))
+                    };
+
+                    if precedence(&robots[first_conflict_idx])
+                        > precedence(&robots[second_conflict_idx])
+                    {
+                        transition(
+                            &mut robots[first_conflict_idx],
+                            MotionState::Resume,
+                            TransitionReason::Deadlock,
+                        );
+                        self.update_motion_coordinates(&mut robots[first_conflict_idx]);
+
+                        (MotionState::Resume, MotionState::Pause)
+                    } else {
+                        transition(
+                            &mut robots[second_conflict_idx],
+                            MotionState::Resume,
+                            TransitionReason::Deadlock,
+                        );
+                        self.update_motion_coordinates(&mut robots[second_conflict_idx]);
+
+                        (MotionState::Pause, MotionState::Resume)
+                    }
+                }
+                (MotionState::Pause | MotionState::Stopped, _) => {
+                    self.update_motion_coordinates(&mut robots[second_conflict_idx]);
+
+                    (state_a, MotionState::Resume)
+                }
+                (_, MotionState::Pause | MotionState::Stopped) => {
+                    self.update_motion_coordinates(&mut robots[first_conflict_idx]);
+
+                    (MotionState::Resume, state_b)
+                }
+                (MotionState::Resume | MotionState::Reroute, _) => {
+                    let priority_a = (
+                        robots[first_conflict_idx].loaded,
+                        robots[first_conflict_idx].priority,
+                    );
+                    let priority_b = (
+                        robots[second_conflict_idx].loaded,
+                        robots[second_conflict_idx].priority,
+                    );
+
+                    match priority_a.cmp(&priority_b) {
+                        Ordering::Greater => {
+                            self.update_motion_coordinates(&mut robots[first_conflict_idx]);
+
+                            (MotionState::Resume, MotionState::Pause)
+                        }
+                        Ordering::Less => {
+                            self.update_motion_coordinates(&mut robots[second_conflict_idx]);
+
+                            (MotionState::Pause, MotionState::Resume)
+                        }
+                        Ordering::Equal => {
+                            self.resolve_collision(robots, first_conflict_idx, second_conflict_idx)
+                        }
+                    }
+                }
+            };
+
+            transition(
+                &mut robots[first_conflict_idx],
+                new_state_i,
+                TransitionReason::Deadlock,
+            );
+            transition(
+                &mut robots[second_conflict_idx],
+                new_state_j,
+                TransitionReason::Deadlock,
+            );
+
+            for (idx, before) in [first_conflict_idx, second_conflict_idx]
+                .into_iter()
+                .zip(before)
+            {
+                if position(&robots[idx]) != before {
+                    moved.insert(idx);
+                }
+            }
+            if [
+                snapshot(&robots[first_conflict_idx]),
+                snapshot(&robots[second_conflict_idx]),
+            ] != before_snapshots
+            {
+                resolved_conflicts += 1;
+            }
+            handled_conflicts.insert(normalized((first_conflict_idx, second_conflict_idx)));
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_deadlocks_resolved(resolved_conflicts);
+        }
+    }
+
+    /// `wait_for_cycles` builds a wait-for graph from the pairwise conflicts, with an edge from each
+    /// robot to the robot its next waypoint runs into, and returns its strongly connected components
+    /// of more than one robot: the genuine circular waits.
+    fn wait_for_cycles(&self, robots: &[Robot], conflicts: &[(usize, usize)]) -> Vec<Vec<usize>> {
+        let mut waits_for: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &(i, j) in conflicts {
+            if self.will_collision_occur(&self.ahead(&robots[i], 1), &robots[j]) {
+                waits_for.entry(i).or_default().push(j);
+            }
+            if self.will_collision_occur(&self.ahead(&robots[j], 1), &robots[i]) {
+                waits_for.entry(j).or_default().push(i);
+            }
+        }
+
+        let reachable = |from: usize| {
+            let mut visited: HashSet<usize> = HashSet::new();
+            let mut stack = vec![from];
+            while let Some(current) = stack.pop() {
+                for &next in waits_for.get(&current).into_iter().flatten() {
+                    if visited.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            visited
+        };
+
+        let mut robots_in_cycles: Vec<usize> = waits_for.keys().copied().collect();
+        robots_in_cycles.sort_unstable();
+        let reachable: HashMap<usize, HashSet<usize>> = robots_in_cycles
+            .iter()
+            .map(|&idx| (idx, reachable(idx)))
+            .collect();
+
+        // two robots belong to the same component when each can reach the other.
+        let mut assigned: HashSet<usize> = HashSet::new();
+        let mut cycles = Vec::new();
+        for &idx in &robots_in_cycles {
+            if assigned.contains(&idx) || !reachable[&idx].contains(&idx) {
+                continue;
+            }
+
+            let cycle: Vec<usize> = robots_in_cycles
+                .iter()
+                .copied()
+                .filter(|other| reachable[&idx].contains(other) && reachable[other].contains(&idx))
+                .collect();
+            assigned.extend(cycle.iter().copied());
+            cycles.push(cycle);
+        }
+
+        cycles
+    }
+
+    /// `abort_deadlock` breaks a deadlock declared by the timeout detector by resolving the conflicts
+    /// among the stalled robots.
+    pub(crate) fn abort_deadlock(&self, robots: &mut [Robot], stalled: &[usize]) {
+        let conflicts: Vec<(usize, usize)> = self
+            .detect_collisions(robots)
+            .into_iter()
+            .filter(|(i, j)| stalled.contains(i) && stalled.contains(j))
+            .collect();
+
+        self.resolve_deadlock(robots, &conflicts);
+    }
+
+    /// `update_motion_coordinates` updates the current position if the current state of the robot is set
+    /// to `Resume` or `Reroute`.
+    fn update_motion_coordinates(&self, robot: &mut Robot) {
+        match robot.state {
+            MotionState::Pause | MotionState::Stopped => {}
+            MotionState::Resume | MotionState::Reroute => {
+                match robot.current_path_index(self.config.position_epsilon) {
+                    Some(current_index) => {
+                        robot.path_index = current_index;
+                        // a robot never steps off the grid, whatever it was told.
+                        if let Some(next_point) = robot
+                            .path
+                            .get(current_index + 1)
+                            .filter(|point| self.within_grid(point))
+                        {
+                            robot.x = next_point.x;
+                            robot.y = next_point.y;
+                            robot.path_index = current_index + 1;
+                            // the robot is now where the hub sent it, so drop the stale smoothed position.
+                            robot.smoothed_position = None;
+                        }
+                    }
+                    // a robot off its path first returns to the closest point on it.
+                    None => {
+                        if let Some(nearest_index) = robot.nearest_path_index() {
+                            log::warn!(
+                                "{} at ({}, {}) is off its path, snapping it to point {}",
+                                robot.device_id,
+                                robot.x,
+                                robot.y,
+                                nearest_index
+                            );
+                            robot.x = robot.path[nearest_index].x;
+                            robot.y = robot.path[nearest_index].y;
+                            robot.path_index = nearest_index;
+                            robot.smoothed_position = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `predicted_next` returns where the robot will be next tick if nothing holds it back.
+    pub(crate) fn predicted_next(&self, robot: &Robot) -> Path {
+        let mut ghost = robot.clone();
+        ghost.state = MotionState::Resume;
+        self.update_motion_coordinates(&mut ghost);
+
+        Path {
+            x: ghost.x,
+            y: ghost.y,
+            theta: ghost.theta,
+        }
+    }
+
+    /// `next_waypoint` returns the path point following the robot's current position, if any.
+    fn next_waypoint<'a>(&self, robot: &'a Robot) -> Option<&'a Path> {
+        robot
+            .current_path_index(self.config.position_epsilon)
+            .and_then(|current_index| robot.path.get(current_index + 1))
+    }
+
+    /// `will_collision_occur` checks if current robot will collide with others within the next tick,
+    /// given their reported velocities.
+    fn will_collision_occur(&self, robot_a: &Robot, robot_b: &Robot) -> bool {
+        let tick_interval = self.config.tick_interval_ms as f64 / 1000.0;
+
+        self.will_collision_occur_continuous(robot_a, robot_b, tick_interval)
+    }
+
+    /// `will_collision_occur_continuous` checks if two robots collide at any point of the next
+    /// `interval` seconds while moving at their reported velocities, by sweeping the footprint of
+    /// one along its velocity relative to the other. This catches fast robots which would pass
+    /// through each other between two discrete checks.
+    pub(crate) fn will_collision_occur_continuous(
+        &self,
+        robot_a: &Robot,
+        robot_b: &Robot,
+        interval: f64,
+    ) -> bool {
+        // robots in different zones of the facility never meet.
+        if robot_a.device_id == robot_b.device_id || robot_a.zone != robot_b.zone {
+            return false;
+        }
+
+        let sweep = (
+            (robot_b.vx - robot_a.vx) * interval,
+            (robot_b.vy - robot_a.vy) * interval,
+        );
+
+        self.collision_check_helper(robot_a, robot_b, sweep)
+    }
+
+    /// `will_collision_occur_within` checks if two robots collide now or at any of the next `horizon`
+    /// steps, assuming both keep moving one waypoint per step along their paths.
+    pub(crate) fn will_collision_occur_within(
+        &self,
+        robot_a: &Robot,
+        robot_b: &Robot,
+        horizon: usize,
+    ) -> bool {
+        if self.will_collision_occur(robot_a, robot_b) {
+            return true;
+        }
+
+        (1..=horizon).any(|step| {
+            self.will_collision_occur(&self.ahead(robot_a, step), &self.ahead(robot_b, step))
+        })
+    }
+
+    /// `ahead` returns the robot as it will be `steps` waypoints further along its path. Robots at the
+    /// end of their path, or off it, stay where they are.
+    fn ahead(&self, robot: &Robot, steps: usize) -> Robot {
+        let mut ghost = robot.clone();
+
+        if let Some(current_index) = robot.current_path_index(self.config.position_epsilon) {
+            ghost.path_index = (current_index + steps).min(robot.path.len() - 1);
+            let point = &robot.path[ghost.path_index];
+            ghost.x = point.x;
+            ghost.y = point.y;
+            ghost.theta = point.theta;
+            ghost.smoothed_position = None;
+        }
+
+        ghost
+    }
+
+    /// `collision_check_helper` checks collision between two robots based on their geometry and
+    /// respective position in the grid, while `other_robot` moves by `sweep` relative to `robot`.
+    fn collision_check_helper(
+        &self,
+        robot: &Robot,
+        other_robot: &Robot,
+        sweep: (f64, f64),
+    ) -> bool {
+        geometry::shapes_collide(
+            &Self::pose(robot),
+            self.geometry(robot),
+            &Self::pose(other_robot),
+            self.geometry(other_robot),
+            sweep,
+        )
+    }
+
+    /// `min_separation` returns the gap between the footprints of two robots, as used for collision
+    /// checks, negative when they overlap.
+    pub(crate) fn min_separation(&self, robot_a: &Robot, robot_b: &Robot) -> f64 {
+        geometry::separation(
+            &Self::pose(robot_a),
+            self.geometry(robot_a),
+            &Self::pose(robot_b),
+            self.geometry(robot_b),
+        )
+    }
+
+    /// `corners` returns the four corners of a robot's footprint, rotated by its heading around its
+    /// position. A disk is bounded by its square, which is not rotated.
+    fn corners(&self, robot: &Robot) -> [(f64, f64); 4] {
+        let mut pose = Self::pose(robot);
+        if let Geometry::Circle { .. } = self.geometry(robot) {
+            pose.theta = 0.0;
+        }
+
+        geometry::corners(&pose, self.footprint(robot))
+    }
+
+    /// `pose` returns the position collision checks are run on: the smoothed position when position
+    /// smoothing is enabled, the reported one otherwise.
+    fn pose(robot: &Robot) -> Path {
+        robot.smoothed_position.clone().unwrap_or(Path {
+            x: robot.x,
+            y: robot.y,
+            theta: robot.theta,
+        })
+    }
+
+    /// `footprint` returns the effective (width, height) of a robot, the diameter for a disk.
+    fn footprint(&self, robot: &Robot) -> (f64, f64) {
+        match self.geometry(robot) {
+            Geometry::Rect { width, height } => (width, height),
+            Geometry::Circle { radius } => (2.0 * radius, 2.0 * radius),
+        }
+    }
+
+    /// `geometry` returns the effective geometry of a robot. Robots that don't report one are
+    /// rectangles of their reported dimensions, or of the configured ones. Robots reporting a low
+    /// `position_confidence` get an inflated footprint to leave room for localization error. Every
+    /// footprint then grows by half the `safety_margin` on each side, so that two robots collide
+    /// once they are closer than the margin.
+    fn geometry(&self, robot: &Robot) -> Geometry {
+        let uncertainty = 1.0 - robot.position_confidence.clamp(0.0, 1.0);
+        let inflation = 1.0 + self.config.confidence_inflation * uncertainty;
+        let margin = self.config.safety_margin;
+
+        match robot.geometry {
+            Some(Geometry::Circle { radius }) => Geometry::Circle {
+                radius: radius * inflation + margin / 2.0,
+            },
+            Some(Geometry::Rect { width, height }) => Geometry::Rect {
+                width: width * inflation + margin,
+                height: height * inflation + margin,
+            },
+            None => Geometry::Rect {
+                width: robot.width.unwrap_or(self.config.width) * inflation + margin,
+                height: robot.height.unwrap_or(self.config.height) * inflation + margin,
+            },
+        }
+    }
+}
+
+/// [TransitionReason] defines why the monitor changes the motion state of a robot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TransitionReason {
+    /// the robot conflicts with another robot
+    Collision,
+    /// the robot is caught in a deadlock, or the whole fleet is stuck
+    Deadlock,
+    /// the robot's battery is below the minimum operating level
+    Battery,
+    /// the robot's next waypoint lies outside the grid bounds
+    Bounds,
+    /// the robot has no path to follow
+    Path,
+    /// a zone the robot is entering is full, or has room for it again
+    Zone,
+    /// the robot completes its crossing of a no-stop region
+    CommitPoint,
+    /// the robot raised an emergency stop, or is near one that did
+    EmergencyStop,
+    /// the robot stopped acknowledging its states, or is near one that did
+    Unacked,
+    /// the fleet is frozen for maintenance
+    Freeze,
+}
+
+impl fmt::Display for TransitionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransitionReason::Collision => write!(f, "collision"),
+            TransitionReason::Deadlock => write!(f, "deadlock"),
+            TransitionReason::Battery => write!(f, "battery"),
+            TransitionReason::Bounds => write!(f, "bounds"),
+            TransitionReason::Path => write!(f, "path"),
+            TransitionReason::Zone => write!(f, "zone"),
+            TransitionReason::CommitPoint => write!(f, "commit point"),
+            TransitionReason::EmergencyStop => write!(f, "emergency stop"),
+            TransitionReason::Unacked => write!(f, "unacked"),
+            TransitionReason::Freeze => write!(f, "freeze"),
+        }
+    }
+}
+
+/// `transition` sets the motion state of `robot` to `to`, logging the change along with its
+/// `reason` so that every pause or resume decided by the monitor can be traced back. A robot
+/// already in that state is left as it is, without a log line.
+fn transition(robot: &mut Robot, to: MotionState, reason: TransitionReason) {
+    if let Some(message) = transition_message(robot, to, reason) {
+        log::info!("{}", message);
+        robot.state = to;
+    }
+}
+
+/// `transition_message` describes the transition of `robot` to `to` for the log, or returns none
+/// when the robot is already in that state.
+fn transition_message(robot: &Robot, to: MotionState, reason: TransitionReason) -> Option<String> {
+    if robot.state == to {
+        return None;
+    }
+
+    Some(format!(
+        "Transition of {}: {} -> {} ({})",
+        robot.device_id, robot.state, to, reason
+    ))
+}
+
+/// [CollisionReport] defines the outcome of analyzing a fleet snapshot offline.
+#[derive(Debug, Serialize)]
+pub struct CollisionReport {
+    /// device id pairs colliding in the snapshot
+    pub colliding_pairs: Vec<(String, String)>,
+    /// groups of conflicting devices left paused by the resolution
+    pub deadlock_cycles: Vec<Vec<String>>,
+    /// robot states after one round of resolution
+    pub resolved_states: Vec<Robot>,
+}
+
+/// [SimResult] defines the outcome of running a fleet to completion offline.
+#[derive(Debug)]
+pub(crate) struct SimResult {
+    /// robot states after each tick
+    pub snapshots: Vec<Vec<Robot>>,
+    /// whether every robot reached the end of its path
+    pub completed: bool,
+    /// whether the fleet got stuck in states it can't leave
+    pub deadlocked: bool,
+}
+
+/// [MonitorError] defines why a round of collision monitoring produced no robot states.
+#[derive(Debug, PartialEq)]
+pub(crate) enum MonitorError {
+    /// not every agent has reported its state for the round yet
+    IncompleteAgentSet { received: usize, expected: usize },
+    /// every robot ended the round paused while these devices still collide
+    DeadlockUnresolvable { device_ids: Vec<String> },
+}
+
+impl fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorError::IncompleteAgentSet { received, expected } => write!(
+                f,
+                "Not yet received all agent records: {} of {}",
+                received, expected
+            ),
+            MonitorError::DeadlockUnresolvable { device_ids } => write!(
+                f,
+                "Deadlock between {} cannot be resolved",
+                device_ids.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MonitorError {}
+
+/// [Robot] defines attributes which define the
+/// current state of each robot.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Robot {
+    /// x-coordinate of the robot
+    pub x: f64,
+    /// y-coordinate of the robot
+    pub y: f64,
+    /// angle of inclination to y-axis in radians
+    pub theta: f64,
+    /// loading status of the robot: true | false
+    pub loaded: bool,
+    /// current timestamp of the robot
+    pub timestamp: i64,
+    /// path of the robot
+    pub path: Vec<Path>,
+    /// index of the robot's current point on its path
+    #[serde(default)]
+    pub path_index: usize,
+    /// device id of the robot
+    pub device_id: String,
+    /// state of the robot: pause | resume | reroute | stopped, as a name or a numeric code
+    #[serde(deserialize_with = "deserialize_state")]
+    pub state: MotionState,
+    /// current battery level of the robot
+    pub battery_level: f64,
+    /// width of the robot, if it differs from the configured one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f64>,
+    /// height of the robot, if it differs from the configured one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f64>,
+    /// shape of the robot's footprint, a rectangle of `width` and `height` when not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<Geometry>,
+    /// localization quality reported by the robot: 0.0 (unknown) to 1.0 (exact)
+    #[serde(default = "default_position_confidence")]
+    pub position_confidence: f64,
+    /// publish interval in milliseconds suggested by the hub under load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_publish_interval_ms: Option<u64>,
+    /// reporting interval of the robot in milliseconds, derived by the monitor from its timestamps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_interval_ms: Option<u64>,
+    /// version of the client software running on the robot
+    #[serde(default)]
+    pub client_version: Option<String>,
+    /// round of the state sent by the monitor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u64>,
+    /// round of the last state the robot applied, acknowledging it to the monitor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acked_round: Option<u64>,
+    /// batch of the monitor the state was last resolved in, 0 if never
+    #[serde(default)]
+    pub tick: u64,
+    /// set while the robot has detected a local hazard and the fleet must stop around it
+    #[serde(default)]
+    pub emergency_stop: bool,
+    /// velocity of the robot along the x-axis, in units per second
+    #[serde(default)]
+    pub vx: f64,
+    /// velocity of the robot along the y-axis, in units per second
+    #[serde(default)]
+    pub vy: f64,
+    /// priority of the robot in deadlocks: the higher one keeps moving
+    #[serde(default)]
+    pub priority: u32,
+    /// position smoothed by the hub, used for collision checks when smoothing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothed_position: Option<Path>,
+    /// zone of the facility the robot operates in, set by the monitor from the queue it published to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+}
+
+fn default_position_confidence() -> f64 {
+    1.0
+}
+
+impl Robot {
+    /// `current_path_index` returns the index of the robot's current point on its path:
+    /// `path_index` while it matches the robot's position, otherwise the first point at the robot's
+    /// position, for states which do not track their index. Coordinates less than `epsilon` apart
+    /// match, so rounding noise in reported positions does not take a robot off its path. Robots
+    /// off their path have none.
+    pub(crate) fn current_path_index(&self, epsilon: f64) -> Option<usize> {
+        let at_position = |point: &Path| self.is_at(point, epsilon);
+
+        match self.path.get(self.path_index) {
+            Some(point) if at_position(point) => Some(self.path_index),
+            _ => self.path.iter().position(at_position),
+        }
+    }
+
+    /// `is_at` checks if the robot is at `point`, both coordinates less than `epsilon` apart.
+    pub(crate) fn is_at(&self, point: &Path, epsilon: f64) -> bool {
+        (point.x - self.x).abs() < epsilon && (point.y - self.y).abs() < epsilon
+    }
+
+    /// `nearest_path_index` returns the index of the path point closest to the robot's position,
+    /// or none for an empty path.
+    pub(crate) fn nearest_path_index(&self) -> Option<usize> {
+        let distance = |point: &Path| (point.x - self.x).hypot(point.y - self.y);
+
+        self.path
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, _)| index)
+    }
+
+    /// `scale_lengths` multiplies every length of the state by `scale`: its position, path,
+    /// velocity, dimensions and smoothed position. It converts states between the units robots
+    /// report in and the meters the monitor works in.
+    pub(crate) fn scale_lengths(&mut self, scale: f64) {
+        for point in self.path.iter_mut().chain(self.smoothed_position.as_mut()) {
+            point.x *= scale;
+            point.y *= scale;
+        }
+        for length in [&mut self.x, &mut self.y, &mut self.vx, &mut self.vy] {
+            *length *= scale;
+        }
+        for length in [&mut self.width, &mut self.height].into_iter().flatten() {
+            *length *= scale;
+        }
+
+        match &mut self.geometry {
+            Some(Geometry::Rect { width, height }) => {
+                *width *= scale;
+                *height *= scale;
+            }
+            Some(Geometry::Circle { radius }) => *radius *= scale,
+            None => {}
+        }
+    }
+
+    /// `path_complete` returns whether the robot has reached the last point of its path.
+    pub(crate) fn path_complete(&self) -> bool {
+        self.path_index + 1 >= self.path.len()
+    }
+
+    /// `path_progress` returns the fraction of the path completed, from 0.0 to 1.0.
+    pub(crate) fn path_progress(&self) -> f64 {
+        if self.path_complete() {
+            return 1.0;
+        }
+
+        self.path_index as f64 / (self.path.len() - 1) as f64
+    }
+
+    /// `to_value` converts the robot state to a JSON value, encoding its motion state as a numeric
+    /// code when `compact_state` is set.
+    pub(crate) fn to_value(&self, compact_state: bool) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        if compact_state {
+            value["state"] = self.state.code().into();
+        }
+
+        Ok(value)
+    }
+
+    /// `to_json` serializes the robot state, encoding its motion state as a numeric code when
+    /// `compact_state` is set.
+    pub(crate) fn to_json(&self, compact_state: bool) -> serde_json::Result<String> {
+        Ok(self.to_value(compact_state)?.to_string())
+    }
+}
+
+/// `deserialize_state` accepts a motion state either by name or by its numeric code.
+fn deserialize_state<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MotionState, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum EncodedState {
+        Name(MotionState),
+        Code(u8),
+    }
+
+    match EncodedState::deserialize(deserializer)? {
+        EncodedState::Name(state) => Ok(state),
+        EncodedState::Code(code) => MotionState::from_code(code)
+            .ok_or_else(|| de::Error::custom(format!("unknown motion state code {}", code))),
+    }
+}
+
+impl Default for Robot {
+    fn default() -> Self {
+        Robot {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: Vec::new(),
+            path_index: 0,
+            device_id: String::new(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            width: None,
+            height: None,
+            geometry: None,
+            position_confidence: default_position_confidence(),
+            suggested_publish_interval_ms: None,
+            expected_interval_ms: None,
+            client_version: None,
+            round: None,
+            acked_round: None,
+            tick: 0,
+            emergency_stop: false,
+            vx: 0.0,
+            vy: 0.0,
+            priority: 0,
+            smoothed_position: None,
+            zone: None,
+        }
+    }
+}
+
+/// [Path] defines attributes which define a
+/// location of the robot.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Path {
+    /// x-coordinate of the robot
+    pub x: f64,
+    /// y-coordinate of the robot
+    pub y: f64,
+    /// angle of inclination to y-axis in radians
+    pub theta: f64,
+}
+
+/// [Geometry] defines the shape of a robot's footprint, centred on its position.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase")]
+pub enum Geometry {
+    /// a rectangle turned with the robot's heading
+    Rect { width: f64, height: f64 },
+    /// a disk, the same whichever way the robot faces
+    Circle { radius: f64 },
+}
+
+/// [ZoneOccupancy] defines how many robots are currently inside a zone.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ZoneOccupancy {
+    /// name of the zone
+    pub name: String,
+    /// number of robots inside the zone
+    pub occupancy: usize,
+    /// maximum number of robots allowed inside the zone
+    pub max_occupancy: usize,
+}
+
+/// [MotionState] defines current state of
+/// motion of the robot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MotionState {
+    /// the robot holds its position until the monitor resumes it
+    #[serde(alias = "Pause")]
+    Pause,
+    /// the robot moves to the next waypoint of its path
+    #[serde(alias = "Resume")]
+    Resume,
+    /// the robot moves along a path it has been given in place of its original one
+    Reroute,
+    /// the robot is out of service and does not move
+    Stopped,
+}
+
+impl MotionState {
+    /// `code` returns the numeric code of the state used by the compact encoding.
+    pub(crate) fn code(&self) -> u8 {
+        match self {
+            MotionState::Pause => 0,
+            MotionState::Resume => 1,
+            MotionState::Reroute => 2,
+            MotionState::Stopped => 3,
+        }
+    }
+
+    /// `from_code` returns the state with the given numeric code, if any.
+    pub(crate) fn from_code(code: u8) -> Option<MotionState> {
+        match code {
+            0 => Some(MotionState::Pause),
+            1 => Some(MotionState::Resume),
+            2 => Some(MotionState::Reroute),
+            3 => Some(MotionState::Stopped),
+            _ => None,
+        }
+    }
+}
+
+// impl for converting enums to string
+impl fmt::Display for MotionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MotionState::Pause => write!(f, "pause"),
+            MotionState::Resume => write!(f, "resume"),
+            MotionState::Reroute => write!(f, "reroute"),
+            MotionState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{test_config, Region, WireFormat, Zone};
+
+    #[test]
+    fn test_collision_monitor_logs_transitions() {
+        let mut robot = Robot {
+            device_id: "robot1".to_string(),
+            state: MotionState::Resume,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            transition_message(&robot, MotionState::Pause, TransitionReason::Battery).as_deref(),
+            Some("Transition of robot1: resume -> pause (battery)")
+        );
+        transition(&mut robot, MotionState::Pause, TransitionReason::Battery);
+        assert_eq!(robot.state, MotionState::Pause);
+
+        // staying in the same state is not a transition.
+        assert_eq!(
+            transition_message(&robot, MotionState::Pause, TransitionReason::Collision),
+            None
+        );
+    }
+
+    #[test]
+    fn test_collision_monitor_update_robot_state() {
+        let robot1 = Robot {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 2.0,
+                    y: 2.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot1".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot2 = Robot {
+            x: 10.0,
+            y: 10.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 10.0,
+                    y: 10.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 20.0,
+                    y: 20.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 30.0,
+                    y: 30.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot2".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot3 = Robot {
+            x: 50.0,
+            y: 50.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 50.0,
+                    y: 50.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 60.0,
+                    y: 60.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 70.0,
+                    y: 70.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot3".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot4 = Robot {
+            x: 3.0,
+            y: 3.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 3.0,
+                    y: 3.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 4.0,
+                    y: 4.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot4".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robots = vec![
+            robot1.clone(),
+            robot2.clone(),
+            robot3.clone(),
+            robot4.clone(),
+        ];
+        let config = CollisionMonitorConfig {
+            num_agents: Some(3),
+            ..test_config()
+        };
+
+        let collision_monitor = CollisionMonitor::new(config);
+
+        let mut updated_robots = robots.clone();
+        collision_monitor.update_robot_state(&mut updated_robots);
+
+        assert_eq!(updated_robots[0].state, MotionState::Resume);
+        assert_eq!(updated_robots[0].x, 1.0);
+        assert_eq!(updated_robots[0].y, 1.0);
+
+        assert_eq!(updated_robots[1].state, MotionState::Resume);
+        assert_eq!(updated_robots[1].x, 20.0);
+        assert_eq!(updated_robots[1].y, 20.0);
+
+        assert_eq!(updated_robots[2].state, MotionState::Resume);
+        assert_eq!(updated_robots[2].x, 60.0);
+        assert_eq!(updated_robots[2].y, 60.0);
+
+        assert_eq!(updated_robots[3].state, MotionState::Resume);
+        assert_eq!(updated_robots[3].x, 4.0);
+        assert_eq!(updated_robots[3].y, 4.0);
+    }
+
+    #[test]
+    fn test_collision_monitor_detect_collisions() {
+        // Create 3 robots for testing
+        let robot1 = Robot {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot1".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot2 = Robot {
+            x: 1.0,
+            y: 1.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 2.0,
+                    y: 2.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot2".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot3 = Robot {
+            x: 2.0,
+            y: 2.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 2.0,
+                    y: 2.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 3.0,
+                    y: 3.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot3".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robots = vec![robot1.clone(), robot2.clone(), robot3.clone()];
+        let config = CollisionMonitorConfig {
+            num_agents: Some(3),
+            ..test_config()
+        };
+        let collision_monitor = CollisionMonitor::new(config);
+
+        let conflicts = collision_monitor.detect_collisions(&robots);
+
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0], (0, 1));
+        assert_eq!(conflicts[1], (1, 2));
+    }
+
+    #[test]
+    fn test_collision_monitor_resolve_deadlock() {
+        let robot1 = Robot {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot1".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot2 = Robot {
+            x: 1.0,
+            y: 1.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot2".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robots = vec![robot1.clone(), robot2.clone()];
+        let config = CollisionMonitorConfig {
+            num_agents: Some(2),
+            ..test_config()
+        };
+
+        let collision_monitor = CollisionMonitor::new(config);
+
+        let conflicts = vec![(0, 1)];
+        collision_monitor.resolve_deadlock(&mut robots.clone(), &conflicts);
+
+        assert_eq!(robots[0].state, MotionState::Resume);
+        assert_eq!(robots[1].state, MotionState::Resume);
+    }
+
+    #[test]
+    fn test_collision_monitor_resolve_deadlock_counts_changed_pairs() {
+        let robot = |device_id: &str, x: f64, path: &[f64], state: MotionState| Robot {
+            x,
+            path: path
+                .iter()
+                .map(|&x| Path {
+                    x,
+                    y: 0.0,
+                    theta: 0.0,
+                })
+                .collect(),
+            device_id: device_id.to_string(),
+            state,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+        let metrics = Arc::new(Metrics::default());
+        let collision_monitor =
+            CollisionMonitor::new(test_config()).with_metrics(Arc::clone(&metrics));
+
+        // robot2 already runs and has nowhere left to go, so the pair is left as it was.
+        let mut stuck_robots = vec![
+            robot("robot1", 0.0, &[0.0, -1.0], MotionState::Pause),
+            robot("robot2", 0.5, &[0.5], MotionState::Resume),
+        ];
+        collision_monitor.resolve_deadlock(&mut stuck_robots, &[(0, 1)]);
+        assert!(metrics.render().contains("deadlocks_resolved_total 0\n"));
+
+        // two paused robots get one of them going again.
+        let mut paused_robots = vec![
+            robot("robot1", 0.0, &[0.0, -1.0], MotionState::Pause),
+            robot("robot2", 0.5, &[0.5, 1.0], MotionState::Pause),
+        ];
+        collision_monitor.resolve_deadlock(&mut paused_robots, &[(0, 1)]);
+        assert!(metrics.render().contains("deadlocks_resolved_total 1\n"));
+    }
+
+    #[test]
+    fn test_collision_monitor_resolve_deadlock_by_priority() {
+        let robot = |device_id: &str, priority: u32, from: f64, to: f64| Robot {
+            x: from,
+            y: from,
+            path: vec![
+                Path {
+                    x: from,
+                    y: from,
+                    theta: 0.0,
+                },
+                Path {
+                    x: to,
+                    y: to,
+                    theta: 0.0,
+                },
+            ],
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            priority,
+            ..Default::default()
+        };
+
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let conflicts = vec![(0, 1)];
+
+        let mut robots = vec![robot("robot1", 10, 0.0, 1.0), robot("robot2", 1, 1.0, 0.0)];
+        collision_monitor.resolve_deadlock(&mut robots, &conflicts);
+        assert_eq!(robots[0].state, MotionState::Resume);
+        assert_eq!(robots[0].x, 1.0);
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!(robots[1].x, 1.0);
+
+        let mut robots = vec![robot("robot1", 1, 0.0, 1.0), robot("robot2", 10, 1.0, 0.0)];
+        collision_monitor.resolve_deadlock(&mut robots, &conflicts);
+        assert_eq!(robots[0].state, MotionState::Pause);
+        assert_eq!(robots[0].x, 0.0);
+        assert_eq!(robots[1].state, MotionState::Resume);
+        assert_eq!(robots[1].x, 0.0);
+
+        let mut robots = vec![robot("robot1", 5, 0.0, 1.0), robot("robot2", 5, 1.0, 0.0)];
+        collision_monitor.resolve_deadlock(&mut robots, &conflicts);
+        assert_eq!(robots[0].state, MotionState::Pause);
+        assert_eq!(robots[1].state, MotionState::Pause);
+    }
+
+    #[test]
+    fn test_collision_monitor_will_collision_occur() {
+        // Create 2 robots for testing
+        let robot1 = Robot {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot1".to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+
+        let robot2 = Robot {
+            x: 1.0,
+            y: 1.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![
+                Path {
+                    x: 1.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
                 Path {
                     x: 2.0,
                     y: 2.0,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot1".to_string(),
-            state: MotionState::Resume.to_string(),
+            device_id: "robot2".to_string(),
+            state: MotionState::Resume,
             battery_level: 100.0,
+            ..Default::default()
         };
 
-        let robot2 = Robot {
-            x: 10.0,
-            y: 10.0,
+        let config = CollisionMonitorConfig {
+            num_agents: Some(2),
+            ..test_config()
+        };
+
+        let collision_monitor = CollisionMonitor::new(config);
+
+        let collision_occurs = collision_monitor.will_collision_occur(&robot1, &robot2);
+
+        assert!(collision_occurs);
+    }
+
+    #[test]
+    fn test_collision_monitor_check_seed_states() {
+        let seed_robot = |device_id: &str, x: f64, path: Vec<Path>| Robot {
+            x,
+            y: 0.0,
             theta: 0.0,
             loaded: false,
             timestamp: 0,
+            path,
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            battery_level: 100.0,
+            ..Default::default()
+        };
+        let waypoint = |x: f64| Path {
+            x,
+            y: 0.0,
+            theta: 0.0,
+        };
+
+        // robot1 and robot2 are seeded on top of each other.
+        let robots = vec![
+            seed_robot("robot1", 0.0, vec![waypoint(0.0), waypoint(-1.0)]),
+            seed_robot(
+                "robot2",
+                0.5,
+                vec![waypoint(0.5), waypoint(1.0), waypoint(2.0), waypoint(3.0)],
+            ),
+        ];
+
+        let refusing_monitor = CollisionMonitor::new(test_config());
+        let error = refusing_monitor
+            .check_seed_states(&mut robots.clone())
+            .unwrap_err();
+        assert!(error.contains("robot1 <-> robot2"));
+
+        let nudging_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            seed_collision_policy: SeedCollisionPolicy::Nudge,
+            ..test_config()
+        });
+        let mut nudged_robots = robots.clone();
+        assert!(nudging_monitor
+            .check_seed_states(&mut nudged_robots)
+            .is_ok());
+        assert_eq!(nudged_robots[0].x, 0.0);
+        assert_eq!(nudged_robots[1].x, 2.0);
+        assert_eq!(nudged_robots[1].path_index, 2);
+
+        // a path coming back to where it started is followed past the revisited point.
+        let mut revisiting_robots = vec![
+            robots[0].clone(),
+            seed_robot(
+                "robot2",
+                0.5,
+                vec![waypoint(0.5), waypoint(1.0), waypoint(0.5), waypoint(3.0)],
+            ),
+        ];
+        assert!(nudging_monitor
+            .check_seed_states(&mut revisiting_robots)
+            .is_ok());
+        assert_eq!(
+            (revisiting_robots[1].x, revisiting_robots[1].path_index),
+            (3.0, 3)
+        );
+
+        // the first robot is nudged when the second one has nowhere to go.
+        let mut blocked_robots = vec![
+            seed_robot("robot1", 0.0, vec![waypoint(0.0), waypoint(-1.0)]),
+            seed_robot("robot2", 0.5, vec![waypoint(0.5)]),
+        ];
+        assert!(nudging_monitor
+            .check_seed_states(&mut blocked_robots)
+            .is_ok());
+        assert_eq!(
+            (blocked_robots[0].x, blocked_robots[0].path_index),
+            (-1.0, 1)
+        );
+        assert_eq!(blocked_robots[1].x, 0.5);
+
+        // a robot that cannot be nudged clear is still refused.
+        let mut stuck_robots = vec![
+            seed_robot("robot1", 0.0, vec![waypoint(0.0)]),
+            seed_robot("robot2", 0.5, vec![waypoint(0.5)]),
+        ];
+        assert!(nudging_monitor
+            .check_seed_states(&mut stuck_robots)
+            .is_err());
+    }
+
+    #[test]
+    fn test_collision_monitor_low_confidence_footprint() {
+        let robot1 = Robot {
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        let robot2 = Robot {
+            x: 1.4,
+            device_id: "robot2".to_string(),
+            ..Default::default()
+        };
+
+        let collision_monitor = CollisionMonitor::new(test_config());
+
+        assert_eq!(collision_monitor.footprint(&robot1), (1.0, 1.0));
+        assert!(!collision_monitor.will_collision_occur(&robot1, &robot2));
+
+        let poorly_localized_robot1 = Robot {
+            position_confidence: 0.0,
+            ..robot1
+        };
+
+        assert_eq!(
+            collision_monitor.footprint(&poorly_localized_robot1),
+            (2.0, 2.0)
+        );
+        assert!(collision_monitor.will_collision_occur(&poorly_localized_robot1, &robot2));
+    }
+
+    #[test]
+    fn test_collision_monitor_commit_point() {
+        let crossing_robot = Robot {
+            x: 0.0,
+            y: 0.0,
             path: vec![
                 Path {
-                    x: 10.0,
-                    y: 10.0,
+                    x: 0.0,
+                    y: 0.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 20.0,
-                    y: 20.0,
+                    x: 0.0,
+                    y: 1.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+
+        let waiting_robot = Robot {
+            x: 0.5,
+            y: -0.5,
+            path: vec![
+                Path {
+                    x: 0.5,
+                    y: -0.5,
                     theta: 0.0,
                 },
                 Path {
-                    x: 30.0,
-                    y: 30.0,
+                    x: 0.5,
+                    y: 0.5,
                     theta: 0.0,
                 },
             ],
             device_id: "robot2".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            ..Default::default()
         };
 
-        let robot3 = Robot {
-            x: 50.0,
-            y: 50.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+        let config = CollisionMonitorConfig {
+            no_stop_regions: vec![Region {
+                x_min: -0.25,
+                y_min: -0.25,
+                x_max: 0.25,
+                y_max: 0.25,
+            }],
+            ..test_config()
+        };
+        let collision_monitor = CollisionMonitor::new(config);
+
+        let mut robots = vec![crossing_robot, waiting_robot];
+        collision_monitor.update_robot_state(&mut robots);
+
+        assert_eq!(robots[0].state, MotionState::Resume);
+        assert_eq!(robots[0].y, 1.0);
+
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!(robots[1].y, -0.5);
+    }
+
+    #[test]
+    fn test_collision_monitor_check_client_version() {
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            min_client_version: Some("0.2.0".to_string()),
+            ..test_config()
+        });
+
+        let robot_with_version = |version: Option<&str>| Robot {
+            device_id: "robot1".to_string(),
+            client_version: version.map(|version| version.to_string()),
+            ..Default::default()
+        };
+
+        assert!(collision_monitor
+            .check_client_version(&robot_with_version(Some("0.1.9")))
+            .is_err());
+        assert!(collision_monitor
+            .check_client_version(&robot_with_version(None))
+            .is_err());
+        assert!(collision_monitor
+            .check_client_version(&robot_with_version(Some("0.2.0")))
+            .is_ok());
+        assert!(collision_monitor
+            .check_client_version(&robot_with_version(Some("0.10.1")))
+            .is_ok());
+
+        // pre-release and build suffixes are ignored, anything else unparsable is refused.
+        assert!(collision_monitor
+            .check_client_version(&robot_with_version(Some("v0.2.0-rc.1+build.5")))
+            .is_ok());
+        assert!(collision_monitor
+            .check_client_version(&robot_with_version(Some("0.1.9-rc.1")))
+            .is_err());
+        let error = collision_monitor
+            .check_client_version(&robot_with_version(Some("0.x.1")))
+            .unwrap_err();
+        assert!(error.contains("is not a valid version"));
+
+        // without a minimum every client is accepted.
+        assert!(CollisionMonitor::new(test_config())
+            .check_client_version(&robot_with_version(None))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_collision_monitor_zone_occupancy() {
+        let robot = |device_id: &str, x: f64, next_x: f64| Robot {
+            x,
+            y: 5.0,
             path: vec![
                 Path {
-                    x: 50.0,
-                    y: 50.0,
+                    x,
+                    y: 5.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 60.0,
-                    y: 60.0,
+                    x: next_x,
+                    y: 5.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+
+        let config = CollisionMonitorConfig {
+            zones: vec![Zone {
+                name: "fragile_floor".to_string(),
+                area: Region {
+                    x_min: 0.0,
+                    y_min: 0.0,
+                    x_max: 10.0,
+                    y_max: 10.0,
+                },
+                max_occupancy: 1,
+            }],
+            ..test_config()
+        };
+        let collision_monitor = CollisionMonitor::new(config);
+
+        // robot1 is leaving the full zone while robot2 wants to enter it.
+        let mut robots = vec![robot("robot1", 5.0, 15.0), robot("robot2", -5.0, 2.0)];
+        assert_eq!(collision_monitor.zone_occupancy(&robots)[0].occupancy, 1);
+
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!(robots[0].x, 15.0);
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!(robots[1].x, -5.0);
+
+        // once robot1 has left, robot2 is admitted.
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!(robots[1].state, MotionState::Resume);
+        assert_eq!(robots[1].x, 2.0);
+        assert_eq!(collision_monitor.zone_occupancy(&robots)[0].occupancy, 1);
+    }
+
+    #[test]
+    fn test_collision_monitor_emergency_stop() {
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            emergency_stop_radius: 5.0,
+            ..test_config()
+        });
+
+        let robot = |device_id: &str, x: f64| Robot {
+            x,
+            path: vec![
+                Path {
+                    x,
+                    y: 0.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 70.0,
-                    y: 70.0,
+                    x,
+                    y: 1.0,
                     theta: 0.0,
                 },
             ],
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+
+        let mut robots = vec![
+            robot("robot1", 0.0),
+            robot("robot2", 4.0),
+            robot("robot3", 20.0),
+        ];
+        robots[0].emergency_stop = true;
+
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!(robots[0].state, MotionState::Pause);
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!(robots[2].state, MotionState::Resume);
+
+        // clearing the flag lets the fleet move again.
+        robots[0].emergency_stop = false;
+        for robot in robots.iter_mut() {
+            robot.state = MotionState::Resume;
+        }
+        collision_monitor.update_robot_state(&mut robots);
+        assert!(robots
+            .iter()
+            .all(|robot| robot.state == MotionState::Resume));
+    }
+
+    #[test]
+    fn test_collision_monitor_check_obstacles() {
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            obstacles: vec![Region {
+                x_min: 4.0,
+                y_min: -1.0,
+                x_max: 6.0,
+                y_max: 1.0,
+            }],
+            ..test_config()
+        });
+
+        let robot = |device_id: &str, y: f64| Robot {
+            y,
+            path: (0..10)
+                .map(|x| Path {
+                    x: x as f64,
+                    y,
+                    theta: 0.0,
+                })
+                .collect(),
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+
+        assert!(collision_monitor
+            .check_obstacles(&[robot("robot1", 5.0)])
+            .is_ok());
+        assert_eq!(
+            collision_monitor.check_obstacles(&[robot("robot1", 5.0), robot("robot2", 0.0)]),
+            Err(
+                "Paths pass through static obstacles: robot2 at (4, 0), robot2 at (5, 0), robot2 at (6, 0)"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_collision_monitor_per_robot_dimensions() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+
+        let robot = |device_id: &str, x: f64, width: Option<f64>| Robot {
+            x,
+            device_id: device_id.to_string(),
+            width,
+            height: width,
+            ..Default::default()
+        };
+
+        // two small AMRs 2.0 apart are clear of each other.
+        let amr = robot("amr", 0.0, Some(0.5));
+        assert!(!collision_monitor.will_collision_occur(&amr, &robot("amr2", 2.0, Some(0.5))));
+
+        // a forklift at the same distance overlaps the AMR.
+        let forklift = robot("forklift", 2.0, Some(3.5));
+        assert!(collision_monitor.will_collision_occur(&amr, &forklift));
+
+        // robots that don't report dimensions fall back to the configured 1.0 x 1.0.
+        assert!(!collision_monitor.will_collision_occur(&amr, &robot("default", 2.0, None)));
+        assert!(collision_monitor.will_collision_occur(&amr, &robot("default", 0.7, None)));
+    }
+
+    #[test]
+    fn test_collision_monitor_zones_never_conflict() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+
+        let robot = |device_id: &str, zone: &str| Robot {
+            x: 5.0,
+            y: 5.0,
+            device_id: device_id.to_string(),
+            zone: Some(zone.to_string()),
+            ..Default::default()
+        };
+
+        // robots at the same coordinates of different zones are on different floors.
+        let robots = [robot("robot1", "floor-1"), robot("robot2", "floor-2")];
+        assert!(collision_monitor.conflicting_pairs(&robots).is_empty());
+
+        let robots = [
+            robot("robot1", "floor-1"),
+            robot("robot2", "floor-2"),
+            robot("robot3", "floor-1"),
+        ];
+        assert_eq!(
+            collision_monitor.conflicting_pairs(&robots),
+            vec![("robot1".to_string(), "robot3".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_collision_monitor_safety_margin() {
+        let robot = |device_id: &str, x: f64| Robot {
+            x,
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+        // two 1.0 x 1.0 robots with a gap of 0.5 between them.
+        let (robot_a, robot_b) = (robot("robot1", 0.0), robot("robot2", 1.5));
+
+        let without_margin = CollisionMonitor::new(test_config());
+        assert!(!without_margin.will_collision_occur(&robot_a, &robot_b));
+
+        let with_margin = CollisionMonitor::new(CollisionMonitorConfig {
+            safety_margin: 1.0,
+            ..test_config()
+        });
+        assert!(with_margin.will_collision_occur(&robot_a, &robot_b));
+        assert_eq!(
+            with_margin.conflicting_pairs(&[robot_a, robot_b]),
+            vec![("robot1".to_string(), "robot2".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_collision_monitor_rotated_overlap() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let quarter_turn = std::f64::consts::FRAC_PI_4;
+
+        let robot = |device_id: &str, x: f64, y: f64, theta: f64| Robot {
+            x,
+            y,
+            theta,
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+
+        // two 1.0 x 1.0 robots rotated 45 degrees reach 0.707 to either side, so their tips overlap
+        // 1.3 apart. Rotating only the min and max corners collapsed their x extent and missed this.
+        assert!(collision_monitor.will_collision_occur(
+            &robot("robot1", 0.0, 0.0, quarter_turn),
+            &robot("robot2", 1.3, 0.0, quarter_turn)
+        ));
+        assert!(!collision_monitor.will_collision_occur(
+            &robot("robot1", 0.0, 0.0, quarter_turn),
+            &robot("robot2", 1.5, 0.0, quarter_turn)
+        ));
+
+        // side by side along the diagonal, the rotated edges keep them apart.
+        assert!(!collision_monitor.will_collision_occur(
+            &robot("robot1", 0.0, 0.0, quarter_turn),
+            &robot("robot2", 0.75, 0.75, quarter_turn)
+        ));
+
+        // a rotated robot against an axis-aligned one: the tip reaches 0.707, the edge starts at 0.75.
+        assert!(!collision_monitor.will_collision_occur(
+            &robot("robot1", 0.0, 0.0, quarter_turn),
+            &robot("robot2", 1.25, 0.0, 0.0)
+        ));
+        assert!(collision_monitor.will_collision_occur(
+            &robot("robot1", 0.0, 0.0, quarter_turn),
+            &robot("robot2", 1.15, 0.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn test_collision_monitor_geometries() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let quarter_turn = std::f64::consts::FRAC_PI_4;
+
+        let robot = |device_id: &str, x: f64, y: f64, geometry: Option<Geometry>| Robot {
+            x,
+            y,
+            device_id: device_id.to_string(),
+            geometry,
+            ..Default::default()
+        };
+        let disk = |radius: f64| Some(Geometry::Circle { radius });
+
+        // disks collide once their centres are within the sum of their radii.
+        let disk_a = robot("disk_a", 0.0, 0.0, disk(1.0));
+        assert!(
+            collision_monitor.will_collision_occur(&disk_a, &robot("disk_b", 1.4, 0.0, disk(0.5)))
+        );
+        assert!(
+            !collision_monitor.will_collision_occur(&disk_a, &robot("disk_b", 1.6, 0.0, disk(0.5)))
+        );
+        // a disk passing straight through another within a tick is caught by the sweep.
+        assert!(collision_monitor.collision_check_helper(
+            &disk_a,
+            &robot("disk_b", 10.0, 0.0, disk(0.5)),
+            (-20.0, 0.0)
+        ));
+
+        // against the configured 1.0 x 1.0 rectangle, in either order.
+        let rect = robot("rect", 0.0, 0.0, None);
+        for (x, y, collides) in [
+            (0.95, 0.0, true),
+            (1.05, 0.0, false),
+            (0.85, 0.85, true),
+            (0.9, 0.9, false),
+        ] {
+            let small_disk = robot("disk", x, y, disk(0.5));
+            assert_eq!(
+                collision_monitor.will_collision_occur(&rect, &small_disk),
+                collides
+            );
+            assert_eq!(
+                collision_monitor.will_collision_occur(&small_disk, &rect),
+                collides
+            );
+        }
+
+        // a rectangle turned 45 degrees reaches 0.707 along the x-axis.
+        let turned_rect = Robot {
+            theta: quarter_turn,
+            ..rect.clone()
+        };
+        assert!(collision_monitor
+            .will_collision_occur(&turned_rect, &robot("disk", 1.15, 0.0, disk(0.5))));
+        assert!(!collision_monitor
+            .will_collision_occur(&turned_rect, &robot("disk", 1.25, 0.0, disk(0.5))));
+
+        // an explicit rectangle overrides the configured dimensions.
+        let long_rect = robot(
+            "long",
+            0.0,
+            0.0,
+            Some(Geometry::Rect {
+                width: 4.0,
+                height: 0.5,
+            }),
+        );
+        assert!(collision_monitor.will_collision_occur(&long_rect, &robot("rect", 2.2, 0.0, None)));
+        assert!(!collision_monitor.will_collision_occur(&long_rect, &robot("rect", 0.0, 1.0, None)));
+
+        assert_eq!(
+            serde_json::from_str::<Geometry>(r#"{ "shape": "circle", "radius": 0.5 }"#).unwrap(),
+            Geometry::Circle { radius: 0.5 }
+        );
+    }
+
+    #[test]
+    fn test_collision_monitor_will_collision_occur_within() {
+        let robot = |device_id: &str, xs: [f64; 3]| Robot {
+            x: xs[0],
+            path: xs
+                .iter()
+                .map(|&x| Path {
+                    x,
+                    y: 0.0,
+                    theta: 0.0,
+                })
+                .collect(),
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+        // far apart now and next step, in the same cell two steps ahead.
+        let robot1 = robot("robot1", [0.0, 1.0, 2.0]);
+        let robot2 = robot("robot2", [4.0, 3.0, 2.0]);
+
+        let collision_monitor = CollisionMonitor::new(test_config());
+        assert!(!collision_monitor.will_collision_occur(&robot1, &robot2));
+        assert!(!collision_monitor.will_collision_occur_within(&robot1, &robot2, 1));
+        assert!(collision_monitor.will_collision_occur_within(&robot1, &robot2, 2));
+        assert!(collision_monitor.will_collision_occur_within(&robot1, &robot2, 5));
+
+        // without lookahead both robots move on.
+        let mut robots = vec![robot1.clone(), robot2.clone()];
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!((robots[0].x, robots[1].x), (1.0, 3.0));
+
+        // with lookahead they are stopped before they meet.
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            lookahead_steps: Some(2),
+            ..test_config()
+        });
+        let mut robots = vec![robot1, robot2];
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!((robots[0].x, robots[1].x), (0.0, 4.0));
+        assert!(robots.iter().all(|robot| robot.state == MotionState::Pause));
+    }
+
+    #[test]
+    fn test_collision_monitor_min_separation() {
+        let robot = |device_id: &str, x: f64, theta: f64| Robot {
+            x,
+            theta,
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            near_miss_distance: Some(1.0),
+            ..test_config()
+        });
+        let origin = robot("robot1", 0.0, 0.0);
+        let separation = |other: &Robot| collision_monitor.min_separation(&origin, other);
+
+        // unit squares overlapping by half, touching, and 2 apart.
+        assert!((separation(&robot("robot2", 0.5, 0.0)) + 0.5).abs() < 1e-9);
+        assert!(separation(&robot("robot2", 1.0, 0.0)).abs() < 1e-9);
+        assert!((separation(&robot("robot2", 3.0, 0.0)) - 2.0).abs() < 1e-9);
+        // a square turned by 45 degrees reaches out by half its diagonal.
+        let turned = robot("robot2", 2.0, std::f64::consts::FRAC_PI_4);
+        assert!((separation(&turned) - (1.5 - 0.5_f64.sqrt())).abs() < 1e-9);
+
+        // only the robots close to, but clear of, each other are near misses.
+        let near_misses = collision_monitor.near_misses(&[
+            origin.clone(),
+            robot("robot2", 1.5, 0.0),
+            robot("robot3", 5.0, 0.0),
+        ]);
+        assert_eq!(near_misses.len(), 1);
+        assert_eq!(
+            (near_misses[0].0.as_str(), near_misses[0].1.as_str()),
+            ("robot1", "robot2")
+        );
+        assert!(collision_monitor
+            .near_misses(&[origin.clone(), robot("robot2", 0.5, 0.0)])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_collision_monitor_msgpack_round_trip() {
+        let robot = Robot {
+            x: 1.5,
+            y: -2.0,
+            path: vec![Path {
+                x: 1.5,
+                y: -2.0,
+                theta: 0.5,
+            }],
+            device_id: "robot1".to_string(),
+            state: MotionState::Reroute,
+            battery_level: 80.0,
+            geometry: Some(Geometry::Circle { radius: 0.4 }),
+            round: Some(3),
+            ..Default::default()
+        };
+
+        let encoded = WireFormat::Msgpack.encode(&robot).unwrap();
+        assert_eq!(
+            WireFormat::Msgpack.decode::<Robot>(&encoded).unwrap(),
+            robot
+        );
+
+        // states are published from their JSON value, possibly with a compact motion state.
+        let encoded = WireFormat::Msgpack
+            .encode(&robot.to_value(true).unwrap())
+            .unwrap();
+        assert_eq!(
+            WireFormat::Msgpack.decode::<Robot>(&encoded).unwrap(),
+            robot
+        );
+    }
+
+    #[test]
+    fn test_collision_monitor_state_encodings() {
+        let robot = Robot {
+            device_id: "robot1".to_string(),
+            state: MotionState::Pause,
+            ..Default::default()
+        };
+
+        let named: serde_json::Value =
+            serde_json::from_str(&robot.to_json(false).unwrap()).unwrap();
+        let compact: serde_json::Value =
+            serde_json::from_str(&robot.to_json(true).unwrap()).unwrap();
+        assert_eq!(named["state"], "pause");
+        assert_eq!(compact["state"], 0);
+
+        for encoded in [robot.to_json(false).unwrap(), robot.to_json(true).unwrap()] {
+            let decoded: Robot = serde_json::from_str(&encoded).unwrap();
+            assert_eq!(decoded.state, MotionState::Pause);
+        }
+
+        let mut resumed = named.clone();
+        resumed["state"] = 1.into();
+        let resumed: Robot = serde_json::from_value(resumed).unwrap();
+        assert_eq!(resumed.state, MotionState::Resume);
+
+        let mut unknown = named;
+        unknown["state"] = 7.into();
+        assert!(serde_json::from_value::<Robot>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_collision_monitor_motion_states() {
+        for (name, state) in [
+            ("\"pause\"", MotionState::Pause),
+            ("\"Resume\"", MotionState::Resume),
+            ("\"reroute\"", MotionState::Reroute),
+            ("\"stopped\"", MotionState::Stopped),
+        ] {
+            assert_eq!(serde_json::from_str::<MotionState>(name).unwrap(), state);
+        }
+        assert!(serde_json::from_str::<MotionState>("\"paused\"").is_err());
+
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let path = vec![
+            Path {
+                x: 0.0,
+                y: 0.0,
+                theta: 0.0,
+            },
+            Path {
+                x: 1.0,
+                y: 0.0,
+                theta: 0.0,
+            },
+        ];
+        let mut robots = vec![
+            Robot {
+                device_id: "robot1".to_string(),
+                state: MotionState::Reroute,
+                path: path.clone(),
+                ..Default::default()
+            },
+            Robot {
+                y: 10.0,
+                device_id: "robot2".to_string(),
+                state: MotionState::Stopped,
+                path: path
+                    .iter()
+                    .map(|point| Path {
+                        y: 10.0,
+                        ..point.clone()
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        ];
+
+        collision_monitor.update_robot_state(&mut robots);
+
+        assert_eq!(robots[0].x, 1.0);
+        assert_eq!(robots[1].x, 0.0);
+        assert_eq!(robots[1].state, MotionState::Stopped);
+    }
+
+    #[test]
+    fn test_collision_monitor_round_errors() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let robot = |device_id: &str, x: f64| Robot {
+            x,
+            device_id: device_id.to_string(),
+            path: vec![Path {
+                x,
+                y: 0.0,
+                theta: 0.0,
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            collision_monitor
+                .trigger_collision_monitor(vec![robot("robot1", 0.0)])
+                .unwrap_err(),
+            MonitorError::IncompleteAgentSet {
+                received: 1,
+                expected: 2
+            }
+        );
+
+        assert_eq!(
+            collision_monitor
+                .trigger_collision_monitor(vec![robot("robot2", 0.0), robot("robot1", 0.5)])
+                .unwrap_err(),
+            MonitorError::DeadlockUnresolvable {
+                device_ids: vec!["robot1".to_string(), "robot2".to_string()]
+            }
+        );
+
+        assert!(collision_monitor
+            .trigger_collision_monitor(vec![robot("robot1", 0.0), robot("robot2", 10.0)])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_collision_monitor_analyze_snapshot() {
+        // robots at the end of their paths cannot move out of each other's way.
+        let robot = |device_id: &str, x: f64| Robot {
+            x,
+            path: vec![Path {
+                x,
+                y: 0.0,
+                theta: 0.0,
+            }],
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+        let robots = vec![
+            robot("robot1", 0.0),
+            robot("robot2", 0.5),
+            robot("robot3", 10.0),
+            robot("robot4", 10.5),
+            robot("robot5", 20.0),
+        ];
+
+        let report = CollisionMonitor::new(test_config()).analyze(&robots);
+
+        assert_eq!(
+            report.colliding_pairs,
+            vec![
+                ("robot1".to_string(), "robot2".to_string()),
+                ("robot3".to_string(), "robot4".to_string()),
+            ]
+        );
+        assert_eq!(
+            report.deadlock_cycles,
+            vec![
+                vec!["robot1".to_string(), "robot2".to_string()],
+                vec!["robot3".to_string(), "robot4".to_string()],
+            ]
+        );
+        assert_eq!(report.resolved_states.len(), robots.len());
+    }
+
+    #[test]
+    fn test_collision_monitor_resolve_three_robot_cycle() {
+        // each robot's next waypoint is where the next robot in the triangle stands.
+        let corners = [(0.0, 0.0), (0.8, 0.0), (0.4, 0.6)];
+        let robots_in_triangle = |priorities: [u32; 3]| -> Vec<Robot> {
+            (0..3)
+                .map(|idx| {
+                    let (x, y) = corners[idx];
+                    let (next_x, next_y) = corners[(idx + 1) % 3];
+                    Robot {
+                        x,
+                        y,
+                        path: vec![
+                            Path { x, y, theta: 0.0 },
+                            Path {
+                                x: next_x,
+                                y: next_y,
+                                theta: 0.0,
+                            },
+                        ],
+                        device_id: format!("robot{}", idx + 1),
+                        state: MotionState::Resume,
+                        priority: priorities[idx],
+                        ..Default::default()
+                    }
+                })
+                .collect()
+        };
+
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            num_agents: Some(3),
+            ..test_config()
+        });
+        let mut robots = robots_in_triangle([5, 5, 1]);
+        let conflicts = collision_monitor.detect_collisions(&robots);
+        assert_eq!(conflicts, vec![(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(
+            collision_monitor.wait_for_cycles(&robots, &conflicts),
+            vec![vec![0, 1, 2]]
+        );
+
+        collision_monitor.resolve_deadlock(&mut robots, &conflicts);
+
+        assert_eq!(robots[0].state, MotionState::Resume);
+        assert_eq!((robots[0].x, robots[0].y), corners[1]);
+        assert_eq!(robots[1].state, MotionState::Resume);
+        assert_eq!((robots[1].x, robots[1].y), corners[2]);
+        assert_eq!(robots[2].state, MotionState::Pause);
+        assert_eq!((robots[2].x, robots[2].y), corners[2]);
+    }
+
+    #[test]
+    fn test_collision_monitor_continuous_check_catches_tunnelling() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let robot_a = Robot {
+            x: 0.0,
+            vx: 1000.0,
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        let robot_b = Robot {
+            x: 10.0,
+            vx: -1000.0,
+            device_id: "robot2".to_string(),
+            ..Default::default()
+        };
+        let after_tick = |robot: &Robot| Robot {
+            x: robot.x + robot.vx * 0.01,
+            ..robot.clone()
+        };
+
+        // the robots swap places within a 10ms tick without ever overlapping at a tick boundary.
+        assert!(!collision_monitor.collision_check_helper(&robot_a, &robot_b, (0.0, 0.0)));
+        assert!(!collision_monitor.collision_check_helper(
+            &after_tick(&robot_a),
+            &after_tick(&robot_b),
+            (0.0, 0.0)
+        ));
+
+        assert!(collision_monitor.will_collision_occur_continuous(&robot_a, &robot_b, 0.01));
+        assert!(collision_monitor.will_collision_occur(&robot_a, &robot_b));
+
+        // robots driving in parallel never meet, however fast they go.
+        let robot_c = Robot {
+            y: 10.0,
+            vx: 1000.0,
             device_id: "robot3".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            ..Default::default()
         };
+        assert!(!collision_monitor.will_collision_occur_continuous(&robot_a, &robot_c, 0.01));
+    }
 
-        let robot4 = Robot {
-            x: 3.0,
-            y: 3.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+    #[test]
+    fn test_collision_monitor_resolution_strategies() {
+        let robot = |device_id: &str, x: f64, priority: u32, timestamp: i64| Robot {
+            x,
+            y: 0.0,
+            timestamp,
             path: vec![
                 Path {
-                    x: 3.0,
-                    y: 3.0,
+                    x,
+                    y: 0.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 4.0,
-                    y: 4.0,
+                    x: x + (x - 0.25) * 8.0,
+                    y: 0.0,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot4".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            priority,
+            ..Default::default()
         };
-
-        let robots = vec![
-            robot1.clone(),
-            robot2.clone(),
-            robot3.clone(),
-            robot4.clone(),
-        ];
-        let config = CollisionMonitorConfig {
-            width: 1.0,
-            height: 1.0,
-            queue_hub_pw: String::new(),
-            queue_hub_user: String::new(),
-            hostname: String::new(),
-            hub_listening_port: 5672,
-            num_agents: 3,
-            logs_dir: String::new(),
-            listening_port: 9877,
-            db_path: String::new(),
+        let monitor = |resolution_strategy| {
+            CollisionMonitor::new(CollisionMonitorConfig {
+                resolution_strategy,
+                ..test_config()
+            })
         };
+        // robot1 has the higher priority, robot2 the newer state.
+        let robots = || vec![robot("robot1", 0.0, 10, 100), robot("robot2", 0.5, 1, 200)];
 
-        let collision_monitor = CollisionMonitor::new(config);
+        let mut pause_both = robots();
+        assert_eq!(
+            monitor(ResolutionStrategy::PauseBoth).resolve_collision(&mut pause_both, 0, 1),
+            (MotionState::Pause, MotionState::Pause)
+        );
 
-        let mut updated_robots = robots.clone();
-        collision_monitor.update_robot_state(&mut updated_robots);
+        let mut lower_priority = robots();
+        assert_eq!(
+            monitor(ResolutionStrategy::PauseLowerPriority).resolve_collision(
+                &mut lower_priority,
+                0,
+                1
+            ),
+            (MotionState::Resume, MotionState::Pause)
+        );
 
-        assert_eq!(updated_robots[0].state, MotionState::Resume.to_string());
-        assert_eq!(updated_robots[0].x, 1.0);
-        assert_eq!(updated_robots[0].y, 1.0);
+        let mut newer = robots();
+        newer[0].timestamp = 300;
+        assert_eq!(
+            monitor(ResolutionStrategy::PauseNewer).resolve_collision(&mut newer, 0, 1),
+            (MotionState::Pause, MotionState::Resume)
+        );
 
-        assert_eq!(updated_robots[1].state, MotionState::Resume.to_string());
-        assert_eq!(updated_robots[1].x, 20.0);
-        assert_eq!(updated_robots[1].y, 20.0);
+        let mut yielding = robots();
+        assert_eq!(
+            monitor(ResolutionStrategy::Yield).resolve_collision(&mut yielding, 0, 1),
+            (MotionState::Resume, MotionState::Reroute)
+        );
+        assert_eq!((yielding[1].x, yielding[1].y), (1.5, 0.0));
+        assert_eq!(yielding[0].x, 0.0);
+
+        // the one free cell is refused once it lies off the grid, in an obstacle or in a no-stop
+        // region.
+        let around_free_cell = Region {
+            x_min: 1.0,
+            y_min: -0.5,
+            x_max: 2.0,
+            y_max: 0.5,
+        };
+        for config in [
+            CollisionMonitorConfig {
+                grid_max_x: Some(1.0),
+                ..test_config()
+            },
+            CollisionMonitorConfig {
+                obstacles: vec![around_free_cell.clone()],
+                ..test_config()
+            },
+            CollisionMonitorConfig {
+                no_stop_regions: vec![around_free_cell.clone()],
+                ..test_config()
+            },
+        ] {
+            let mut fenced = robots();
+            let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+                resolution_strategy: ResolutionStrategy::Yield,
+                ..config
+            });
+            assert_eq!(
+                collision_monitor.resolve_collision(&mut fenced, 0, 1),
+                (MotionState::Pause, MotionState::Pause)
+            );
+            assert_eq!((fenced[1].x, fenced[1].y), (0.5, 0.0));
+        }
 
-        assert_eq!(updated_robots[2].state, MotionState::Resume.to_string());
-        assert_eq!(updated_robots[2].x, 60.0);
-        assert_eq!(updated_robots[2].y, 60.0);
+        // in a full round the robot which stepped aside is left there rather than sent on.
+        let mut round = robots();
+        monitor(ResolutionStrategy::Yield).update_robot_state(&mut round);
+        assert_eq!(round[1].state, MotionState::Reroute);
+        assert_eq!((round[1].x, round[1].y), (1.5, 0.0));
+        assert_eq!(round[0].state, MotionState::Resume);
+        assert_eq!(round[0].x, -2.0);
+
+        // with every adjacent cell taken the yielding robot cannot step aside.
+        let mut boxed_in = robots();
+        for (idx, (x, y)) in [(1.5, 0.0), (0.5, 1.0), (0.5, -1.0)].iter().enumerate() {
+            boxed_in.push(Robot {
+                x: *x,
+                y: *y,
+                device_id: format!("wall{}", idx),
+                state: MotionState::Pause,
+                ..Default::default()
+            });
+        }
+        assert_eq!(
+            monitor(ResolutionStrategy::Yield).resolve_collision(&mut boxed_in, 0, 1),
+            (MotionState::Pause, MotionState::Pause)
+        );
 
-        assert_eq!(updated_robots[3].state, MotionState::Resume.to_string());
-        assert_eq!(updated_robots[3].x, 4.0);
-        assert_eq!(updated_robots[3].y, 4.0);
+        // a full round lets the higher-priority robot drive away instead of pausing the fleet.
+        let mut round = robots();
+        monitor(ResolutionStrategy::PauseLowerPriority).update_robot_state(&mut round);
+        assert_eq!(round[0].state, MotionState::Resume);
+        assert_eq!(round[0].x, -2.0);
+        assert_eq!(round[1].state, MotionState::Pause);
+        assert_eq!(round[1].x, 0.5);
+
+        let mut round = robots();
+        monitor(ResolutionStrategy::PauseBoth).update_robot_state(&mut round);
+        assert_eq!(round[0].state, MotionState::Resume);
+        assert_eq!(round[0].x, -2.0);
+        assert_eq!(round[1].state, MotionState::Pause);
     }
 
     #[test]
-    fn test_collision_monitor_detect_collisions() {
-        // Create 3 robots for testing
-        let robot1 = Robot {
-            x: 0.0,
-            y: 0.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+    fn test_collision_monitor_resolve_deadlock_advances_shared_robot_once() {
+        let point = |x: f64, y: f64| Path { x, y, theta: 0.0 };
+        let robot = |device_id: &str, x: f64, y: f64, priority: u32| Robot {
+            x,
+            y,
+            path: vec![point(x, y), point(x + 1.0, y), point(x + 2.0, y)],
+            device_id: device_id.to_string(),
+            state: MotionState::Pause,
+            priority,
+            ..Default::default()
+        };
+        let collision_monitor = CollisionMonitor::new(test_config());
+        // robot0 is in both pairs, one of them listed both ways round.
+        let mut robots = vec![
+            robot("robot0", 0.0, 0.0, 10),
+            robot("robot1", 0.5, 0.0, 1),
+            robot("robot2", 0.0, 0.5, 1),
+        ];
+
+        collision_monitor.resolve_deadlock(&mut robots, &[(0, 1), (1, 0), (0, 2)]);
+
+        assert_eq!((robots[0].x, robots[0].path_index), (1.0, 1));
+        assert_eq!(robots[0].state, MotionState::Resume);
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!(robots[2].state, MotionState::Pause);
+        assert_eq!((robots[2].x, robots[2].y), (0.0, 0.5));
+    }
+
+    #[test]
+    fn test_collision_monitor_loaded_robot_takes_precedence() {
+        let robot = |device_id: &str, x: f64, priority: u32, loaded: bool| Robot {
+            x,
             path: vec![
                 Path {
-                    x: 0.0,
+                    x,
                     y: 0.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 1.0,
-                    y: 1.0,
+                    x: x + (x - 0.25) * 8.0,
+                    y: 0.0,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot1".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            device_id: device_id.to_string(),
+            loaded,
+            priority,
+            ..Default::default()
         };
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            resolution_strategy: ResolutionStrategy::PauseLowerPriority,
+            ..test_config()
+        });
+
+        // the loaded robot2 keeps moving despite its lower priority.
+        let mut loaded_vs_empty = vec![
+            robot("robot1", 0.0, 10, false),
+            robot("robot2", 0.5, 1, true),
+        ];
+        assert_eq!(
+            collision_monitor.resolve_collision(&mut loaded_vs_empty, 0, 1),
+            (MotionState::Pause, MotionState::Resume)
+        );
+        collision_monitor.update_robot_state(&mut loaded_vs_empty);
+        assert_eq!(loaded_vs_empty[0].state, MotionState::Pause);
+        assert_eq!(loaded_vs_empty[0].x, 0.0);
+        assert_eq!(loaded_vs_empty[1].state, MotionState::Resume);
+        assert_eq!(loaded_vs_empty[1].x, 2.5);
+
+        // between two loaded robots priority decides again.
+        let mut both_loaded = vec![
+            robot("robot1", 0.0, 10, true),
+            robot("robot2", 0.5, 1, true),
+        ];
+        assert_eq!(
+            collision_monitor.resolve_collision(&mut both_loaded, 0, 1),
+            (MotionState::Resume, MotionState::Pause)
+        );
+        collision_monitor.update_robot_state(&mut both_loaded);
+        assert_eq!(both_loaded[0].state, MotionState::Resume);
+        assert_eq!(both_loaded[1].state, MotionState::Pause);
+
+        // without a priority either, both pause.
+        let mut equals = vec![robot("robot1", 0.0, 1, true), robot("robot2", 0.5, 1, true)];
+        assert_eq!(
+            collision_monitor.resolve_collision(&mut equals, 0, 1),
+            (MotionState::Pause, MotionState::Pause)
+        );
+    }
 
-        let robot2 = Robot {
-            x: 1.0,
-            y: 1.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
-            path: vec![
-                Path {
-                    x: 1.0,
-                    y: 1.0,
-                    theta: 0.0,
-                },
-                Path {
-                    x: 2.0,
-                    y: 2.0,
-                    theta: 0.0,
-                },
-            ],
-            device_id: "robot2".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+    #[test]
+    fn test_collision_monitor_run_to_completion() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let robot = |device_id: &str, y: f64, xs: &[f64]| Robot {
+            x: xs[0],
+            y,
+            path: xs.iter().map(|&x| Path { x, y, theta: 0.0 }).collect(),
+            device_id: device_id.to_string(),
+            ..Default::default()
         };
 
-        let robot3 = Robot {
-            x: 2.0,
-            y: 2.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+        // robots on parallel lanes drive straight to the end of their paths.
+        let result = collision_monitor.run_to_completion(
+            vec![
+                robot("robot0", 0.0, &[0.0, 1.0, 2.0, 3.0]),
+                robot("robot1", 5.0, &[3.0, 2.0, 1.0]),
+            ],
+            20,
+            |_| {},
+        );
+        assert!(result.completed);
+        assert!(!result.deadlocked);
+        assert_eq!(result.snapshots.len(), 3);
+        assert_eq!(
+            result.snapshots[1]
+                .iter()
+                .map(|robot| robot.x)
+                .collect::<Vec<_>>(),
+            vec![2.0, 1.0]
+        );
+
+        // robots swapping places on a single lane can't get past each other, which is reported
+        // long before the tick limit.
+        let result = collision_monitor.run_to_completion(
+            vec![
+                robot("robot0", 0.0, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+                robot("robot1", 0.0, &[6.0, 5.0, 4.0, 3.0, 2.0, 1.0, 0.0]),
+            ],
+            1000,
+            |_| {},
+        );
+        assert!(!result.completed);
+        assert!(result.deadlocked);
+        assert!(result.snapshots.len() < 10);
+    }
+
+    #[test]
+    fn test_collision_monitor_resolves_two_robot_conflict() {
+        let robot = |device_id: &str, x: f64, next_x: f64| Robot {
+            x,
             path: vec![
                 Path {
-                    x: 2.0,
-                    y: 2.0,
+                    x,
+                    y: 0.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 3.0,
-                    y: 3.0,
+                    x: next_x,
+                    y: 0.0,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot3".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            ..Default::default()
         };
+        let collision_monitor = CollisionMonitor::new(test_config());
 
-        let robots = vec![robot1.clone(), robot2.clone(), robot3.clone()];
-        let config = CollisionMonitorConfig {
-            width: 1.0,
-            height: 1.0,
-            queue_hub_pw: String::new(),
-            queue_hub_user: String::new(),
-            hostname: String::new(),
-            hub_listening_port: 5672,
-            num_agents: 3,
-            logs_dir: String::new(),
-            listening_port: 9877,
-            db_path: String::new(),
-        };
-        let collision_monitor = CollisionMonitor::new(config);
+        // robot2 can drive away from robot1, so the round ends with it advancing.
+        let mut robots = vec![robot("robot1", 0.0, 1.0), robot("robot2", 0.5, 3.0)];
+        collision_monitor.update_robot_state(&mut robots);
 
-        let conflicts = collision_monitor.detect_collisions(&robots);
+        assert_eq!(robots[0].state, MotionState::Pause);
+        assert_eq!(robots[0].x, 0.0);
+        assert_eq!(robots[1].state, MotionState::Resume);
+        assert_eq!(robots[1].x, 3.0);
 
-        assert_eq!(conflicts.len(), 2);
-        assert_eq!(conflicts[0], (0, 1));
-        assert_eq!(conflicts[1], (1, 2));
+        // at the end of their paths neither robot can move away, so the round pauses the fleet.
+        let mut robots = vec![robot("robot1", 0.0, 1.0), robot("robot2", 0.5, 3.0)];
+        for robot in robots.iter_mut() {
+            robot.path.truncate(1);
+        }
+        collision_monitor.update_robot_state(&mut robots);
+
+        assert!(robots.iter().all(|robot| robot.state == MotionState::Pause));
     }
 
     #[test]
-    fn test_collision_monitor_resolve_deadlock() {
-        let robot1 = Robot {
-            x: 0.0,
-            y: 0.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+    fn test_collision_monitor_bounds_resolution_passes() {
+        let robot = |device_id: &str, x: f64, next_x: f64| Robot {
+            x,
             path: vec![
                 Path {
-                    x: 0.0,
+                    x,
                     y: 0.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 1.0,
-                    y: 1.0,
+                    x: next_x,
+                    y: 0.0,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot1".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            ..Default::default()
+        };
+        // letting robot2 go pushes it into robot3, so every pass hands the conflict down the line.
+        let robots = || {
+            vec![
+                robot("robot1", 0.0, 1.0),
+                robot("robot2", 0.5, 1.6),
+                robot("robot3", 2.0, 5.0),
+            ]
+        };
+        let monitor = |max_resolution_iterations| {
+            CollisionMonitor::new(CollisionMonitorConfig {
+                max_resolution_iterations,
+                num_agents: Some(3),
+                ..test_config()
+            })
         };
 
-        let robot2 = Robot {
-            x: 1.0,
-            y: 1.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+        let mut bounded = robots();
+        monitor(1).update_robot_state(&mut bounded);
+        assert!(bounded
+            .iter()
+            .all(|robot| robot.state == MotionState::Pause));
+        assert_eq!(bounded[2].x, 2.0);
+
+        let mut resolved = robots();
+        monitor(2).update_robot_state(&mut resolved);
+        assert_eq!(resolved[0].state, MotionState::Pause);
+        assert_eq!(resolved[1].state, MotionState::Pause);
+        assert_eq!(resolved[2].state, MotionState::Resume);
+        assert_eq!(resolved[2].x, 5.0);
+    }
+
+    #[test]
+    fn test_collision_monitor_resolves_every_conflict_once() {
+        let robot = |device_id: &str, x: f64, y: f64, size: f64, priority: u32| Robot {
+            x,
+            y,
             path: vec![
+                Path { x, y, theta: 0.0 },
                 Path {
-                    x: 1.0,
-                    y: 1.0,
+                    x,
+                    y: y - 5.0,
                     theta: 0.0,
                 },
                 Path {
-                    x: 0.0,
-                    y: 0.0,
+                    x,
+                    y: y - 10.0,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot2".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
-        };
-
-        let robots = vec![robot1.clone(), robot2.clone()];
-        let config = CollisionMonitorConfig {
-            width: 1.0,
-            height: 1.0,
-            queue_hub_pw: String::new(),
-            queue_hub_user: String::new(),
-            hostname: String::new(),
-            hub_listening_port: 5672,
-            num_agents: 2,
-            logs_dir: String::new(),
-            listening_port: 9877,
-            db_path: String::new(),
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            width: Some(size),
+            height: Some(size),
+            priority,
+            ..Default::default()
         };
 
-        let collision_monitor = CollisionMonitor::new(config);
+        // robot1 overlaps each of the others, which don't overlap one another.
+        let mut robots = vec![
+            robot("robot1", 0.0, 0.0, 2.0, 10),
+            robot("robot2", 1.2, 0.0, 1.0, 1),
+            robot("robot3", -1.2, 0.0, 1.0, 1),
+            robot("robot4", 0.0, 1.2, 1.0, 1),
+        ];
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            resolution_strategy: ResolutionStrategy::PauseLowerPriority,
+            ..test_config()
+        });
+        assert_eq!(
+            collision_monitor.detect_collisions(&robots),
+            vec![(0, 1), (0, 2), (0, 3)]
+        );
 
-        let conflicts = vec![(0, 1)];
-        collision_monitor.resolve_deadlock(&mut robots.clone(), &conflicts);
+        collision_monitor.update_robot_state(&mut robots);
 
-        assert_eq!(robots[0].state, MotionState::Resume.to_string());
-        assert_eq!(robots[1].state, MotionState::Resume.to_string());
+        // every pair was decided, and robot1 moved a single waypoint despite its three conflicts.
+        assert_eq!(robots[0].state, MotionState::Resume);
+        assert_eq!((robots[0].x, robots[0].y), (0.0, -5.0));
+        for robot in &robots[1..] {
+            assert_eq!(robot.state, MotionState::Pause);
+            assert_eq!(robot.path_index, 0);
+        }
     }
 
     #[test]
-    fn test_collision_monitor_will_collision_occur() {
-        // Create 2 robots for testing
-        let robot1 = Robot {
-            x: 0.0,
+    fn test_collision_monitor_position_epsilon() {
+        let point = |x: f64| Path {
+            x,
             y: 0.0,
             theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+        };
+        // the reported position is 1e-10 off the first point of the path.
+        let robot = Robot {
+            x: 1e-10,
+            path: vec![point(0.0), point(1.0), point(2.0)],
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+
+        let mut advanced = robot.clone();
+        CollisionMonitor::new(test_config()).update_motion_coordinates(&mut advanced);
+        assert_eq!((advanced.x, advanced.path_index), (1.0, 1));
+
+        // with a tighter epsilon the robot is off its path and only returns to it.
+        let mut returned = robot;
+        CollisionMonitor::new(CollisionMonitorConfig {
+            position_epsilon: 1e-12,
+            ..test_config()
+        })
+        .update_motion_coordinates(&mut returned);
+        assert_eq!((returned.x, returned.path_index), (0.0, 0));
+    }
+
+    #[test]
+    fn test_collision_monitor_pauses_low_battery_robots() {
+        let robot = |device_id: &str, y: f64, battery_level: f64| Robot {
+            x: 0.0,
+            y,
             path: vec![
                 Path {
                     x: 0.0,
-                    y: 0.0,
+                    y,
                     theta: 0.0,
                 },
                 Path {
                     x: 1.0,
-                    y: 1.0,
+                    y,
                     theta: 0.0,
                 },
             ],
-            device_id: "robot1".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            device_id: device_id.to_string(),
+            state: MotionState::Resume,
+            battery_level,
+            ..Default::default()
         };
 
-        let robot2 = Robot {
-            x: 1.0,
-            y: 1.0,
-            theta: 0.0,
-            loaded: false,
-            timestamp: 0,
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            min_operating_soc: 10.0,
+            ..test_config()
+        });
+
+        let mut robots = vec![
+            robot("robot1", 0.0, 80.0),
+            robot("robot2", 5.0, 5.0),
+            robot("robot3", 10.0, 60.0),
+        ];
+        collision_monitor.update_robot_state(&mut robots);
+
+        assert_eq!(robots[1].state, MotionState::Pause);
+        assert_eq!((robots[1].x, robots[1].y), (0.0, 5.0));
+        for idx in [0, 2] {
+            assert_eq!(robots[idx].state, MotionState::Resume);
+            assert_eq!(robots[idx].x, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_collision_monitor_advances_by_path_index() {
+        let point = |x: f64, y: f64| Path { x, y, theta: 0.0 };
+        // the path crosses its own start, so position alone is ambiguous there.
+        let mut robot = Robot {
             path: vec![
-                Path {
-                    x: 1.0,
-                    y: 1.0,
-                    theta: 0.0,
-                },
-                Path {
-                    x: 2.0,
-                    y: 2.0,
-                    theta: 0.0,
-                },
+                point(0.0, 0.0),
+                point(1.0, 0.0),
+                point(1.0, 1.0),
+                point(0.0, 1.0),
+                point(0.0, 0.0),
+                point(-1.0, 0.0),
             ],
-            device_id: "robot2".to_string(),
-            state: MotionState::Resume.to_string(),
-            battery_level: 100.0,
+            device_id: "robot1".to_string(),
+            ..Default::default()
         };
 
-        let config = CollisionMonitorConfig {
-            width: 1.0,
-            height: 1.0,
-            queue_hub_pw: String::new(),
-            queue_hub_user: String::new(),
-            hostname: String::new(),
-            hub_listening_port: 5672,
-            num_agents: 2,
-            logs_dir: String::new(),
-            listening_port: 9877,
-            db_path: String::new(),
+        let collision_monitor = CollisionMonitor::new(test_config());
+        for _ in 0..5 {
+            collision_monitor.update_motion_coordinates(&mut robot);
+        }
+        assert_eq!((robot.x, robot.y, robot.path_index), (-1.0, 0.0, 5));
+        assert!(robot.path_complete());
+        assert_eq!(robot.path_progress(), 1.0);
+
+        // states which do not track their index fall back to the robot's position.
+        robot.x = 1.0;
+        robot.y = 1.0;
+        robot.path_index = 0;
+        assert_eq!(robot.current_path_index(1e-9), Some(2));
+        assert!(!robot.path_complete());
+        assert_eq!(robot.path_progress(), 0.0);
+    }
+
+    #[test]
+    fn test_collision_monitor_pauses_robot_with_empty_path() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let robots = vec![
+            Robot {
+                device_id: "robot0".to_string(),
+                x: 5.0,
+                y: 5.0,
+                ..Default::default()
+            },
+            Robot {
+                device_id: "robot1".to_string(),
+                x: 20.0,
+                path: vec![
+                    Path {
+                        x: 20.0,
+                        y: 0.0,
+                        theta: 0.0,
+                    },
+                    Path {
+                        x: 21.0,
+                        y: 0.0,
+                        theta: 0.0,
+                    },
+                ],
+                ..Default::default()
+            },
+        ];
+
+        let updated = collision_monitor.trigger_collision_monitor(robots).unwrap();
+        assert_eq!(updated[0].state, MotionState::Pause);
+        assert_eq!((updated[0].x, updated[0].y), (5.0, 5.0));
+        // robots with a path carry on.
+        assert_eq!(updated[1].state, MotionState::Resume);
+        assert_eq!(updated[1].x, 21.0);
+    }
+
+    #[test]
+    fn test_collision_monitor_pauses_robot_at_grid_bounds() {
+        let point = |x: f64| Path {
+            x,
+            y: 0.0,
+            theta: 0.0,
         };
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            num_agents: Some(1),
+            grid_min_x: Some(0.0),
+            grid_max_x: Some(10.0),
+            ..test_config()
+        });
+        let mut robots = vec![Robot {
+            x: 8.0,
+            path: vec![point(8.0), point(9.0), point(10.0), point(11.0)],
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        }];
+
+        // the robot moves up to the boundary, and is paused there before the point beyond it.
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!((robots[0].x, robots[0].state), (9.0, MotionState::Resume));
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!((robots[0].x, robots[0].state), (10.0, MotionState::Pause));
+
+        // told to resume anyway, it still stays on the grid.
+        robots[0].state = MotionState::Resume;
+        collision_monitor.update_robot_state(&mut robots);
+        assert_eq!((robots[0].x, robots[0].state), (10.0, MotionState::Pause));
+        collision_monitor.update_motion_coordinates(&mut robots[0]);
+        assert_eq!(robots[0].x, 10.0);
+    }
 
-        let collision_monitor = CollisionMonitor::new(config);
+    #[test]
+    fn test_collision_monitor_snaps_robot_off_its_path() {
+        let point = |x: f64, y: f64| Path { x, y, theta: 0.0 };
+        let mut robot = Robot {
+            x: 1.9,
+            y: 0.3,
+            path: vec![point(0.0, 0.0), point(1.0, 0.0), point(2.0, 0.0)],
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(robot.current_path_index(1e-9), None);
+        assert_eq!(robot.nearest_path_index(), Some(2));
+
+        // the robot first returns to the closest point, then follows its path from there.
+        let collision_monitor = CollisionMonitor::new(test_config());
+        collision_monitor.update_motion_coordinates(&mut robot);
+        assert_eq!((robot.x, robot.y, robot.path_index), (2.0, 0.0, 2));
+        assert!(robot.path_complete());
+
+        robot.x = 0.2;
+        robot.path_index = 0;
+        collision_monitor.update_motion_coordinates(&mut robot);
+        assert_eq!((robot.x, robot.y, robot.path_index), (0.0, 0.0, 0));
+        collision_monitor.update_motion_coordinates(&mut robot);
+        assert_eq!((robot.x, robot.y, robot.path_index), (1.0, 0.0, 1));
+    }
 
-        let collision_occurs = collision_monitor.will_collision_occur(&robot1, &robot2);
+    /// `random_fleet` returns `count` robots with random poses, velocities and dimensions spread
+    /// over a `spread` x `spread` area. A fixed linear congruential generator keeps it reproducible.
+    fn random_fleet(count: usize, spread: f64) -> Vec<Robot> {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = move || {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        (0..count)
+            .map(|idx| {
+                let (x, y) = (random() * spread, random() * spread);
+                let step = |x: f64, y: f64| Path {
+                    x: x + 1.0,
+                    y: y + 0.5,
+                    theta: 0.0,
+                };
+                Robot {
+                    x,
+                    y,
+                    theta: random() * f64::consts::PI,
+                    vx: random() * 4.0 - 2.0,
+                    vy: random() * 4.0 - 2.0,
+                    width: Some(0.5 + random() * 1.5),
+                    height: Some(0.5 + random() * 1.5),
+                    path: vec![
+                        Path { x, y, theta: 0.0 },
+                        step(x, y),
+                        step(x + 1.0, y + 0.5),
+                    ],
+                    device_id: format!("robot{}", idx),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// `sequential_conflicts` checks every pair of robots one after the other.
+    fn sequential_conflicts(
+        collision_monitor: &CollisionMonitor,
+        robots: &[Robot],
+        skipped: &HashSet<usize>,
+        lookahead: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut conflicts = Vec::new();
+        for idx in (0..robots.len()).filter(|idx| !skipped.contains(idx)) {
+            for jdx in ((idx + 1)..robots.len()).filter(|jdx| !skipped.contains(jdx)) {
+                if collision_monitor.will_collision_occur_within(
+                    &robots[idx],
+                    &robots[jdx],
+                    lookahead,
+                ) {
+                    conflicts.push((idx, jdx));
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    #[test]
+    fn test_collision_monitor_spatial_grid_matches_brute_force() {
+        let robots = random_fleet(500, 60.0);
+        let skipped: HashSet<usize> = (0..500).step_by(7).collect();
+
+        let collision_monitor = CollisionMonitor::new(test_config());
+        for lookahead in [0, 2] {
+            let brute_force =
+                sequential_conflicts(&collision_monitor, &robots, &skipped, lookahead);
+
+            let conflicts =
+                collision_monitor.detect_collisions_skipping(&robots, &skipped, lookahead);
+            assert!(!brute_force.is_empty());
+            assert_eq!(conflicts, brute_force);
+        }
+    }
+
+    #[test]
+    fn test_collision_monitor_detection_matches_sequential_scan() {
+        // a crowded fleet, so that most robots conflict with several others.
+        let robots = random_fleet(300, 12.0);
+
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let sequential = sequential_conflicts(&collision_monitor, &robots, &HashSet::new(), 0);
 
-        assert_eq!(collision_occurs, true);
+        assert!(sequential.len() > robots.len());
+        assert_eq!(collision_monitor.detect_collisions(&robots), sequential);
     }
 }
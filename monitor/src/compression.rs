@@ -0,0 +1,188 @@
+use std::io::Write;
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use warp::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use warp::http::Response;
+use warp::hyper::body::{self, Body, HttpBody};
+use warp::{Filter, Rejection, Reply};
+
+/// [Encoding] defines the content codings REST responses can be compressed with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// `name` returns the coding as named in `Accept-Encoding` and `Content-Encoding` headers.
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    /// `negotiate` picks the coding to answer an `Accept-Encoding` header with, gzip taking
+    /// precedence over deflate. Codings the client refuses with `q=0` are never picked.
+    fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+        let accepted: Vec<&str> = accept_encoding
+            .split(',')
+            .filter_map(|coding| {
+                let mut params = coding.split(';').map(str::trim);
+                let name = params.next()?;
+                let refused = params.any(|param| {
+                    param
+                        .strip_prefix("q=")
+                        .and_then(|quality| quality.parse::<f64>().ok())
+                        == Some(0.0)
+                });
+                (!refused).then_some(name)
+            })
+            .collect();
+
+        [Encoding::Gzip, Encoding::Deflate]
+            .into_iter()
+            .find(|encoding| {
+                accepted
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(encoding.name()))
+            })
+    }
+
+    /// `encode` compresses `bytes` with the coding.
+    fn encode(self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            // HTTP's deflate coding is the zlib format.
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// `compressed` wraps `filter` so that its responses are compressed with gzip or deflate when the
+/// client accepts either. Bodies under `threshold` bytes are sent as they are, since compressing
+/// them costs more than it saves, and so are streamed ones such as `/events`, whose size is not
+/// known up front.
+pub(crate) fn compressed<F, T>(
+    filter: F,
+    threshold: usize,
+) -> impl Filter<Extract = (Response<Body>,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (T,), Error = Rejection> + Clone + Send + Sync + 'static,
+    T: Reply,
+{
+    warp::header::optional::<String>("accept-encoding")
+        .and(filter)
+        .and_then(
+            move |accept_encoding: Option<String>, reply: T| async move {
+                let encoding = accept_encoding.as_deref().and_then(Encoding::negotiate);
+                Ok::<_, Rejection>(compress(reply.into_response(), encoding, threshold).await)
+            },
+        )
+}
+
+/// `compress` encodes the body of `response` with `encoding`, unless no coding was accepted or
+/// the body is empty, smaller than `threshold`, of unknown size or already encoded.
+async fn compress(
+    response: Response<Body>,
+    encoding: Option<Encoding>,
+    threshold: usize,
+) -> Response<Body> {
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => return response,
+    };
+    let large_enough = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|size| size > 0 && size >= threshold as u64);
+    if !large_enough || response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            log::warn!("Could not read response body to compress: {}", error);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    match encoding.encode(&bytes) {
+        Ok(encoded) => {
+            parts
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+            parts
+                .headers
+                .append(VARY, HeaderValue::from_static("accept-encoding"));
+            parts.headers.remove(CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(encoded))
+        }
+        Err(error) => {
+            log::warn!("Could not compress response body: {}", error);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        warp::path!("payload" / usize).map(|size: usize| "x".repeat(size))
+    }
+
+    #[tokio::test]
+    async fn test_compression_gzips_large_responses() {
+        let filter = compressed(payload(), 1024);
+
+        let response = warp::test::request()
+            .path("/payload/4096")
+            .header("accept-encoding", "deflate, gzip;q=0.9")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.body()[..2], [0x1f, 0x8b]);
+
+        // small bodies and clients that don't accept gzip get the body as is.
+        let response = warp::test::request()
+            .path("/payload/16")
+            .header("accept-encoding", "gzip")
+            .reply(&filter)
+            .await;
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(response.body().len(), 16);
+
+        let response = warp::test::request()
+            .path("/payload/4096")
+            .header("accept-encoding", "gzip;q=0, br")
+            .reply(&filter)
+            .await;
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(response.body().len(), 4096);
+    }
+
+    #[test]
+    fn test_compression_negotiates_encoding() {
+        assert_eq!(Encoding::negotiate("deflate, gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("GZIP"), Some(Encoding::Gzip));
+        assert_eq!(
+            Encoding::negotiate("gzip;q=0, deflate"),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(Encoding::negotiate("br, identity"), None);
+    }
+}
@@ -1,12 +1,38 @@
 use clap::Parser;
 use serde_derive::{Deserialize, Serialize};
-use std::fs;
+use std::{fmt::Display, fs, str::FromStr};
 
 #[derive(Parser, Debug)]
 pub struct CLIArguments {
     /// path to configuration file
     #[clap(long, value_parser)]
     pub config_path: String,
+    /// print what the resolver decides for a JSON snapshot of robot states, then exit
+    #[clap(long, value_parser)]
+    pub preview: Option<String>,
+    /// play a JSON snapshot of robot states forward round by round, then exit
+    #[clap(long, value_parser)]
+    pub replay: Option<String>,
+    /// advance the replay one round per keypress (Enter) instead of on a timer
+    #[clap(long, action)]
+    pub step: bool,
+    /// replay speed multiplier applied to the robots' publish interval
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub speed: f64,
+    /// print the state each robot of a JSON snapshot takes at each tick until every path is
+    /// complete, then exit
+    #[clap(long, value_parser)]
+    pub schedule: Option<String>,
+    /// feed a newline-delimited JSON recording of robot states through the monitor in batches of
+    /// `num_agents`, then exit
+    #[clap(long, value_parser)]
+    pub replay_recording: Option<String>,
+    /// file the states resolved from a recording are written to
+    #[clap(long, value_parser, default_value = "resolved_states.jsonl")]
+    pub replay_output: String,
+    /// load and validate the configuration file, print OK or why it is rejected, then exit
+    #[clap(long, action)]
+    pub validate: bool,
 }
 
 /// [CollisionMonitorConfig] defines attributes for Collision Monitor
@@ -16,6 +42,10 @@ pub struct CollisionMonitorConfig {
     pub width: f64,
     // height of the robot container
     pub height: f64,
+    // unit the lengths in this file and in the robot states on the wire are written in; the
+    // monitor converts them to meters
+    #[serde(default)]
+    pub units: Units,
     // rabbit mq hub password
     pub queue_hub_pw: String,
     // rabbit mq user id
@@ -24,34 +54,1039 @@ pub struct CollisionMonitorConfig {
     pub hostname: String,
     // listening port for rabbitmq
     pub hub_listening_port: u64,
-    // number of robot agents participating in the game
-    pub num_agents: usize,
+    // connect to the hub over TLS (amqps://)
+    #[serde(default)]
+    pub use_tls: bool,
+    // optional PEM file of the CA trusted for the hub's certificate, instead of the system roots
+    pub ca_cert_path: Option<String>,
+    // number of robot agents participating in the game, required unless `batch_window_ms` is set
+    pub num_agents: Option<usize>,
+    // optional window in ms after a round's first state; when set, the states received within the
+    // window make up the round and `num_agents` is not waited for
+    pub batch_window_ms: Option<u64>,
+    // optional time in ms after which a robot not heard from is stale and held in place as an
+    // obstacle
+    pub stale_after_ms: Option<u64>,
     // logs directory
     pub logs_dir: String,
     // listening port to get information of agents
     pub listening_port: u16,
     // sled db path
     pub db_path: String,
+    // optional JSON file of robot states loaded into the db at startup
+    pub seed_states_path: Option<String>,
+    // what to do when seeded robot states already collide: refuse | nudge
+    #[serde(default)]
+    pub seed_collision_policy: SeedCollisionPolicy,
+    // serve aggregated fleet data on the dashboard endpoint
+    #[serde(default)]
+    pub enable_dashboard: bool,
+    // number of recent rounds kept for dashboard latencies
+    #[serde(default = "default_dashboard_window")]
+    pub dashboard_window: usize,
+    // number of most conflict-prone robots listed on the dashboard
+    #[serde(default = "default_dashboard_top_conflicts")]
+    pub dashboard_top_conflicts: usize,
+    // battery level below which a robot is reported as low on battery
+    #[serde(default = "default_low_battery_threshold")]
+    pub low_battery_threshold: f64,
+    // battery level below which a robot is paused in place, regardless of collisions
+    #[serde(default)]
+    pub min_operating_soc: f64,
+    // how much a robot's footprint grows as its position confidence drops to 0.0
+    #[serde(default = "default_confidence_inflation")]
+    pub confidence_inflation: f64,
+    // distance robots must keep from each other; closer robots are flagged as colliding
+    #[serde(default)]
+    pub safety_margin: f64,
+    // separation under which robots that do not collide are logged as a near miss
+    pub near_miss_distance: Option<f64>,
+    // largest difference between coordinates still treated as the same position, absorbing
+    // floating-point noise in the positions robots report
+    #[serde(default = "default_position_epsilon")]
+    pub position_epsilon: f64,
+    // regions, such as narrow intersections, in which a robot is never paused mid-crossing
+    #[serde(default)]
+    pub no_stop_regions: Vec<Region>,
+    // ask robots to publish less frequently when rounds fall behind
+    #[serde(default)]
+    pub enable_backpressure: bool,
+    // round latency in milliseconds above which backpressure kicks in
+    #[serde(default = "default_backpressure_latency_ms")]
+    pub backpressure_latency_ms: f64,
+    // publish interval in milliseconds that backpressure scales up from
+    #[serde(default = "default_base_publish_interval_ms")]
+    pub base_publish_interval_ms: u64,
+    // upper bound on the publish interval suggested to robots
+    #[serde(default = "default_max_publish_interval_ms")]
+    pub max_publish_interval_ms: u64,
+    // SQLite file collision incidents are exported to (requires the `sqlite` feature)
+    pub incidents_db_path: Option<String>,
+    // expose the fleet freeze/unfreeze endpoints
+    #[serde(default)]
+    pub enable_fleet_freeze: bool,
+    // file the fleet snapshot is written to when freezing
+    #[serde(default = "default_freeze_snapshot_path")]
+    pub freeze_snapshot_path: String,
+    // warn when robots reuse correlation ids across rounds
+    #[serde(default)]
+    pub track_correlation_ids: bool,
+    // number of recent correlation ids remembered
+    #[serde(default = "default_correlation_id_window")]
+    pub correlation_id_window: usize,
+    // number of rounds within which a repeated correlation id is treated as a retry
+    #[serde(default)]
+    pub correlation_retry_rounds: u64,
+    // skip, but still ack, states whose correlation id was seen recently
+    #[serde(default)]
+    pub deduplicate_correlation_ids: bool,
+    // number of recent correlation ids remembered for deduplication
+    #[serde(default = "default_correlation_id_window")]
+    pub dedup_window: usize,
+    // oldest robot client version accepted by the hub, e.g. "0.2.0"
+    pub min_client_version: Option<String>,
+    // track the share of recent rounds robots spend in each motion state
+    #[serde(default)]
+    pub enable_utilization_stats: bool,
+    // number of recent rounds utilization is computed over
+    #[serde(default = "default_utilization_window")]
+    pub utilization_window: usize,
+    // expose the endpoint evaluating hypothetical robot states against the fleet
+    #[serde(default)]
+    pub enable_collision_check: bool,
+    // zones capping how many robots may be inside them at once
+    #[serde(default)]
+    pub zones: Vec<Zone>,
+    // queues robots publish to per zone of the facility, instead of the single `rpc_queue`
+    #[serde(default)]
+    pub zone_queues: Vec<ZoneQueue>,
+    // smoothing factor in (0.0, 1.0] of the moving average applied to reported positions
+    pub position_smoothing: Option<f64>,
+    // throttle processing of queued robot messages right after (re)connecting to the hub
+    #[serde(default)]
+    pub enable_slow_start: bool,
+    // how long after (re)connecting processing is throttled, in milliseconds
+    #[serde(default = "default_slow_start_ms")]
+    pub slow_start_ms: u64,
+    // maximum number of robot messages processed per second while throttled
+    #[serde(default = "default_slow_start_rate")]
+    pub slow_start_rate: u32,
+    // how deadlocks are detected: conflicts | timeout
+    #[serde(default)]
+    pub deadlock_detector: DeadlockDetector,
+    // how a conflict between two moving robots is resolved: pause_both | pause_lower_priority |
+    // pause_newer | yield
+    #[serde(default)]
+    pub resolution_strategy: ResolutionStrategy,
+    // number of resolution passes in a round after which the whole fleet is paused instead
+    #[serde(default = "default_max_resolution_iterations")]
+    pub max_resolution_iterations: usize,
+    // how long robots may stay paused in place before the timeout detector aborts the deadlock
+    #[serde(default = "default_deadlock_abort_ms")]
+    pub deadlock_abort_ms: u64,
+    // append every robot state to a per-device history, e.g. for trajectory exports
+    #[serde(default)]
+    pub record_history: bool,
+    // maximum number of states kept per device in the history, unbounded if not set
+    pub history_limit: Option<usize>,
+    // distance around a robot reporting an emergency stop within which other robots are paused too
+    #[serde(default)]
+    pub emergency_stop_radius: f64,
+    // expose the current conflict graph in Graphviz DOT format
+    #[serde(default)]
+    pub enable_conflict_graph: bool,
+    // expose admin routes such as `POST /admin/reset`, for test setups only
+    #[serde(default)]
+    pub enable_admin_routes: bool,
+    // write-ahead log of the states received in the current round, recovered on restart
+    pub wal_path: Option<String>,
+    // check robots far from every other robot only every few rounds
+    #[serde(default)]
+    pub enable_check_downsampling: bool,
+    // number of rounds between collision checks of far robots
+    #[serde(default = "default_far_check_every")]
+    pub far_check_every: u64,
+    // distance to the nearest robot beyond which a robot counts as far
+    #[serde(default = "default_far_distance")]
+    pub far_distance: f64,
+    // Kafka brokers decisions are streamed to (requires the `kafka` feature)
+    pub kafka_brokers: Option<String>,
+    // Kafka topic decisions are published to
+    #[serde(default = "default_kafka_topic")]
+    pub kafka_topic: String,
+    // number of decisions queued for Kafka before new ones are dropped
+    #[serde(default = "default_kafka_queue_size")]
+    pub kafka_queue_size: usize,
+    // number of state updates buffered for each `/events` subscriber; slower ones miss updates
+    #[serde(default = "default_event_buffer_size")]
+    pub event_buffer_size: usize,
+    // size in bytes from which REST responses are compressed for clients accepting gzip or deflate
+    #[serde(default = "default_compression_threshold_bytes")]
+    pub compression_threshold_bytes: usize,
+    // static obstacles no seeded robot path may pass through
+    #[serde(default)]
+    pub obstacles: Vec<Region>,
+    // bounds of the warehouse floor, robots are paused before a path point outside them
+    pub grid_min_x: Option<f64>,
+    pub grid_max_x: Option<f64>,
+    pub grid_min_y: Option<f64>,
+    pub grid_max_y: Option<f64>,
+    // accumulate per-cell conflict counts for the heatmap endpoint
+    #[serde(default)]
+    pub enable_heatmap: bool,
+    // side length of a heatmap grid cell
+    #[serde(default = "default_heatmap_cell_size")]
+    pub heatmap_cell_size: f64,
+    // fraction of each heatmap count kept every round, 1.0 keeps counts forever
+    #[serde(default = "default_heatmap_decay")]
+    pub heatmap_decay: f64,
+    // escalate robots that stop acknowledging the states sent to them
+    #[serde(default)]
+    pub enable_ack_tracking: bool,
+    // number of consecutive missed acknowledgements after which a robot is escalated
+    #[serde(default = "default_ack_deadline_rounds")]
+    pub ack_deadline_rounds: u64,
+    // distance around an escalated robot within which other robots are paused defensively
+    #[serde(default)]
+    pub ack_escalation_radius: f64,
+    // number of waypoints ahead along their paths robots are checked for collisions
+    pub lookahead_steps: Option<usize>,
+    // send and store motion states as numeric codes instead of names
+    #[serde(default)]
+    pub compact_state_encoding: bool,
+    // encoding of robot states exchanged with the robots: json | msgpack
+    #[serde(default)]
+    pub wire_format: WireFormat,
+    // log the states the monitor would send instead of publishing and storing them
+    #[serde(default)]
+    pub dry_run: bool,
+    // how long to wait before reconnecting to the hub after the connection is lost, in milliseconds
+    #[serde(default = "default_reconnect_backoff_ms")]
+    pub reconnect_backoff_ms: u64,
+    // interval between two robot updates in milliseconds, over which reported velocities are swept
+    #[serde(default = "default_tick_interval_ms")]
+    pub tick_interval_ms: u64,
+}
+
+/// [Region] defines an axis-aligned rectangular area of the arena.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    // lower x bound of the region
+    pub x_min: f64,
+    // lower y bound of the region
+    pub y_min: f64,
+    // upper x bound of the region
+    pub x_max: f64,
+    // upper y bound of the region
+    pub y_max: f64,
+}
+
+/// [Zone] defines a named area of the arena with a maximum number of robots allowed inside.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    // name of the zone
+    pub name: String,
+    // area covered by the zone
+    pub area: Region,
+    // maximum number of robots allowed inside the zone at once
+    pub max_occupancy: usize,
+}
+
+/// [ZoneQueue] defines the queue the robots of a zone of the facility publish their states to.
+/// Robots in different zones are resolved independently and never conflict.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneQueue {
+    // name of the zone, as configured on its robots
+    pub zone: String,
+    // name of the queue, `rpc_queue.<zone>` by default
+    pub queue_name: Option<String>,
+}
+
+impl ZoneQueue {
+    /// `queue_name` returns the name of the zone's queue, where robots configured with the zone
+    /// publish by default.
+    pub(crate) fn queue_name(&self) -> String {
+        self.queue_name
+            .clone()
+            .unwrap_or_else(|| format!("rpc_queue.{}", self.zone))
+    }
+}
+
+impl Region {
+    /// `contains` checks if the point (x, y) lies within the region.
+    pub(crate) fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x_min && x <= self.x_max && y >= self.y_min && y <= self.y_max
+    }
+}
+
+fn default_dashboard_window() -> usize {
+    100
+}
+
+fn default_dashboard_top_conflicts() -> usize {
+    5
+}
+
+fn default_low_battery_threshold() -> f64 {
+    20.0
+}
+
+fn default_position_epsilon() -> f64 {
+    1e-9
+}
+
+fn default_confidence_inflation() -> f64 {
+    1.0
+}
+
+fn default_backpressure_latency_ms() -> f64 {
+    50.0
+}
+
+fn default_base_publish_interval_ms() -> u64 {
+    10
+}
+
+fn default_max_publish_interval_ms() -> u64 {
+    1000
+}
+
+fn default_correlation_id_window() -> usize {
+    1024
+}
+
+fn default_utilization_window() -> usize {
+    100
+}
+
+fn default_slow_start_ms() -> u64 {
+    5000
+}
+
+fn default_slow_start_rate() -> u32 {
+    50
+}
+
+fn default_deadlock_abort_ms() -> u64 {
+    10000
 }
 
-/// `load_config` loads collision monitoring configuration into memory.
+fn default_far_check_every() -> u64 {
+    5
+}
+
+fn default_far_distance() -> f64 {
+    10.0
+}
+
+fn default_kafka_topic() -> String {
+    "monitor-decisions".to_string()
+}
+
+fn default_kafka_queue_size() -> usize {
+    1024
+}
+
+fn default_event_buffer_size() -> usize {
+    256
+}
+
+fn default_compression_threshold_bytes() -> usize {
+    1024
+}
+
+fn default_heatmap_cell_size() -> f64 {
+    1.0
+}
+
+fn default_heatmap_decay() -> f64 {
+    0.999
+}
+
+fn default_ack_deadline_rounds() -> u64 {
+    3
+}
+
+fn default_tick_interval_ms() -> u64 {
+    10
+}
+
+fn default_max_resolution_iterations() -> usize {
+    100
+}
+
+fn default_reconnect_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_freeze_snapshot_path() -> String {
+    "/tmp/monitor/fleet_snapshot.json".to_string()
+}
+
+/// `parse_version` splits a client version such as "v0.2.1-rc.1+build.5" into its numeric release
+/// parts, dropping the pre-release and build suffixes.
+pub(crate) fn parse_version(version: &str) -> std::result::Result<Vec<u64>, String> {
+    let release = version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default();
+
+    release
+        .split('.')
+        .map(|part| {
+            part.parse::<u64>()
+                .map_err(|_| format!("{:?} is not a valid version", version))
+        })
+        .collect()
+}
+
+/// [Units] defines the unit of length a configuration file is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Units {
+    /// meters, the unit the monitor works in
+    #[default]
+    #[serde(rename = "m")]
+    Meters,
+    /// centimeters
+    #[serde(rename = "cm")]
+    Centimeters,
+}
+
+impl Units {
+    /// `meters_per_unit` returns the length of one unit in meters.
+    pub(crate) fn meters_per_unit(self) -> f64 {
+        match self {
+            Units::Meters => 1.0,
+            Units::Centimeters => 0.01,
+        }
+    }
+}
+
+/// [WireFormat] defines how robot states are encoded in the messages between robots and the
+/// monitor. Both sides must be configured with the same one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    /// JSON text
+    #[default]
+    Json,
+    /// MessagePack, a more compact binary encoding
+    Msgpack,
+}
+
+impl WireFormat {
+    /// `encode` serializes `value` in the wire format. MessagePack keeps field names, so that
+    /// fields left out when empty are understood on the other side.
+    pub(crate) fn encode<T: serde::Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            WireFormat::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            WireFormat::Msgpack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// `decode` deserializes a value encoded in the wire format.
+    pub(crate) fn decode<T: serde::de::DeserializeOwned>(self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            WireFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            WireFormat::Msgpack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// [SeedCollisionPolicy] defines how the monitor reacts to seed states that already collide.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedCollisionPolicy {
+    /// refuse to start and report the offending device pairs
+    #[default]
+    Refuse,
+    /// warn and advance the offending robots along their own paths until they are clear
+    Nudge,
+}
+
+/// [DeadlockDetector] defines how the monitor decides that robots are deadlocked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlockDetector {
+    /// resolve deadlocks from the conflicts detected within a round
+    #[default]
+    Conflicts,
+    /// declare a deadlock once robots have stayed paused in place past `deadlock_abort_ms`
+    Timeout,
+}
+
+/// [ResolutionStrategy] defines how the monitor resolves a conflict between two moving robots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionStrategy {
+    /// pause the whole fleet as soon as robots conflict
+    #[default]
+    PauseBoth,
+    /// pause the lower-priority robot and let the other proceed; ties pause both
+    PauseLowerPriority,
+    /// pause the robot whose state is newer and let the other proceed; ties pause both
+    PauseNewer,
+    /// step the lower-priority robot aside to an adjacent free cell; if there is none, pause both
+    Yield,
+}
+
+impl CollisionMonitorConfig {
+    /// `normalize_units` converts every length of the configuration, the robot dimensions, distances
+    /// and region bounds, from `units` to meters, so that the monitor only ever works in meters.
+    /// `units` is kept, as robot states are still exchanged in it. It must only be called once.
+    pub(crate) fn normalize_units(&mut self) {
+        let scale = self.units.meters_per_unit();
+        if scale == 1.0 {
+            return;
+        }
+
+        for length in [
+            &mut self.width,
+            &mut self.height,
+            &mut self.safety_margin,
+            &mut self.emergency_stop_radius,
+            &mut self.ack_escalation_radius,
+            &mut self.far_distance,
+            &mut self.heatmap_cell_size,
+            &mut self.position_epsilon,
+        ] {
+            *length *= scale;
+        }
+
+        let regions = self
+            .no_stop_regions
+            .iter_mut()
+            .chain(self.obstacles.iter_mut())
+            .chain(self.zones.iter_mut().map(|zone| &mut zone.area));
+        for bound in [
+            &mut self.near_miss_distance,
+            &mut self.grid_min_x,
+            &mut self.grid_max_x,
+            &mut self.grid_min_y,
+            &mut self.grid_max_y,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *bound *= scale;
+        }
+
+        for region in regions {
+            region.x_min *= scale;
+            region.y_min *= scale;
+            region.x_max *= scale;
+            region.y_max *= scale;
+        }
+    }
+
+    /// `validate` rejects configuration values the monitor cannot run with, naming the offending
+    /// field.
+    pub(crate) fn validate(&self) -> std::result::Result<(), String> {
+        if self.width <= 0.0 {
+            return Err(format!("width must be positive, got {}", self.width));
+        }
+        if self.height <= 0.0 {
+            return Err(format!("height must be positive, got {}", self.height));
+        }
+        match (self.num_agents, self.batch_window_ms) {
+            (Some(0), _) => return Err("num_agents must be at least 1".to_string()),
+            (None, None) => {
+                return Err("num_agents is required unless batch_window_ms is set".to_string())
+            }
+            _ => {}
+        }
+        if self.batch_window_ms == Some(0) {
+            return Err("batch_window_ms must be at least 1".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.low_battery_threshold) {
+            return Err(format!(
+                "low_battery_threshold must be between 0 and 100, got {}",
+                self.low_battery_threshold
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.min_operating_soc) {
+            return Err(format!(
+                "min_operating_soc must be between 0 and 100, got {}",
+                self.min_operating_soc
+            ));
+        }
+        if self.safety_margin < 0.0 {
+            return Err(format!(
+                "safety_margin must not be negative, got {}",
+                self.safety_margin
+            ));
+        }
+        if let Some(min_client_version) = &self.min_client_version {
+            parse_version(min_client_version)
+                .map_err(|error| format!("min_client_version: {}", error))?;
+        }
+        if self.position_epsilon <= 0.0 {
+            return Err(format!(
+                "position_epsilon must be positive, got {}",
+                self.position_epsilon
+            ));
+        }
+        if let Some(near_miss_distance) = self.near_miss_distance.filter(|&d| d < 0.0) {
+            return Err(format!(
+                "near_miss_distance must not be negative, got {}",
+                near_miss_distance
+            ));
+        }
+        if self.max_resolution_iterations == 0 {
+            return Err("max_resolution_iterations must be at least 1".to_string());
+        }
+        if self.event_buffer_size == 0 {
+            return Err("event_buffer_size must be at least 1".to_string());
+        }
+        for (axis, min, max) in [
+            ("x", self.grid_min_x, self.grid_max_x),
+            ("y", self.grid_min_y, self.grid_max_y),
+        ] {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min >= max {
+                    return Err(format!(
+                        "grid_min_{} must be less than grid_max_{}, got {} and {}",
+                        axis, axis, min, max
+                    ));
+                }
+            }
+        }
+        for (idx, zone_queue) in self.zone_queues.iter().enumerate() {
+            if self.zone_queues[..idx]
+                .iter()
+                .any(|other| other.zone == zone_queue.zone)
+            {
+                return Err(format!(
+                    "zone_queues must not repeat zone {:?}",
+                    zone_queue.zone
+                ));
+            }
+        }
+        if self.deduplicate_correlation_ids && self.dedup_window == 0 {
+            return Err("dedup_window must be at least 1".to_string());
+        }
+        if self.hostname.trim().is_empty() {
+            return Err("hostname must not be empty".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// `apply_env_overrides` overlays the `MONITOR_*` environment variables set on top of the
+    /// values parsed from the file, returning the fields they overrode.
+    pub(crate) fn apply_env_overrides(&mut self) -> std::result::Result<Vec<String>, String> {
+        let mut overridden = Vec::new();
+        env_override(
+            "MONITOR_HOSTNAME",
+            "hostname",
+            &mut self.hostname,
+            &mut overridden,
+        )?;
+        env_override(
+            "MONITOR_HUB_PORT",
+            "hub_listening_port",
+            &mut self.hub_listening_port,
+            &mut overridden,
+        )?;
+        env_override(
+            "MONITOR_HUB_USER",
+            "queue_hub_user",
+            &mut self.queue_hub_user,
+            &mut overridden,
+        )?;
+        env_override(
+            "MONITOR_HUB_PW",
+            "queue_hub_pw",
+            &mut self.queue_hub_pw,
+            &mut overridden,
+        )?;
+        if std::env::var("MONITOR_NUM_AGENTS").is_ok() {
+            let mut num_agents = 0;
+            env_override(
+                "MONITOR_NUM_AGENTS",
+                "num_agents",
+                &mut num_agents,
+                &mut overridden,
+            )?;
+            self.num_agents = Some(num_agents);
+        }
+        env_override(
+            "MONITOR_LISTENING_PORT",
+            "listening_port",
+            &mut self.listening_port,
+            &mut overridden,
+        )?;
+        env_override(
+            "MONITOR_DB_PATH",
+            "db_path",
+            &mut self.db_path,
+            &mut overridden,
+        )?;
+        env_override(
+            "MONITOR_LOGS_DIR",
+            "logs_dir",
+            &mut self.logs_dir,
+            &mut overridden,
+        )?;
+
+        Ok(overridden)
+    }
+}
+
+/// `env_override` replaces `field` with the value of the environment variable `var`, if it is set.
+fn env_override<T>(
+    var: &str,
+    name: &str,
+    field: &mut T,
+    overridden: &mut Vec<String>,
+) -> std::result::Result<(), String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Ok(value) = std::env::var(var) {
+        *field = value
+            .parse()
+            .map_err(|e| format!("{} from {} is invalid: {}", name, var, e))?;
+        overridden.push(format!("{} ({})", name, var));
+    }
+
+    Ok(())
+}
+
+/// `load_config` loads collision monitoring configuration into memory, with environment variables
+/// taking precedence over the file. It also returns the fields overridden from the environment.
 pub(crate) fn load_config(
     config_path: &str,
-) -> std::result::Result<CollisionMonitorConfig, String> {
+) -> std::result::Result<(CollisionMonitorConfig, Vec<String>), String> {
     match fs::read_to_string(config_path) {
-        Ok(file_str) => {
-            let ret: CollisionMonitorConfig = match toml::from_str(&file_str) {
-                Ok(r) => r,
-                Err(_) => return Err(format!("config.toml is not a proper toml file.")),
-            };
-            return Ok(ret);
-        }
-        Err(e) => {
-            return Err(format!(
-                "Error: Config file (config.toml) is not found in the correct directory. 
+        Ok(file_str) => parse_config(&file_str),
+        Err(e) => Err(format!(
+            "Error: Config file (config.toml) is not found in the correct directory. 
         Please ensure that the configuration directory: \"{}\" exists. ERROR: {:?}",
-                config_path, e
-            ))
-        }
+            config_path, e
+        )),
+    }
+}
+
+/// `validate_config` loads the configuration at `config_path` the way the service does on start,
+/// without connecting to anything, and returns why it would be rejected, if at all.
+pub(crate) fn validate_config(config_path: &str) -> std::result::Result<(), String> {
+    load_config(config_path).map(|_| ())
+}
+
+/// `parse_config` parses the contents of a configuration file, overlays the environment and
+/// validates the result.
+fn parse_config(
+    file_str: &str,
+) -> std::result::Result<(CollisionMonitorConfig, Vec<String>), String> {
+    let mut ret: CollisionMonitorConfig = match toml::from_str(file_str) {
+        Ok(r) => r,
+        Err(_) => return Err("config.toml is not a proper toml file.".to_string()),
     };
+    let overridden = ret.apply_env_overrides()?;
+    ret.normalize_units();
+    ret.validate()?;
+
+    Ok((ret, overridden))
+}
+
+/// `test_config` returns a small configuration used across unit tests.
+#[cfg(test)]
+pub(crate) fn test_config() -> CollisionMonitorConfig {
+    CollisionMonitorConfig {
+        width: 1.0,
+        height: 1.0,
+        units: Units::Meters,
+        queue_hub_pw: String::new(),
+        queue_hub_user: String::new(),
+        hostname: String::new(),
+        hub_listening_port: 5672,
+        use_tls: false,
+        ca_cert_path: None,
+        num_agents: Some(2),
+        batch_window_ms: None,
+        stale_after_ms: None,
+        logs_dir: String::new(),
+        listening_port: 9877,
+        db_path: String::new(),
+        seed_states_path: None,
+        seed_collision_policy: SeedCollisionPolicy::Refuse,
+        enable_dashboard: false,
+        dashboard_window: default_dashboard_window(),
+        dashboard_top_conflicts: default_dashboard_top_conflicts(),
+        low_battery_threshold: default_low_battery_threshold(),
+        min_operating_soc: 0.0,
+        confidence_inflation: default_confidence_inflation(),
+        safety_margin: 0.0,
+        near_miss_distance: None,
+        position_epsilon: default_position_epsilon(),
+        no_stop_regions: Vec::new(),
+        enable_backpressure: false,
+        backpressure_latency_ms: default_backpressure_latency_ms(),
+        base_publish_interval_ms: default_base_publish_interval_ms(),
+        max_publish_interval_ms: default_max_publish_interval_ms(),
+        incidents_db_path: None,
+        enable_fleet_freeze: false,
+        freeze_snapshot_path: default_freeze_snapshot_path(),
+        track_correlation_ids: false,
+        correlation_id_window: default_correlation_id_window(),
+        correlation_retry_rounds: 0,
+        deduplicate_correlation_ids: false,
+        dedup_window: default_correlation_id_window(),
+        min_client_version: None,
+        enable_utilization_stats: false,
+        utilization_window: default_utilization_window(),
+        enable_collision_check: false,
+        zones: Vec::new(),
+        zone_queues: Vec::new(),
+        position_smoothing: None,
+        enable_slow_start: false,
+        slow_start_ms: default_slow_start_ms(),
+        slow_start_rate: default_slow_start_rate(),
+        deadlock_detector: DeadlockDetector::Conflicts,
+        resolution_strategy: ResolutionStrategy::PauseBoth,
+        max_resolution_iterations: default_max_resolution_iterations(),
+        deadlock_abort_ms: default_deadlock_abort_ms(),
+        record_history: false,
+        history_limit: None,
+        emergency_stop_radius: 0.0,
+        enable_conflict_graph: false,
+        enable_admin_routes: false,
+        wal_path: None,
+        enable_check_downsampling: false,
+        far_check_every: default_far_check_every(),
+        far_distance: default_far_distance(),
+        kafka_brokers: None,
+        kafka_topic: default_kafka_topic(),
+        kafka_queue_size: default_kafka_queue_size(),
+        event_buffer_size: default_event_buffer_size(),
+        compression_threshold_bytes: default_compression_threshold_bytes(),
+        obstacles: Vec::new(),
+        grid_min_x: None,
+        grid_max_x: None,
+        grid_min_y: None,
+        grid_max_y: None,
+        enable_heatmap: false,
+        heatmap_cell_size: default_heatmap_cell_size(),
+        heatmap_decay: default_heatmap_decay(),
+        enable_ack_tracking: false,
+        ack_deadline_rounds: default_ack_deadline_rounds(),
+        ack_escalation_radius: 0.0,
+        lookahead_steps: None,
+        compact_state_encoding: false,
+        wire_format: WireFormat::Json,
+        dry_run: false,
+        reconnect_backoff_ms: default_reconnect_backoff_ms(),
+        tick_interval_ms: default_tick_interval_ms(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// `ENV_LOCK` serializes the tests reading the process environment.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn env_lock() -> MutexGuard<'static, ()> {
+        ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    const VALID_CONFIG: &str = r#"
+        width = 1000.0
+        height = 800.0
+        queue_hub_pw = "guest"
+        queue_hub_user = "guest"
+        hostname = "rabbitmq"
+        hub_listening_port = 5672
+        num_agents = 2
+        logs_dir = "/tmp/monitor/logs"
+        listening_port = 9000
+        db_path = "/tmp/monitor/db"
+    "#;
+
+    #[test]
+    fn test_parse_config_accepts_valid_values() {
+        let _env = env_lock();
+        assert!(parse_config(VALID_CONFIG).is_ok());
+    }
+
+    #[test]
+    fn test_parse_config_normalizes_units() {
+        let _env = env_lock();
+        let lengths = |config: &CollisionMonitorConfig| {
+            (
+                config.width,
+                config.height,
+                config.safety_margin,
+                config.emergency_stop_radius,
+                config.position_epsilon,
+                config.obstacles.clone(),
+            )
+        };
+
+        let (meters, _) = parse_config(&format!(
+            "{}\n{}",
+            "units = \"m\"\nsafety_margin = 0.5\nemergency_stop_radius = 2.0\nposition_epsilon = 0.001",
+            VALID_CONFIG.replace(
+                "db_path",
+                "obstacles = [{ x_min = 1.0, y_min = 2.0, x_max = 3.5, y_max = 4.0 }]\ndb_path"
+            )
+        ))
+        .unwrap();
+        let (centimeters, _) = parse_config(&format!(
+            "{}\n{}",
+            "units = \"cm\"\nsafety_margin = 50.0\nemergency_stop_radius = 200.0\nposition_epsilon = 0.1",
+            VALID_CONFIG
+                .replace("width = 1000.0", "width = 100000.0")
+                .replace("height = 800.0", "height = 80000.0")
+                .replace("db_path", "obstacles = [{ x_min = 100.0, y_min = 200.0, x_max = 350.0, y_max = 400.0 }]\ndb_path")
+        ))
+        .unwrap();
+
+        assert_eq!(lengths(&centimeters), lengths(&meters));
+        assert_eq!(centimeters.units, Units::Centimeters);
+    }
+
+    #[test]
+    fn test_validate_config_reports_errors() {
+        let _env = env_lock();
+        let path = |name: &str| {
+            std::env::temp_dir().join(format!(
+                "monitor_config_{}_{}.toml",
+                std::process::id(),
+                name
+            ))
+        };
+        let (good, bad) = (path("good"), path("bad"));
+        std::fs::write(&good, VALID_CONFIG).unwrap();
+        std::fs::write(
+            &bad,
+            VALID_CONFIG.replace("num_agents = 2", "num_agents = 0"),
+        )
+        .unwrap();
+
+        assert_eq!(validate_config(good.to_str().unwrap()), Ok(()));
+        assert_eq!(
+            validate_config(bad.to_str().unwrap()),
+            Err("num_agents must be at least 1".to_string())
+        );
+        assert!(validate_config("/nonexistent/config.toml").is_err());
+
+        std::fs::remove_file(good).unwrap();
+        std::fs::remove_file(bad).unwrap();
+    }
+
+    #[test]
+    fn test_parse_config_rejects_invalid_values() {
+        let _env = env_lock();
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("width = 1000.0", "width = -1.0")).unwrap_err(),
+            "width must be positive, got -1"
+        );
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("height = 800.0", "height = 0.0")).unwrap_err(),
+            "height must be positive, got 0"
+        );
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("num_agents = 2", "num_agents = 0")).unwrap_err(),
+            "num_agents must be at least 1"
+        );
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("num_agents = 2", "")).unwrap_err(),
+            "num_agents is required unless batch_window_ms is set"
+        );
+        let (windowed, _) = parse_config(&format!(
+            "batch_window_ms = 500\n{}",
+            VALID_CONFIG.replace("num_agents = 2", "")
+        ))
+        .unwrap();
+        assert_eq!(windowed.num_agents, None);
+        assert_eq!(
+            parse_config(&format!("batch_window_ms = 0\n{}", VALID_CONFIG)).unwrap_err(),
+            "batch_window_ms must be at least 1"
+        );
+        assert_eq!(
+            parse_config(&format!("low_battery_threshold = 120.0\n{}", VALID_CONFIG)).unwrap_err(),
+            "low_battery_threshold must be between 0 and 100, got 120"
+        );
+        assert_eq!(
+            parse_config(&format!("safety_margin = -0.5\n{}", VALID_CONFIG)).unwrap_err(),
+            "safety_margin must not be negative, got -0.5"
+        );
+        assert_eq!(
+            parse_config(&format!(
+                "min_client_version = \"0.2.beta\"\n{}",
+                VALID_CONFIG
+            ))
+            .unwrap_err(),
+            "min_client_version: \"0.2.beta\" is not a valid version"
+        );
+        assert_eq!(
+            parse_config(&format!("max_resolution_iterations = 0\n{}", VALID_CONFIG)).unwrap_err(),
+            "max_resolution_iterations must be at least 1"
+        );
+        assert_eq!(
+            parse_config(&format!("event_buffer_size = 0\n{}", VALID_CONFIG)).unwrap_err(),
+            "event_buffer_size must be at least 1"
+        );
+        assert_eq!(
+            parse_config(&format!(
+                "grid_min_y = 5.0\ngrid_max_y = 5.0\n{}",
+                VALID_CONFIG
+            ))
+            .unwrap_err(),
+            "grid_min_y must be less than grid_max_y, got 5 and 5"
+        );
+        assert_eq!(
+            parse_config(&format!(
+                "deduplicate_correlation_ids = true\ndedup_window = 0\n{}",
+                VALID_CONFIG
+            ))
+            .unwrap_err(),
+            "dedup_window must be at least 1"
+        );
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("\"rabbitmq\"", "\"\"")).unwrap_err(),
+            "hostname must not be empty"
+        );
+    }
+
+    #[test]
+    fn test_parse_config_applies_env_overrides() {
+        let _env = env_lock();
+        std::env::set_var("MONITOR_HOSTNAME", "broker");
+        std::env::set_var("MONITOR_HUB_PORT", "5673");
+        std::env::set_var("MONITOR_NUM_AGENTS", "5");
+
+        let (config, overridden) = parse_config(VALID_CONFIG).unwrap();
+        assert_eq!(config.hostname, "broker");
+        assert_eq!(config.hub_listening_port, 5673);
+        assert_eq!(config.num_agents, Some(5));
+        assert_eq!(config.queue_hub_user, "guest");
+        assert_eq!(config.dashboard_window, default_dashboard_window());
+        assert_eq!(
+            overridden,
+            vec![
+                "hostname (MONITOR_HOSTNAME)",
+                "hub_listening_port (MONITOR_HUB_PORT)",
+                "num_agents (MONITOR_NUM_AGENTS)",
+            ]
+        );
+
+        std::env::set_var("MONITOR_NUM_AGENTS", "many");
+        let error = parse_config(VALID_CONFIG).unwrap_err();
+
+        std::env::remove_var("MONITOR_HOSTNAME");
+        std::env::remove_var("MONITOR_HUB_PORT");
+        std::env::remove_var("MONITOR_NUM_AGENTS");
+
+        assert_eq!(
+            error,
+            "num_agents from MONITOR_NUM_AGENTS is invalid: invalid digit found in string"
+        );
+    }
 }
@@ -0,0 +1,110 @@
+use amiquip::{Auth, Connection, ConnectionOptions, ConnectionTuning};
+use std::{fmt, fs, net::TcpStream};
+
+use crate::config::CollisionMonitorConfig;
+
+/// [ConnectionError] defines why talking to the hub failed.
+#[derive(Debug)]
+pub(crate) enum ConnectionError {
+    /// the AMQP connection to the hub failed
+    Amqp(amiquip::Error),
+    /// the TLS connection could not be set up
+    Tls(String),
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::Amqp(e) => write!(f, "Connection to hub failed: {}", e),
+            ConnectionError::Tls(e) => write!(f, "TLS setup failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionError {}
+
+impl From<amiquip::Error> for ConnectionError {
+    fn from(e: amiquip::Error) -> Self {
+        ConnectionError::Amqp(e)
+    }
+}
+
+pub(crate) type Result<T> = std::result::Result<T, ConnectionError>;
+
+/// `connect` opens a connection to the hub. With `use_tls` the connection goes over `amqps://`,
+/// trusting the CA at `ca_cert_path` if one is configured and the system roots otherwise.
+pub(crate) fn connect(config: &CollisionMonitorConfig) -> Result<Connection> {
+    if !config.use_tls {
+        return Ok(Connection::insecure_open(&format!(
+            "amqp://{}:{}@{}:{}",
+            config.queue_hub_user, config.queue_hub_pw, config.hostname, config.hub_listening_port
+        ))?);
+    }
+
+    let ca_cert_path = match &config.ca_cert_path {
+        Some(path) => path,
+        None => {
+            return Ok(Connection::open(&format!(
+                "amqps://{}:{}@{}:{}",
+                config.queue_hub_user,
+                config.queue_hub_pw,
+                config.hostname,
+                config.hub_listening_port
+            ))?)
+        }
+    };
+
+    let connector = tls_connector(ca_cert_path)?;
+    let stream = TcpStream::connect(format!("{}:{}", config.hostname, config.hub_listening_port))
+        .and_then(|stream| stream.set_nonblocking(true).map(|_| stream))
+        .map_err(|e| {
+            ConnectionError::Tls(format!(
+                "could not reach {}:{}: {}",
+                config.hostname, config.hub_listening_port, e
+            ))
+        })?;
+
+    Ok(Connection::open_tls_stream(
+        connector,
+        &config.hostname,
+        mio::net::TcpStream::from_std(stream),
+        ConnectionOptions::<Auth>::default().auth(Auth::Plain {
+            username: config.queue_hub_user.clone(),
+            password: config.queue_hub_pw.clone(),
+        }),
+        ConnectionTuning::default(),
+    )?)
+}
+
+/// `tls_connector` builds a TLS connector trusting the PEM-encoded CA certificate at `ca_cert_path`.
+fn tls_connector(ca_cert_path: &str) -> Result<native_tls::TlsConnector> {
+    let pem = fs::read(ca_cert_path).map_err(|e| {
+        ConnectionError::Tls(format!(
+            "could not read CA certificate {}: {}",
+            ca_cert_path, e
+        ))
+    })?;
+    let certificate = native_tls::Certificate::from_pem(&pem).map_err(|e| {
+        ConnectionError::Tls(format!("invalid CA certificate {}: {}", ca_cert_path, e))
+    })?;
+
+    native_tls::TlsConnector::builder()
+        .add_root_certificate(certificate)
+        .build()
+        .map_err(|e| ConnectionError::Tls(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_connector_requires_readable_ca_certificate() {
+        match tls_connector("/nonexistent/ca.pem") {
+            Err(ConnectionError::Tls(message)) => {
+                assert!(message.starts_with("could not read CA certificate /nonexistent/ca.pem"))
+            }
+            _ => panic!("expected a TLS setup error"),
+        }
+    }
+}
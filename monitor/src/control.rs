@@ -0,0 +1,110 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use warp::ws::{Message, WebSocket};
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [ControlCommand] defines a command sent by an operator console over `/ws`, such as
+/// `{"cmd":"pause","id":"robot1"}`.
+#[derive(Debug, Deserialize)]
+struct ControlCommand {
+    // state the robot is told to take: pause | resume
+    cmd: MotionState,
+    // device id of the robot
+    id: String,
+}
+
+/// `session` serves one operator console connection: every text frame received is applied as a
+/// command and answered with an acknowledgement or an error, while the state updates sent to the
+/// robots are streamed back as they happen. It returns once the console disconnects.
+pub(crate) async fn session(
+    socket: WebSocket,
+    db: Arc<sled::Db>,
+    commands: Arc<Mutex<HashMap<String, MotionState>>>,
+    mut updates: broadcast::Receiver<Robot>,
+) {
+    let (mut outgoing, mut incoming) = socket.split();
+
+    loop {
+        let frame = tokio::select! {
+            message = incoming.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(message)) => match message.to_str() {
+                    Ok(text) => apply_command(&db, &commands, text),
+                    // pings and binary frames carry no command.
+                    Err(_) => continue,
+                },
+                _ => break,
+            },
+            update = updates.recv() => match update {
+                Ok(robot) => serde_json::json!({ "type": "state", "state": robot }),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Control console lagged behind, skipping {} updates", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if outgoing
+            .send(Message::text(frame.to_string()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// `apply_command` validates a command, pauses or resumes the robot's stored state and hands the
+/// command to the RPC server, which applies it to the robot's next state. It returns the frame
+/// acknowledging the command, or the error it was rejected with.
+pub(crate) fn apply_command(
+    db: &sled::Db,
+    commands: &Mutex<HashMap<String, MotionState>>,
+    text: &str,
+) -> serde_json::Value {
+    let error = |message: String| serde_json::json!({ "type": "error", "message": message });
+
+    let command: ControlCommand = match serde_json::from_str(text) {
+        Ok(command) => command,
+        Err(e) => return error(format!("invalid command: {}", e)),
+    };
+    if !matches!(command.cmd, MotionState::Pause | MotionState::Resume) {
+        return error(format!("unsupported command {}", command.cmd));
+    }
+
+    let mut robot: Robot = match db.get(&command.id) {
+        Ok(Some(record)) => match serde_json::from_slice(&record) {
+            Ok(robot) => robot,
+            Err(e) => return error(format!("could not read the state of {}: {}", command.id, e)),
+        },
+        Ok(None) => return error(format!("no record exists for {}", command.id)),
+        Err(e) => return error(format!("could not read the state of {}: {}", command.id, e)),
+    };
+
+    robot.state = command.cmd;
+    let stored = serde_json::to_vec(&robot)
+        .map_err(|e| e.to_string())
+        .and_then(|record| {
+            db.insert(&command.id, record)
+                .map_err(|e| format!("{:?}", e))
+        });
+    if let Err(e) = stored {
+        return error(format!(
+            "could not store the state of {}: {}",
+            command.id, e
+        ));
+    }
+
+    commands
+        .lock()
+        .expect("commands lock poisoned")
+        .insert(command.id.clone(), command.cmd);
+    log::info!("Operator console set {} to {}", command.id, command.cmd);
+
+    serde_json::json!({ "type": "ack", "cmd": command.cmd, "id": command.id })
+}
@@ -0,0 +1,130 @@
+use std::collections::{HashMap, VecDeque};
+
+/// [CorrelationTracker] remembers a bounded window of recently seen correlation ids and the round
+/// each one arrived in, so that clients reusing ids across rounds can be detected.
+#[derive(Debug)]
+pub(crate) struct CorrelationTracker {
+    // maximum number of correlation ids remembered
+    capacity: usize,
+    // number of rounds within which a repeated id is treated as a legitimate retry
+    retry_rounds: u64,
+    // correlation ids in arrival order, oldest first
+    order: VecDeque<String>,
+    // round each remembered correlation id was first seen in
+    first_seen: HashMap<String, u64>,
+}
+
+impl CorrelationTracker {
+    /// `new` creates a tracker remembering at most `capacity` correlation ids.
+    pub(crate) fn new(capacity: usize, retry_rounds: u64) -> Self {
+        CorrelationTracker {
+            capacity,
+            retry_rounds,
+            order: VecDeque::with_capacity(capacity),
+            first_seen: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// `observe` records a correlation id seen in `round`. It returns the round the id was first
+    /// seen in when it is reused outside the retry window.
+    pub(crate) fn observe(&mut self, correlation_id: &str, round: u64) -> Option<u64> {
+        if let Some(&first_round) = self.first_seen.get(correlation_id) {
+            if round.saturating_sub(first_round) > self.retry_rounds {
+                return Some(first_round);
+            }
+            return None;
+        }
+
+        if self.capacity == 0 {
+            return None;
+        }
+
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.first_seen.remove(&oldest);
+            }
+        }
+
+        self.order.push_back(correlation_id.to_string());
+        self.first_seen.insert(correlation_id.to_string(), round);
+
+        None
+    }
+}
+
+/// [RecentCorrelationIds] defines a least-recently-used set of correlation ids, used to drop
+/// states a robot publishes again under the same id, e.g. after a client-side timeout.
+#[derive(Debug)]
+pub(crate) struct RecentCorrelationIds {
+    // maximum number of correlation ids remembered
+    capacity: usize,
+    // correlation ids from least to most recently seen
+    order: VecDeque<String>,
+}
+
+impl RecentCorrelationIds {
+    /// `new` creates a set remembering at most `capacity` correlation ids.
+    pub(crate) fn new(capacity: usize) -> Self {
+        RecentCorrelationIds {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// `observe` marks a correlation id as the most recently seen one, evicting the least recently
+    /// seen id when the set is full. It returns whether the id was already in the set.
+    pub(crate) fn observe(&mut self, correlation_id: &str) -> bool {
+        if let Some(position) = self.order.iter().position(|id| id == correlation_id) {
+            if let Some(id) = self.order.remove(position) {
+                self.order.push_back(id);
+            }
+            return true;
+        }
+
+        if self.capacity == 0 {
+            return false;
+        }
+
+        if self.order.len() == self.capacity {
+            self.order.pop_front();
+        }
+        self.order.push_back(correlation_id.to_string());
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlation_tracker_flags_reuse_across_rounds() {
+        let mut tracker = CorrelationTracker::new(2, 0);
+
+        assert_eq!(tracker.observe("corr-1", 0), None);
+        // a retry within the same round is expected.
+        assert_eq!(tracker.observe("corr-1", 0), None);
+        // the same id showing up in a later round is a client bug.
+        assert_eq!(tracker.observe("corr-1", 1), Some(0));
+
+        // ids falling out of the window are forgotten.
+        assert_eq!(tracker.observe("corr-2", 1), None);
+        assert_eq!(tracker.observe("corr-3", 1), None);
+        assert_eq!(tracker.observe("corr-1", 2), None);
+    }
+
+    #[test]
+    fn test_recent_correlation_ids_evicts_least_recently_seen() {
+        let mut recent = RecentCorrelationIds::new(2);
+
+        assert!(!recent.observe("corr-1"));
+        assert!(!recent.observe("corr-2"));
+        assert!(recent.observe("corr-1"));
+
+        // corr-2 is now the least recently seen id and makes room for corr-3.
+        assert!(!recent.observe("corr-3"));
+        assert!(recent.observe("corr-1"));
+        assert!(!recent.observe("corr-2"));
+    }
+}
@@ -0,0 +1,122 @@
+use serde_derive::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::collision_monitor::{CollisionMonitor, MotionState, Robot};
+use crate::config::CollisionMonitorConfig;
+
+/// [Dashboard] accumulates per-round data from the RPC server for the `/dashboard` endpoint.
+#[derive(Debug, Default)]
+pub(crate) struct Dashboard {
+    // latencies of the most recent rounds in milliseconds
+    round_latencies_ms: VecDeque<f64>,
+    // number of conflicts each device took part in
+    conflict_counts: HashMap<String, u64>,
+    // groups of devices left paused by the last round
+    deadlock_groups: Vec<Vec<String>>,
+}
+
+impl Dashboard {
+    /// `record_round` stores the latency and conflicts of a completed round.
+    pub(crate) fn record_round(
+        &mut self,
+        latency: Duration,
+        conflicting_pairs: &[(String, String)],
+        updated_states: &[Robot],
+        window: usize,
+    ) {
+        self.round_latencies_ms
+            .push_back(latency.as_secs_f64() * 1000.0);
+        while self.round_latencies_ms.len() > window {
+            self.round_latencies_ms.pop_front();
+        }
+
+        for (device_a, device_b) in conflicting_pairs {
+            *self.conflict_counts.entry(device_a.clone()).or_insert(0) += 1;
+            *self.conflict_counts.entry(device_b.clone()).or_insert(0) += 1;
+        }
+
+        self.deadlock_groups = CollisionMonitor::deadlock_cycles(conflicting_pairs, updated_states);
+    }
+
+    /// `view` assembles the dashboard blob from the recorded rounds and the stored robot states.
+    pub(crate) fn view(&self, robots: &[Robot], config: &CollisionMonitorConfig) -> DashboardView {
+        let resumed = robots
+            .iter()
+            .filter(|robot| robot.state == MotionState::Resume)
+            .count();
+
+        let mut top_conflict_robots: Vec<ConflictCount> = self
+            .conflict_counts
+            .iter()
+            .map(|(device_id, conflicts)| ConflictCount {
+                device_id: device_id.clone(),
+                conflicts: *conflicts,
+            })
+            .collect();
+        top_conflict_robots.sort_by(|a, b| {
+            b.conflicts
+                .cmp(&a.conflicts)
+                .then_with(|| a.device_id.cmp(&b.device_id))
+        });
+        top_conflict_robots.truncate(config.dashboard_top_conflicts);
+
+        let low_battery_robots = robots
+            .iter()
+            .filter(|robot| robot.battery_level < config.low_battery_threshold)
+            .map(|robot| BatteryLevel {
+                device_id: robot.device_id.clone(),
+                battery_level: robot.battery_level,
+            })
+            .collect();
+
+        DashboardView {
+            counts: FleetCounts {
+                agents: robots.len(),
+                resumed,
+                paused: robots.len() - resumed,
+            },
+            round_latencies_ms: self.round_latencies_ms.iter().copied().collect(),
+            top_conflict_robots,
+            deadlock_groups: self.deadlock_groups.clone(),
+            low_battery_robots,
+        }
+    }
+}
+
+/// [DashboardView] defines the JSON blob returned by the `/dashboard` endpoint.
+#[derive(Debug, Serialize)]
+pub(crate) struct DashboardView {
+    /// number of known agents per motion state
+    pub counts: FleetCounts,
+    /// latencies of the most recent rounds in milliseconds
+    pub round_latencies_ms: Vec<f64>,
+    /// devices which took part in the most conflicts
+    pub top_conflict_robots: Vec<ConflictCount>,
+    /// groups of devices left paused by the last round
+    pub deadlock_groups: Vec<Vec<String>>,
+    /// devices below the configured battery threshold
+    pub low_battery_robots: Vec<BatteryLevel>,
+}
+
+/// [FleetCounts] defines the number of known agents per motion state.
+#[derive(Debug, Serialize)]
+pub(crate) struct FleetCounts {
+    pub agents: usize,
+    pub resumed: usize,
+    pub paused: usize,
+}
+
+/// [ConflictCount] defines the number of conflicts a device took part in.
+#[derive(Debug, Serialize)]
+pub(crate) struct ConflictCount {
+    pub device_id: String,
+    pub conflicts: u64,
+}
+
+/// [BatteryLevel] defines the battery level reported by a device.
+#[derive(Debug, Serialize)]
+pub(crate) struct BatteryLevel {
+    pub device_id: String,
+    pub battery_level: f64,
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use crate::collision_monitor::Robot;
+
+/// [CheckDownsampler] decides which robots can skip collision checks in a round. Robots found far
+/// from every other robot are only checked fully every `every` rounds, and as soon as any robot
+/// comes within `far_distance` of them.
+#[derive(Debug)]
+pub(crate) struct CheckDownsampler {
+    // number of rounds between full checks of far robots
+    every: u64,
+    // distance to the nearest robot beyond which a robot counts as far
+    far_distance: f64,
+    // number of rounds left until the next full check
+    rounds_to_full_check: u64,
+    // device ids found far at the last full check
+    far: HashSet<String>,
+}
+
+impl CheckDownsampler {
+    /// `new` creates a downsampler checking far robots every `every` rounds.
+    pub(crate) fn new(every: u64, far_distance: f64) -> Self {
+        CheckDownsampler {
+            every: every.max(1),
+            far_distance,
+            rounds_to_full_check: 0,
+            far: HashSet::new(),
+        }
+    }
+
+    /// `skipped` starts a new round and returns the indices of the robots whose collision checks
+    /// can be skipped in it.
+    pub(crate) fn skipped(&mut self, robots: &[Robot]) -> HashSet<usize> {
+        let full_check = self.rounds_to_full_check == 0;
+        self.rounds_to_full_check = match self.rounds_to_full_check {
+            0 => self.every - 1,
+            rounds => rounds - 1,
+        };
+
+        let far_now: HashSet<usize> = (0..robots.len())
+            .filter(|&idx| {
+                robots.iter().enumerate().all(|(jdx, other)| {
+                    jdx == idx
+                        || (robots[idx].x - other.x).hypot(robots[idx].y - other.y)
+                            > self.far_distance
+                })
+            })
+            .collect();
+
+        if full_check {
+            self.far = far_now
+                .iter()
+                .map(|&idx| robots[idx].device_id.clone())
+                .collect();
+            return HashSet::new();
+        }
+
+        // a robot something has approached since the last full check is checked right away.
+        far_now
+            .into_iter()
+            .filter(|&idx| self.far.contains(&robots[idx].device_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn robot(device_id: &str, x: f64) -> Robot {
+        Robot {
+            x,
+            device_id: device_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_downsampler_skips_far_robots_until_approached() {
+        let mut downsampler = CheckDownsampler::new(3, 10.0);
+        let mut robots = vec![
+            robot("robot1", 0.0),
+            robot("robot2", 2.0),
+            robot("far", 100.0),
+        ];
+
+        // the first round checks everyone and notes the far robot.
+        assert!(downsampler.skipped(&robots).is_empty());
+        // intermediate rounds skip it.
+        assert_eq!(downsampler.skipped(&robots), HashSet::from([2]));
+
+        // as soon as another robot approaches, it is checked again.
+        robots[1].x = 95.0;
+        assert!(downsampler.skipped(&robots).is_empty());
+
+        // the next full check re-evaluates who is far.
+        robots[1].x = 2.0;
+        assert!(downsampler.skipped(&robots).is_empty());
+        assert_eq!(downsampler.skipped(&robots), HashSet::from([2]));
+    }
+}
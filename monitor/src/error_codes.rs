@@ -1,30 +1,141 @@
 use std::convert::Infallible;
-use warp::{self, http, hyper::StatusCode};
+use warp::{self, hyper::StatusCode};
 
 #[derive(Debug)]
 pub(crate) enum Error {
-    IncorrectInput,
+    /// the request can't be served, for the reason given
+    IncorrectInput(&'static str),
     IncorrectDBRecord,
     DeserializationFailure,
+    SnapshotFailure,
+    MissingHistory,
+    /// the state DB could not be read or written
+    StorageFailure,
 }
 
 impl warp::reject::Reject for Error {}
 
+impl Error {
+    /// `status` returns the HTTP status and the numeric error code the error is reported with.
+    fn status(&self) -> (StatusCode, u16) {
+        match self {
+            Error::IncorrectInput(_) => (StatusCode::BAD_REQUEST, INCORRECT_INPUT),
+            Error::IncorrectDBRecord => (StatusCode::NOT_FOUND, INCORRECT_DB_RECORD),
+            Error::DeserializationFailure => (StatusCode::BAD_REQUEST, DESERIALIZATION_FAILURE),
+            Error::SnapshotFailure => (StatusCode::INTERNAL_SERVER_ERROR, SNAPSHOT_FAILURE),
+            Error::MissingHistory => (StatusCode::NOT_FOUND, MISSING_HISTORY),
+            Error::StorageFailure => (StatusCode::INTERNAL_SERVER_ERROR, STORAGE_FAILURE),
+        }
+    }
+
+    /// `name` returns the name the error is reported under.
+    fn name(&self) -> &'static str {
+        match self {
+            Error::IncorrectInput(_) => "IncorrectInput",
+            Error::IncorrectDBRecord => "IncorrectDBRecord",
+            Error::DeserializationFailure => "DeserializationFailure",
+            Error::SnapshotFailure => "SnapshotFailure",
+            Error::MissingHistory => "MissingHistory",
+            Error::StorageFailure => "StorageFailure",
+        }
+    }
+
+    /// `message` returns a human-readable description of the error.
+    fn message(&self) -> &'static str {
+        match self {
+            Error::IncorrectInput(reason) => reason,
+            Error::IncorrectDBRecord => "no record exists for the requested agent",
+            Error::DeserializationFailure => "a record could not be serialized or deserialized",
+            Error::SnapshotFailure => "the fleet snapshot could not be written or read",
+            Error::MissingHistory => "no state history has been recorded for the requested agent",
+            Error::StorageFailure => "the state DB could not be read or written",
+        }
+    }
+}
+
 pub(crate) async fn handle_rejection(
     err: warp::reject::Rejection,
 ) -> Result<impl warp::Reply, Infallible> {
-    let (code, message): (StatusCode, u16) = match err.find() {
-        Some(Error::IncorrectInput) => (StatusCode::BAD_REQUEST, INCORRECT_INPUT),
-        Some(Error::IncorrectDBRecord) => (StatusCode::BAD_REQUEST, INCORRECT_DB_RECORD),
-        Some(Error::DeserializationFailure) => (StatusCode::BAD_REQUEST, DESERIALIZATION_FAILURE),
-        None => (StatusCode::BAD_REQUEST, DESERIALIZATION_FAILURE),
+    let (status, code, error, message) = match err.find::<Error>() {
+        Some(e) => {
+            let (status, code) = e.status();
+            (status, code, e.name(), e.message())
+        }
+        None if err.is_not_found() => (
+            StatusCode::NOT_FOUND,
+            UNKNOWN_ROUTE,
+            "UnknownRoute",
+            "no endpoint matches the request",
+        ),
+        None => (
+            StatusCode::BAD_REQUEST,
+            DESERIALIZATION_FAILURE,
+            "DeserializationFailure",
+            "the request could not be parsed",
+        ),
     };
 
-    Ok(http::Response::builder()
-        .status(code)
-        .body(message.to_string()))
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({
+            "code": code,
+            "error": error,
+            "message": message,
+        })),
+        status,
+    ))
 }
 
 const INCORRECT_INPUT: u16 = 0x835;
 const INCORRECT_DB_RECORD: u16 = 0x836;
 const DESERIALIZATION_FAILURE: u16 = 0x837;
+const SNAPSHOT_FAILURE: u16 = 0x838;
+const MISSING_HISTORY: u16 = 0x839;
+const UNKNOWN_ROUTE: u16 = 0x83a;
+const STORAGE_FAILURE: u16 = 0x83b;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Reply;
+
+    #[tokio::test]
+    async fn test_handle_rejection_json_body() {
+        let response = handle_rejection(warp::reject::custom(Error::IncorrectInput(
+            "agent id must not be empty",
+        )))
+        .await
+        .unwrap()
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["content-type"], "application/json");
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({
+                "code": 2101,
+                "error": "IncorrectInput",
+                "message": "agent id must not be empty",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_rejection_storage_failure() {
+        let response = handle_rejection(warp::reject::custom(Error::StorageFailure))
+            .await
+            .unwrap()
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(body["code"], 2107);
+        assert_eq!(body["error"], "StorageFailure");
+    }
+}
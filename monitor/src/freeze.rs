@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [FleetFreeze] defines the maintenance freeze shared between the REST API and the RPC server.
+/// While frozen every robot is held paused; on unfreeze the snapshot taken at the freeze point is
+/// handed back to the robots exactly as it was.
+#[derive(Debug, Default)]
+pub(crate) struct FleetFreeze {
+    // whether the fleet is currently frozen
+    frozen: bool,
+    // snapshot states still to be handed back to robots after unfreezing
+    pending_restore: HashMap<String, Robot>,
+}
+
+impl FleetFreeze {
+    /// `is_frozen` checks if the fleet is currently frozen.
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// `freeze` writes a snapshot of the fleet to `snapshot_path` and marks the fleet frozen.
+    /// It returns the fleet as it should be stored while frozen, with every robot paused.
+    pub(crate) fn freeze(
+        &mut self,
+        robots: &[Robot],
+        snapshot_path: &str,
+    ) -> Result<Vec<Robot>, String> {
+        if self.frozen {
+            return Err("Fleet is already frozen".to_string());
+        }
+
+        let snapshot = serde_json::to_vec_pretty(robots)
+            .map_err(|e| format!("Could not serialize fleet snapshot: {:?}", e))?;
+        std::fs::write(snapshot_path, snapshot)
+            .map_err(|e| format!("Could not write fleet snapshot: {:?}", e))?;
+
+        self.frozen = true;
+        self.pending_restore.clear();
+
+        Ok(robots
+            .iter()
+            .cloned()
+            .map(|mut robot| {
+                robot.state = MotionState::Pause;
+                robot
+            })
+            .collect())
+    }
+
+    /// `unfreeze` reads the snapshot back from `snapshot_path`, clears the freeze and schedules the
+    /// snapshot states to be handed back to the robots on their next message.
+    pub(crate) fn unfreeze(&mut self, snapshot_path: &str) -> Result<Vec<Robot>, String> {
+        if !self.frozen {
+            return Err("Fleet is not frozen".to_string());
+        }
+
+        let contents = std::fs::read(snapshot_path)
+            .map_err(|e| format!("Could not read fleet snapshot: {:?}", e))?;
+        let snapshot: Vec<Robot> = serde_json::from_slice(&contents)
+            .map_err(|e| format!("Could not deserialize fleet snapshot: {:?}", e))?;
+
+        self.frozen = false;
+        self.pending_restore = snapshot
+            .iter()
+            .map(|robot| (robot.device_id.clone(), robot.clone()))
+            .collect();
+
+        Ok(snapshot)
+    }
+
+    /// `restore_state` replaces an incoming robot state by its snapshot state if one is pending.
+    pub(crate) fn restore_state(&mut self, robot: &mut Robot) {
+        if let Some(snapshot_state) = self.pending_restore.remove(&robot.device_id) {
+            *robot = snapshot_state;
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+
+use crate::collision_monitor::Robot;
+
+/// [Heatmap] accumulates how many conflicts occurred in each cell of a coarse grid over the arena.
+/// Counts fade by a constant factor every round so that old activity drops out over time.
+#[derive(Debug, Default)]
+pub(crate) struct Heatmap {
+    // decayed conflict count per (column, row) cell
+    counts: BTreeMap<(i64, i64), f64>,
+}
+
+/// `FADED_COUNT` is the count below which a cell is dropped from the heatmap.
+const FADED_COUNT: f64 = 0.01;
+
+impl Heatmap {
+    /// `record_round` fades the existing counts by `decay` and adds one count, at the midpoint of the
+    /// two robots, for each conflicting pair of the round.
+    pub(crate) fn record_round(
+        &mut self,
+        robot_states: &[Robot],
+        conflicting_pairs: &[(String, String)],
+        cell_size: f64,
+        decay: f64,
+    ) {
+        for count in self.counts.values_mut() {
+            *count *= decay;
+        }
+        self.counts.retain(|_, count| *count >= FADED_COUNT);
+
+        let position = |device_id: &str| {
+            robot_states
+                .iter()
+                .find(|robot| robot.device_id == device_id)
+                .map(|robot| (robot.x, robot.y))
+        };
+
+        for (device_id_a, device_id_b) in conflicting_pairs {
+            if let (Some((x_a, y_a)), Some((x_b, y_b))) =
+                (position(device_id_a), position(device_id_b))
+            {
+                let cell = (
+                    ((x_a + x_b) / 2.0 / cell_size).floor() as i64,
+                    ((y_a + y_b) / 2.0 / cell_size).floor() as i64,
+                );
+                *self.counts.entry(cell).or_insert(0.0) += 1.0;
+            }
+        }
+    }
+
+    /// `cells` lists every cell with conflict activity, in column then row order.
+    pub(crate) fn cells(&self) -> Vec<HeatmapCell> {
+        self.counts
+            .iter()
+            .map(|(&(column, row), &count)| HeatmapCell { column, row, count })
+            .collect()
+    }
+}
+
+/// [HeatmapCell] defines the conflict count of one grid cell. The cell covers
+/// `[column * cell_size, (column + 1) * cell_size)` horizontally and likewise for rows.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct HeatmapCell {
+    pub column: i64,
+    pub row: i64,
+    pub count: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap_counts_conflicts_per_cell() {
+        let robot = |device_id: &str, x: f64, y: f64| Robot {
+            x,
+            y,
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+        let robots = vec![
+            robot("robot1", 1.0, 1.0),
+            robot("robot2", 1.5, 1.5),
+            robot("robot3", -3.0, 7.5),
+            robot("robot4", -3.5, 7.0),
+        ];
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        let mut heatmap = Heatmap::default();
+        heatmap.record_round(&robots, &[pair("robot1", "robot2")], 2.0, 0.5);
+        heatmap.record_round(
+            &robots,
+            &[pair("robot1", "robot2"), pair("robot3", "robot4")],
+            2.0,
+            0.5,
+        );
+
+        assert_eq!(
+            heatmap.cells(),
+            vec![
+                HeatmapCell {
+                    column: -2,
+                    row: 3,
+                    count: 1.0
+                },
+                HeatmapCell {
+                    column: 0,
+                    row: 0,
+                    count: 1.5
+                },
+            ]
+        );
+
+        // without new conflicts, old activity fades out.
+        for _ in 0..10 {
+            heatmap.record_round(&robots, &[], 2.0, 0.5);
+        }
+        assert!(heatmap.cells().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::collision_monitor::Robot;
+
+/// `HISTORY_TREE` names the sled tree the per-device state history is kept in, apart from the
+/// current states.
+const HISTORY_TREE: &str = "history";
+
+/// `record` appends a robot state to its device's history, keyed by device id and timestamp. When
+/// a `limit` is given, the oldest states of the device beyond it are pruned.
+pub(crate) fn record(db: &sled::Db, robot: &Robot, limit: Option<usize>) -> sled::Result<()> {
+    let history = db.open_tree(HISTORY_TREE)?;
+    history.insert(
+        history_key(&robot.device_id, robot.timestamp),
+        serde_json::to_vec(robot).map_err(std::io::Error::from)?,
+    )?;
+
+    if let Some(limit) = limit {
+        let prefix = format!("{}/", robot.device_id);
+        let excess = history.scan_prefix(&prefix).count().saturating_sub(limit);
+        for key in history.scan_prefix(&prefix).keys().take(excess) {
+            history.remove(key?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `recent` returns at most `limit` of the latest states recorded for a device, oldest first.
+pub(crate) fn recent(db: &sled::Db, device_id: &str, limit: usize) -> sled::Result<Vec<Robot>> {
+    let history = db.open_tree(HISTORY_TREE)?;
+
+    let mut states = history
+        .scan_prefix(format!("{}/", device_id))
+        .values()
+        .rev()
+        .take(limit)
+        .filter_map(|value| value.ok())
+        .filter_map(|value| serde_json::from_slice::<Robot>(&value).ok())
+        .collect::<Vec<Robot>>();
+    states.reverse();
+
+    Ok(states)
+}
+
+/// `clear` removes every state recorded for a device, returning how many were removed.
+pub(crate) fn clear(db: &sled::Db, device_id: &str) -> sled::Result<usize> {
+    let history = db.open_tree(HISTORY_TREE)?;
+
+    let mut removed = 0;
+    for key in history.scan_prefix(format!("{}/", device_id)).keys() {
+        history.remove(key?)?;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+/// `trajectory_csv` renders states as CSV rows of timestamp, position, motion state and battery.
+pub(crate) fn trajectory_csv(states: &[Robot]) -> String {
+    let mut csv = "timestamp,x,y,theta,state,battery\n".to_string();
+    for state in states {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            state.timestamp, state.x, state.y, state.theta, state.state, state.battery_level
+        ));
+    }
+
+    csv
+}
+
+/// `history_key` zero-pads the timestamp so that keys sort in timestamp order.
+fn history_key(device_id: &str, timestamp: i64) -> String {
+    format!("{}/{:020}", device_id, timestamp)
+}
@@ -0,0 +1,188 @@
+use rusqlite::{params, Connection};
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [IncidentSink] defines the SQLite file collision and deadlock incidents are exported to.
+pub(crate) struct IncidentSink {
+    connection: Connection,
+}
+
+impl IncidentSink {
+    /// `open` opens (or creates) the incidents database and its table.
+    pub(crate) fn open(path: &str) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS incidents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                device_id_a TEXT NOT NULL,
+                device_id_b TEXT NOT NULL,
+                x_a REAL NOT NULL,
+                y_a REAL NOT NULL,
+                x_b REAL NOT NULL,
+                y_b REAL NOT NULL,
+                severity TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(IncidentSink { connection })
+    }
+
+    /// `record` inserts a single incident as a row.
+    pub(crate) fn record(&self, incident: &Incident) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT INTO incidents (timestamp, device_id_a, device_id_b, x_a, y_a, x_b, y_b, severity)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                incident.timestamp,
+                incident.device_id_a,
+                incident.device_id_b,
+                incident.position_a.0,
+                incident.position_a.1,
+                incident.position_b.0,
+                incident.position_b.1,
+                incident.severity.as_str(),
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// [Incident] defines a collision or deadlock detected between two robots in a round.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Incident {
+    pub timestamp: i64,
+    pub device_id_a: String,
+    pub device_id_b: String,
+    pub position_a: (f64, f64),
+    pub position_b: (f64, f64),
+    pub severity: Severity,
+}
+
+impl Incident {
+    /// `from_round` builds one incident per conflicting pair. Pairs left paused on both sides after
+    /// resolution are reported as deadlocks, the rest as collisions.
+    pub(crate) fn from_round(
+        timestamp: i64,
+        robot_states: &[Robot],
+        updated_states: &[Robot],
+        conflicting_pairs: &[(String, String)],
+    ) -> Vec<Incident> {
+        let find = |robots: &'_ [Robot], device_id: &str| -> Option<Robot> {
+            robots
+                .iter()
+                .find(|robot| robot.device_id == device_id)
+                .cloned()
+        };
+        let is_paused = |device_id: &str| {
+            find(updated_states, device_id)
+                .map(|robot| robot.state == MotionState::Pause)
+                .unwrap_or(false)
+        };
+
+        conflicting_pairs
+            .iter()
+            .filter_map(|(device_id_a, device_id_b)| {
+                let robot_a = find(robot_states, device_id_a)?;
+                let robot_b = find(robot_states, device_id_b)?;
+
+                let severity = if is_paused(device_id_a) && is_paused(device_id_b) {
+                    Severity::Deadlock
+                } else {
+                    Severity::Collision
+                };
+
+                Some(Incident {
+                    timestamp,
+                    device_id_a: device_id_a.clone(),
+                    device_id_b: device_id_b.clone(),
+                    position_a: (robot_a.x, robot_a.y),
+                    position_b: (robot_b.x, robot_b.y),
+                    severity,
+                })
+            })
+            .collect()
+    }
+}
+
+/// [Severity] defines how severe an incident was.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Severity {
+    Collision,
+    Deadlock,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Collision => "collision",
+            Severity::Deadlock => "deadlock",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incidents_record_collision_row() {
+        let robot_states = vec![
+            Robot {
+                device_id: "robot1".to_string(),
+                ..Default::default()
+            },
+            Robot {
+                x: 0.5,
+                device_id: "robot2".to_string(),
+                ..Default::default()
+            },
+        ];
+        let mut updated_states = robot_states.clone();
+        updated_states[1].state = MotionState::Pause;
+
+        let incidents = Incident::from_round(
+            1657453020000,
+            &robot_states,
+            &updated_states,
+            &[("robot1".to_string(), "robot2".to_string())],
+        );
+        assert_eq!(incidents.len(), 1);
+
+        let sink = IncidentSink::open(":memory:").unwrap();
+        sink.record(&incidents[0]).unwrap();
+
+        let row: (i64, String, String, f64, f64, String) = sink
+            .connection
+            .query_row(
+                "SELECT timestamp, device_id_a, device_id_b, x_a, x_b, severity FROM incidents",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            row,
+            (
+                1657453020000,
+                "robot1".to_string(),
+                "robot2".to_string(),
+                0.0,
+                0.5,
+                "collision".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+use serde_derive::Serialize;
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [DecisionSink] streams the monitor's decisions to a Kafka topic. Messages are handed to a
+/// background producer through a bounded queue, so an unavailable broker never blocks a round.
+pub(crate) struct DecisionSink {
+    sender: SyncSender<DecisionMessage>,
+}
+
+impl DecisionSink {
+    /// `start` creates the producer for `brokers` and spawns the thread publishing to `topic`.
+    pub(crate) fn start(
+        brokers: &str,
+        topic: String,
+        queue_size: usize,
+    ) -> rdkafka::error::KafkaResult<Self> {
+        let producer: BaseProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .create()?;
+        let (sender, receiver) = sync_channel::<DecisionMessage>(queue_size);
+
+        thread::spawn(move || {
+            for message in receiver {
+                let record = BaseRecord::to(&topic)
+                    .key(&message.key)
+                    .payload(&message.payload);
+                if let Err((e, _)) = producer.send(record) {
+                    log::warn!("Dropping decision for {} on Kafka: {:?}", message.key, e);
+                }
+                producer.poll(Duration::ZERO);
+            }
+            let _ = producer.flush(Duration::from_secs(1));
+        });
+
+        Ok(DecisionSink { sender })
+    }
+
+    /// `publish` queues the decisions of a round, dropping them with a warning if the queue is full.
+    pub(crate) fn publish(&self, messages: Vec<DecisionMessage>) {
+        for message in messages {
+            match self.sender.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(message)) => {
+                    log::warn!("Kafka queue full, dropping decision for {}", message.key)
+                }
+                Err(TrySendError::Disconnected(message)) => {
+                    log::warn!(
+                        "Kafka producer stopped, dropping decision for {}",
+                        message.key
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// [DecisionMessage] defines a Kafka message keyed by device id.
+#[derive(Debug, PartialEq)]
+pub(crate) struct DecisionMessage {
+    pub key: String,
+    pub payload: String,
+}
+
+/// [Decision] defines the JSON payload describing what the monitor decided for a robot in a round.
+#[derive(Debug, Serialize)]
+struct Decision<'a> {
+    round: u64,
+    timestamp: i64,
+    device_id: &'a str,
+    state: MotionState,
+    x: f64,
+    y: f64,
+    theta: f64,
+    conflicts_with: Vec<&'a str>,
+}
+
+impl DecisionMessage {
+    /// `from_round` builds one message per robot, keyed by its device id.
+    pub(crate) fn from_round(
+        round: u64,
+        timestamp: i64,
+        updated_states: &[Robot],
+        conflicting_pairs: &[(String, String)],
+    ) -> Vec<DecisionMessage> {
+        updated_states
+            .iter()
+            .map(|robot| {
+                let conflicts_with = conflicting_pairs
+                    .iter()
+                    .filter_map(|(device_id_a, device_id_b)| {
+                        if *device_id_a == robot.device_id {
+                            Some(device_id_b.as_str())
+                        } else if *device_id_b == robot.device_id {
+                            Some(device_id_a.as_str())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                let decision = Decision {
+                    round,
+                    timestamp,
+                    device_id: &robot.device_id,
+                    state: robot.state,
+                    x: robot.x,
+                    y: robot.y,
+                    theta: robot.theta,
+                    conflicts_with,
+                };
+
+                DecisionMessage {
+                    key: robot.device_id.clone(),
+                    payload: serde_json::to_string(&decision).expect("Could not serialize"),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kafka_decision_messages_keyed_by_device() {
+        let updated_states = vec![
+            Robot {
+                device_id: "robot1".to_string(),
+                state: MotionState::Pause,
+                ..Default::default()
+            },
+            Robot {
+                x: 0.5,
+                device_id: "robot2".to_string(),
+                state: MotionState::Resume,
+                ..Default::default()
+            },
+        ];
+
+        let messages = DecisionMessage::from_round(
+            7,
+            1657453020000,
+            &updated_states,
+            &[("robot1".to_string(), "robot2".to_string())],
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                DecisionMessage {
+                    key: "robot1".to_string(),
+                    payload: r#"{"round":7,"timestamp":1657453020000,"device_id":"robot1","state":"pause","x":0.0,"y":0.0,"theta":0.0,"conflicts_with":["robot2"]}"#.to_string(),
+                },
+                DecisionMessage {
+                    key: "robot2".to_string(),
+                    payload: r#"{"round":7,"timestamp":1657453020000,"device_id":"robot2","state":"resume","x":0.5,"y":0.0,"theta":0.0,"conflicts_with":["robot1"]}"#.to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,9 +1,62 @@
+/// `acks` defines the tracking of state acknowledgements from robots
+mod acks;
 /// `collision_monitor` defines the collision monitoring system
 mod collision_monitor;
+/// `compression` defines the gzip and deflate compression of REST responses
+mod compression;
 /// `config` defines configuration for Collission Monitorng System
 mod config;
+/// `connection` defines the connection to the RabbitMQ hub
+mod connection;
+/// `control` defines the operator console commands received over WebSocket
+mod control;
+/// `correlation` defines tracking of correlation ids used by robots
+mod correlation;
+/// `dashboard` defines aggregated fleet data for the dashboard endpoint
+mod dashboard;
+/// `downsampling` defines the skipping of collision checks for far robots
+mod downsampling;
+/// `freeze` defines the maintenance freeze of the fleet
+mod freeze;
+/// `geometry` defines the collision checks between robot footprints
+mod geometry;
+/// `heatmap` defines the per-cell conflict counts of the arena
+mod heatmap;
+/// `history` defines the per-device history of robot states
+mod history;
+/// `incidents` defines the SQLite export of collision incidents
+#[cfg(feature = "sqlite")]
+mod incidents;
+/// `kafka` defines the Kafka export of the monitor's decisions
+#[cfg(feature = "kafka")]
+mod kafka;
+/// `metrics` defines the Prometheus metrics of the monitor
+mod metrics;
+/// `preview` defines the one-round conflict-resolution preview of a snapshot
+mod preview;
+/// `replay` defines the round-by-round replay of a snapshot
+mod replay;
+/// `schedule` defines the offline planning of collision-free schedules
+mod schedule;
 /// `server` defines the curret RPC server for listening to messages from robots
 mod server;
+/// `slow_start` defines the throttling of queued messages after reconnecting to the hub
+mod slow_start;
+/// `smoothing` defines the moving-average filter applied to reported positions
+mod smoothing;
+/// `spatial_grid` defines the spatial hash narrowing down pairwise collision checks
+mod spatial_grid;
+/// `staleness` defines the detection of robots that stopped reporting
+mod staleness;
+
+/// `timeout_detector` defines the timeout-based deadlock detector
+mod timeout_detector;
+
+/// `utilization` defines rolling fleet utilization statistics
+mod utilization;
+
+/// `wal` defines the write-ahead log of the states received in the current round
+mod wal;
 
 /// `error codes` defines error handling for Agent Info REST API
 mod error_codes;
@@ -14,14 +67,22 @@ mod routes;
 use amiquip::Error;
 use clap::Parser;
 use humantime::Timestamp;
+use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::SystemTime;
-use tokio::task;
+use tokio::{sync::broadcast, task};
 use warp::{self, Filter};
 
+use crate::acks::AckTracker;
 use crate::config::CLIArguments;
-use crate::server::Server;
+use crate::dashboard::Dashboard;
+use crate::freeze::FleetFreeze;
+use crate::heatmap::Heatmap;
+use crate::metrics::Metrics;
+use crate::server::{Server, SharedState};
+use crate::utilization::Utilization;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -31,9 +92,67 @@ async fn main() -> Result<(), Error> {
 
     let cli_args = CLIArguments::parse();
 
-    let config = config::load_config(cli_args.config_path.as_str())
+    if cli_args.validate {
+        match config::validate_config(cli_args.config_path.as_str()) {
+            Ok(()) => println!("OK"),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let (config, env_overrides) = config::load_config(cli_args.config_path.as_str())
         .expect("Irrecoverable error: failed to load config.toml");
 
+    if let Some(snapshot_path) = cli_args.preview {
+        let preview = preview::preview_snapshot(config, &snapshot_path)
+            .expect("Irrecoverable error: failed to preview snapshot");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&preview).expect("Could not serialize preview")
+        );
+        return Ok(());
+    }
+
+    if let Some(snapshot_path) = cli_args.replay {
+        let snapshot = preview::load_snapshot(&snapshot_path)
+            .expect("Irrecoverable error: failed to load snapshot");
+        let tick_interval = std::time::Duration::from_millis(config.base_publish_interval_ms);
+        let mut replay = replay::Replay::new(config, snapshot);
+        replay::play(
+            &mut replay,
+            cli_args.step,
+            cli_args.speed,
+            tick_interval,
+            std::io::stdin().lock(),
+            std::io::stdout(),
+        )
+        .expect("Irrecoverable error: failed to replay snapshot");
+        return Ok(());
+    }
+
+    if let Some(snapshot_path) = cli_args.schedule {
+        let schedule = schedule::schedule_snapshot(config, &snapshot_path)
+            .expect("Irrecoverable error: failed to schedule snapshot");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&schedule).expect("Could not serialize schedule")
+        );
+        return Ok(());
+    }
+
+    if let Some(recording_path) = cli_args.replay_recording {
+        let report = replay::replay_recording(config, &recording_path, &cli_args.replay_output)
+            .expect("Irrecoverable error: failed to replay recording");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Could not serialize replay report")
+        );
+        return Ok(());
+    }
+
     ///////////////////
     // 2.Set up logger.
     ///////////////////
@@ -60,6 +179,10 @@ async fn main() -> Result<(), Error> {
         .apply()
         .expect("could not set up logger");
 
+    for field in env_overrides {
+        log::info!("Config field {} overridden from the environment", field);
+    }
+
     ///////////////////
     // 3. Open Sled DB.
     ///////////////////
@@ -68,22 +191,125 @@ async fn main() -> Result<(), Error> {
     let db_instance_rpc = Arc::clone(&db);
     let db_instance_agent_api = Arc::clone(&db);
 
+    /////////////////////////
+    // 4. Seed robot states.
+    /////////////////////////
+
+    Server::seed_states(&config, &db).expect("Irrecoverable error: failed to seed robot states");
+
     /////////////////////////////////
-    // 4.Start Collision Monitor RPC
+    // 5.Start Collision Monitor RPC
     /////////////////////////////////
     let server_listening_port = config.listening_port;
+    let compression_threshold = config.compression_threshold_bytes;
+    let config_agent_api = Arc::new(config.clone());
+    let shared = SharedState {
+        dashboard: Arc::new(RwLock::new(Dashboard::default())),
+        fleet_freeze: Arc::new(Mutex::new(FleetFreeze::default())),
+        utilization: Arc::new(RwLock::new(Utilization::default())),
+        heatmap: Arc::new(RwLock::new(Heatmap::default())),
+        acks: Arc::new(RwLock::new(AckTracker::default())),
+        metrics: Arc::new(Metrics::default()),
+        reroutes: Arc::new(Mutex::new(HashMap::new())),
+        commands: Arc::new(Mutex::new(HashMap::new())),
+        shutdown: Arc::new(AtomicBool::new(false)),
+        halt: Arc::new(AtomicBool::new(false)),
+        events: broadcast::channel(config.event_buffer_size).0,
+        latest_tick: Arc::new(AtomicU64::new(0)),
+    };
+    let shared_rpc = shared.clone();
+
+    let shutdown = Arc::clone(&shared.shutdown);
 
-    task::spawn(async move { Server::start(config, db_instance_rpc) });
+    // the RPC server blocks on the hub, so it runs on its own thread rather than a warp worker.
+    let rpc_server = task::spawn_blocking(move || Server::run(config, db_instance_rpc, shared_rpc));
 
     ////////////////////////
-    // 5.Start Warp Threads
+    // 6.Start Warp Threads
     ////////////////////////
 
     let warp_serve = warp::serve(
-        routes::index_route()
-            .or(routes::agents(db_instance_agent_api))
-            .recover(error_codes::handle_rejection)
-            .with(warp::cors().allow_any_origin()),
+        compression::compressed(
+            routes::index_route()
+                .or(routes::health(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::agents(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                    Arc::clone(&shared.latest_tick),
+                ))
+                .or(routes::config(Arc::clone(&config_agent_api)))
+                .or(routes::agents_list(Arc::clone(&db_instance_agent_api)))
+                .or(routes::dashboard(
+                    Arc::clone(&db_instance_agent_api),
+                    shared.dashboard,
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::fleet_freeze(
+                    Arc::clone(&db_instance_agent_api),
+                    shared.fleet_freeze,
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::utilization(
+                    shared.utilization,
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::heatmap(
+                    shared.heatmap,
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::unacked(shared.acks, Arc::clone(&config_agent_api)))
+                .or(routes::metrics(shared.metrics))
+                .or(routes::collision_check(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::zones(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::conflict_graph(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::history(Arc::clone(&db_instance_agent_api)))
+                .or(routes::progress(Arc::clone(&db_instance_agent_api)))
+                .or(routes::set_path(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                    shared.reroutes,
+                ))
+                .or(routes::set_battery(Arc::clone(&db_instance_agent_api)))
+                .or(routes::evict(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::stale(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::control(
+                    Arc::clone(&db_instance_agent_api),
+                    shared.commands,
+                    shared.events.clone(),
+                ))
+                .or(routes::batch(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::reset(
+                    Arc::clone(&db_instance_agent_api),
+                    Arc::clone(&config_agent_api),
+                ))
+                .or(routes::halt(shared.halt, Arc::clone(&config_agent_api)))
+                .or(routes::events(shared.events))
+                .or(routes::trajectory(db_instance_agent_api)),
+            compression_threshold,
+        )
+        .recover(error_codes::handle_rejection)
+        .with(warp::cors().allow_any_origin()),
     );
 
     let (_, server) =
@@ -91,9 +317,12 @@ async fn main() -> Result<(), Error> {
             tokio::signal::ctrl_c()
                 .await
                 .expect("failed to listen to shutdown signal");
+            // stop the RPC server along with the REST API.
+            shutdown.store(true, Ordering::SeqCst);
         });
 
     server.await;
+    rpc_server.await.expect("RPC server task panicked");
 
     Ok(())
 }
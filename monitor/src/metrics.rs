@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [Metrics] keeps the operational counters and gauges of the monitor exposed on `/metrics`.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    // collisions detected across all rounds
+    collisions_detected: AtomicU64,
+    // conflicts settled by deadlock resolution across all rounds
+    deadlocks_resolved: AtomicU64,
+    // robots left paused by the last round
+    robots_paused: AtomicU64,
+    // agents which took part in the last round
+    agents_connected: AtomicU64,
+}
+
+impl Metrics {
+    /// `record_collisions` adds the collisions detected in a round.
+    pub(crate) fn record_collisions(&self, collisions: usize) {
+        self.collisions_detected
+            .fetch_add(collisions as u64, Ordering::Relaxed);
+    }
+
+    /// `record_deadlocks_resolved` adds the conflicts settled by deadlock resolution.
+    pub(crate) fn record_deadlocks_resolved(&self, conflicts: usize) {
+        self.deadlocks_resolved
+            .fetch_add(conflicts as u64, Ordering::Relaxed);
+    }
+
+    /// `observe_round` updates the gauges from the robot states a round ended with.
+    pub(crate) fn observe_round(&self, updated_states: &[Robot]) {
+        let paused = updated_states
+            .iter()
+            .filter(|robot| robot.state == MotionState::Pause)
+            .count();
+
+        self.robots_paused.store(paused as u64, Ordering::Relaxed);
+        self.agents_connected
+            .store(updated_states.len() as u64, Ordering::Relaxed);
+    }
+
+    /// `render` formats the metrics in the Prometheus text exposition format.
+    pub(crate) fn render(&self) -> String {
+        [
+            (
+                "collisions_detected_total",
+                "counter",
+                "Collisions detected between robots.",
+                &self.collisions_detected,
+            ),
+            (
+                "deadlocks_resolved_total",
+                "counter",
+                "Conflicts settled by deadlock resolution.",
+                &self.deadlocks_resolved,
+            ),
+            (
+                "robots_paused",
+                "gauge",
+                "Robots left paused by the last round.",
+                &self.robots_paused,
+            ),
+            (
+                "agents_connected",
+                "gauge",
+                "Agents which took part in the last round.",
+                &self.agents_connected,
+            ),
+        ]
+        .iter()
+        .map(|(name, kind, help, value)| {
+            format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {}\n",
+                value.load(Ordering::Relaxed),
+                name = name,
+                help = help,
+                kind = kind,
+            )
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_render() {
+        let metrics = Metrics::default();
+        metrics.record_collisions(2);
+        metrics.record_collisions(1);
+        metrics.record_deadlocks_resolved(1);
+        metrics.observe_round(&[
+            Robot {
+                state: MotionState::Pause,
+                ..Default::default()
+            },
+            Robot::default(),
+        ]);
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(
+            "# HELP collisions_detected_total Collisions detected between robots.\n\
+             # TYPE collisions_detected_total counter\n\
+             collisions_detected_total 3\n"
+        ));
+        assert!(rendered.contains("deadlocks_resolved_total 1\n"));
+        assert!(rendered.contains("# TYPE robots_paused gauge\nrobots_paused 1\n"));
+        assert!(rendered.contains("agents_connected 2\n"));
+    }
+}
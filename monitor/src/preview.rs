@@ -0,0 +1,79 @@
+use crate::collision_monitor::{CollisionMonitor, CollisionReport, Robot};
+use crate::config::CollisionMonitorConfig;
+
+/// `preview_snapshot` loads a JSON array of robot states from `snapshot_path` and previews it.
+pub(crate) fn preview_snapshot(
+    config: CollisionMonitorConfig,
+    snapshot_path: &str,
+) -> Result<CollisionReport, String> {
+    Ok(preview(config, load_snapshot(snapshot_path)?))
+}
+
+/// `load_snapshot` reads a JSON array of robot states from `snapshot_path`.
+pub(crate) fn load_snapshot(snapshot_path: &str) -> Result<Vec<Robot>, String> {
+    let contents = std::fs::read(snapshot_path)
+        .map_err(|e| format!("Failed to open snapshot file: {:?}", e))?;
+
+    serde_json::from_slice(&contents)
+        .map_err(|e| format!("Failed to deserialize snapshot: {:?}", e))
+}
+
+/// `preview` runs collision detection and the resolver once over `snapshot` without advancing
+/// any further.
+pub(crate) fn preview(config: CollisionMonitorConfig, snapshot: Vec<Robot>) -> CollisionReport {
+    CollisionMonitor::new(config).analyze(&snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision_monitor::Path;
+    use crate::config::test_config;
+
+    fn robot(device_id: &str, x: f64) -> Robot {
+        Robot {
+            x,
+            path: vec![
+                Path {
+                    x,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: x + 1.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: device_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_preview_matches_one_round() {
+        let snapshot = vec![
+            robot("robot1", 0.0),
+            robot("robot2", 0.5),
+            robot("robot3", 9.0),
+        ];
+        let path =
+            std::env::temp_dir().join(format!("{}_preview_snapshot.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&snapshot).unwrap()).unwrap();
+
+        let preview = preview_snapshot(test_config(), path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut expected = snapshot;
+        CollisionMonitor::new(test_config()).update_robot_state(&mut expected);
+
+        assert_eq!(
+            preview.colliding_pairs,
+            vec![("robot1".to_string(), "robot2".to_string())]
+        );
+        assert_eq!(
+            serde_json::to_value(&preview.resolved_states).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use serde_derive::Serialize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::collision_monitor::{CollisionMonitor, Robot};
+use crate::config::CollisionMonitorConfig;
+
+/// [Replay] plays a snapshot of the fleet forward one round at a time, the way the robots would by
+/// sending back the states the monitor hands them.
+pub(crate) struct Replay {
+    collision_monitor: CollisionMonitor,
+    // current states of the fleet
+    pub robots: Vec<Robot>,
+    // number of rounds played so far
+    pub tick: u64,
+}
+
+impl Replay {
+    /// `new` starts a replay from `snapshot`.
+    pub(crate) fn new(config: CollisionMonitorConfig, snapshot: Vec<Robot>) -> Self {
+        Replay {
+            collision_monitor: CollisionMonitor::new(config),
+            robots: snapshot,
+            tick: 0,
+        }
+    }
+
+    /// `step` plays a single round. It returns false once a round no longer changes the fleet.
+    pub(crate) fn step(&mut self) -> bool {
+        let before = serde_json::to_value(&self.robots).expect("Could not serialize");
+        self.collision_monitor.update_robot_state(&mut self.robots);
+        self.tick += 1;
+
+        serde_json::to_value(&self.robots).expect("Could not serialize") != before
+    }
+
+    /// `print` writes the tick and the position and state of every robot to `output`.
+    pub(crate) fn print<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "tick {}", self.tick)?;
+        for robot in &self.robots {
+            writeln!(
+                output,
+                "  {} {} ({}, {}, {})",
+                robot.device_id, robot.state, robot.x, robot.y, robot.theta
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `play` runs a replay until the fleet settles. In `step` mode it advances one tick per line read
+/// from `input` and stops on end of input or `q`; otherwise it advances one tick every
+/// `tick_interval` divided by `speed`.
+pub(crate) fn play<R: BufRead, W: Write>(
+    replay: &mut Replay,
+    step: bool,
+    speed: f64,
+    tick_interval: Duration,
+    mut input: R,
+    mut output: W,
+) -> io::Result<()> {
+    replay.print(&mut output)?;
+
+    loop {
+        if step {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+                return Ok(());
+            }
+        } else {
+            thread::sleep(tick_interval.div_f64(speed.max(f64::EPSILON)));
+        }
+
+        if !replay.step() {
+            writeln!(output, "fleet settled after tick {}", replay.tick)?;
+            return Ok(());
+        }
+        replay.print(&mut output)?;
+    }
+}
+
+/// [RecordingReport] summarizes a recording fed through the monitor by [replay_recording].
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct RecordingReport {
+    // number of batches resolved and written out
+    pub resolved_batches: usize,
+    // index of every batch the monitor rejected, with the reason
+    pub rejected_batches: Vec<(usize, String)>,
+}
+
+/// `replay_recording` reads newline-delimited JSON robot states from `recording_path`, groups them
+/// into batches of `num_agents` and runs each batch through the monitor like a round received from
+/// RabbitMQ, writing the resolved states to `output_path` one per line. Batches the monitor
+/// rejects, such as an incomplete batch left at the end of the recording, are reported instead.
+pub(crate) fn replay_recording(
+    config: CollisionMonitorConfig,
+    recording_path: &str,
+    output_path: &str,
+) -> Result<RecordingReport, String> {
+    let batch_size = config
+        .num_agents
+        .ok_or("Replaying a recording requires num_agents to group states into batches")?;
+    let recording =
+        File::open(recording_path).map_err(|e| format!("Failed to open recording: {:?}", e))?;
+    // recorded states are in the units robots report in, the monitor works in meters.
+    let meters_per_unit = config.units.meters_per_unit();
+    let mut robots: Vec<Robot> = Vec::new();
+    for (line_idx, line) in BufReader::new(recording).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read recording: {:?}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut robot: Robot = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "Failed to deserialize line {} of the recording: {:?}",
+                line_idx + 1,
+                e
+            )
+        })?;
+        robot.scale_lengths(meters_per_unit);
+        robots.push(robot);
+    }
+
+    let write_error = |e: io::Error| format!("Failed to write resolved states: {:?}", e);
+    let mut output = BufWriter::new(File::create(output_path).map_err(write_error)?);
+    let compact_state = config.compact_state_encoding;
+    let collision_monitor = CollisionMonitor::new(config);
+    let mut report = RecordingReport::default();
+
+    for (batch_idx, batch) in robots.chunks(batch_size).enumerate() {
+        match collision_monitor.trigger_collision_monitor(batch.to_vec()) {
+            Ok(updated_states) => {
+                for mut robot in updated_states {
+                    robot.scale_lengths(1.0 / meters_per_unit);
+                    let json = robot
+                        .to_json(compact_state)
+                        .map_err(|e| format!("Failed to serialize resolved state: {:?}", e))?;
+                    writeln!(output, "{}", json).map_err(write_error)?;
+                }
+                report.resolved_batches += 1;
+            }
+            Err(e) => report.rejected_batches.push((batch_idx, e.to_string())),
+        }
+    }
+    output.flush().map_err(write_error)?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision_monitor::{MotionState, Path};
+    use crate::config::test_config;
+
+    #[test]
+    fn test_replay_step_mode_advances_one_tick_per_input() {
+        let robot = Robot {
+            path: (0..5)
+                .map(|x| Path {
+                    x: x as f64,
+                    y: 0.0,
+                    theta: 0.0,
+                })
+                .collect(),
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        let mut replay = Replay::new(test_config(), vec![robot]);
+        let mut output = Vec::new();
+
+        play(
+            &mut replay,
+            true,
+            1.0,
+            Duration::ZERO,
+            "\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(replay.tick, 1);
+        assert_eq!(replay.robots[0].x, 1.0);
+
+        play(
+            &mut replay,
+            true,
+            1.0,
+            Duration::ZERO,
+            "\n\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(replay.tick, 3);
+        assert_eq!(replay.robots[0].x, 3.0);
+
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .ends_with("tick 3\n  robot1 resume (3, 0, 0)\n"));
+    }
+
+    #[test]
+    fn test_replay_recording_resolves_batches() {
+        let recording = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/recorded_states.jsonl"
+        );
+        let output_path =
+            std::env::temp_dir().join(format!("{}_replayed_states.jsonl", std::process::id()));
+
+        let report =
+            replay_recording(test_config(), recording, output_path.to_str().unwrap()).unwrap();
+        let resolved: Vec<Robot> = std::fs::read_to_string(&output_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(&output_path).unwrap();
+
+        assert_eq!(report.resolved_batches, 2);
+        // the single state left at the end of the recording is reported, not dropped.
+        assert_eq!(
+            report.rejected_batches,
+            vec![(2, "Not yet received all agent records: 1 of 2".to_string())]
+        );
+
+        let summary: Vec<(&str, f64, MotionState)> = resolved
+            .iter()
+            .map(|robot| (robot.device_id.as_str(), robot.x, robot.state))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("robot1", 1.0, MotionState::Resume),
+                ("robot2", 6.0, MotionState::Resume),
+                ("robot1", 4.0, MotionState::Resume),
+                ("robot2", 1.5, MotionState::Pause),
+            ]
+        );
+
+        // without a fleet size there is nothing to group the recording by.
+        let windowed = CollisionMonitorConfig {
+            num_agents: None,
+            batch_window_ms: Some(500),
+            ..test_config()
+        };
+        assert!(replay_recording(windowed, recording, output_path.to_str().unwrap()).is_err());
+    }
+}
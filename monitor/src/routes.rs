@@ -1,9 +1,30 @@
+use serde_derive::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::{Stream, StreamExt};
 use warp::{self, http, Filter};
 
-use std::{convert::Infallible, sync::Arc};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, RwLock,
+    },
+};
 
-use crate::collision_monitor::Robot;
+use crate::acks::{AckTracker, UnackedRobot};
+use crate::collision_monitor::{CollisionMonitor, MonitorError, MotionState, Path, Robot};
+use crate::config::CollisionMonitorConfig;
+use crate::control;
+use crate::dashboard::Dashboard;
 use crate::error_codes::Error as CollisionMonitorError;
+use crate::freeze::FleetFreeze;
+use crate::heatmap::{Heatmap, HeatmapCell};
+use crate::history;
+use crate::metrics::Metrics;
+use crate::staleness::{self, StaleRobot};
+use crate::utilization::Utilization;
 
 pub(crate) fn index_route(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -15,51 +36,2343 @@ pub(crate) fn index_route(
     warp::path!().and(warp::get()).and_then(index_page_handler)
 }
 
+/// [HealthResponse] defines the body of the `/health` endpoint.
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    db: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agents_known: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_agents: Option<usize>,
+}
+
+pub(crate) fn health(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_health(
+        db: Arc<sled::Db>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let (status, response) = match db.iter().keys().collect::<sled::Result<Vec<_>>>() {
+            Ok(keys) => (
+                http::StatusCode::OK,
+                HealthResponse {
+                    db: "ok",
+                    agents_known: Some(keys.len()),
+                    num_agents: config.num_agents,
+                },
+            ),
+            Err(e) => {
+                log::error!("Health check failed to read the DB: {:?}", e);
+                (
+                    http::StatusCode::SERVICE_UNAVAILABLE,
+                    HealthResponse {
+                        db: "unavailable",
+                        agents_known: None,
+                        num_agents: config.num_agents,
+                    },
+                )
+            }
+        };
+
+        let body = serde_json::to_string(&response)
+            .expect("Could not serialize")
+            .as_bytes()
+            .to_vec();
+
+        Ok(http::Response::builder().status(status).body(body))
+    }
+
+    warp::path!("health")
+        .and(warp::get())
+        .and_then(move || get_health(Arc::clone(&db), Arc::clone(&config)))
+}
+
+/// [RedactedConfig] defines the running config as served by `/config`, with the hub password
+/// blanked.
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+struct RedactedConfig(CollisionMonitorConfig);
+
+impl RedactedConfig {
+    fn new(config: &CollisionMonitorConfig) -> Self {
+        let mut config = config.clone();
+        config.queue_hub_pw = "<redacted>".to_string();
+
+        RedactedConfig(config)
+    }
+}
+
+pub(crate) fn config(
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_config(
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let body = match serde_json::to_string(&RedactedConfig::new(&config)) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("config")
+        .and(warp::get())
+        .and_then(move || get_config(Arc::clone(&config)))
+}
+
+/// [StateQuery] defines optional extras to include in a robot's state.
+#[derive(Debug, Deserialize)]
+struct StateQuery {
+    include: Option<String>,
+}
+
 pub(crate) fn agents(
     db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+    latest_tick: Arc<AtomicU64>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     async fn get_agent_info(
         db: Arc<sled::Db>,
+        collision_monitor: Arc<CollisionMonitor>,
+        latest_tick: Arc<AtomicU64>,
         agent_identidier: String,
+        query: StateQuery,
     ) -> Result<impl warp::Reply, warp::Rejection> {
         if agent_identidier == String::new() {
-            return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput));
+            return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                "agent id must not be empty",
+            )));
         }
 
-        let db_record = match db.get(&agent_identidier).expect("Failed to get record") {
-            Some(state) => state,
-            None => {
+        let db_record = match db.get(&agent_identidier) {
+            Ok(Some(state)) => state,
+            Ok(None) => {
                 return Err(warp::reject::custom(
                     CollisionMonitorError::IncorrectDBRecord,
                 ));
             }
+            Err(e) => {
+                log::error!(
+                    "Could not read the record of {:?}: {:?}",
+                    agent_identidier,
+                    e
+                );
+                return Err(warp::reject::custom(CollisionMonitorError::StorageFailure));
+            }
         };
 
-        let current_state: Robot =
-            serde_json::from_slice(&db_record).expect("Could not deserialize record");
+        // an empty or corrupt record, e.g. left by a partial write, is reported rather than served.
+        let current_state: Robot = match serde_json::from_slice(&db_record) {
+            Ok(state) => state,
+            Err(e) => {
+                log::error!("Corrupt record stored for {:?}: {}", agent_identidier, e);
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        };
 
-        let body = match serde_json::to_string(&current_state) {
-            Ok(str) => str,
+        let mut response = match serde_json::to_value(&current_state) {
+            Ok(value) => value,
             Err(_) => {
                 return Err(warp::reject::custom(
                     CollisionMonitorError::DeserializationFailure,
                 ));
             }
+        };
+
+        // a state left behind by the latest batch belongs to a robot which missed it.
+        response["fresh"] =
+            serde_json::json!(current_state.tick == latest_tick.load(Ordering::SeqCst));
+
+        let includes_predicted = query
+            .include
+            .as_deref()
+            .map(|include| include.split(',').any(|extra| extra == "predicted"))
+            .unwrap_or(false);
+        if includes_predicted {
+            response["predicted_next"] =
+                serde_json::json!(collision_monitor.predicted_next(&current_state));
         }
-        .as_bytes()
-        .to_vec();
+
+        let body = response.to_string().as_bytes().to_vec();
 
         Ok(http::Response::builder()
             .status(http::StatusCode::OK)
             .body(body))
     }
 
-    let agents_route = |db: Arc<sled::Db>| {
+    let collision_monitor = Arc::new(CollisionMonitor::new((*config).clone()));
+
+    let agents_route = move |db: Arc<sled::Db>| {
         warp::path!("state" / String)
             .and(warp::get())
             .and(warp::path::end())
-            .and_then(move |agent| get_agent_info(Arc::clone(&db), agent))
+            .and(warp::query::<StateQuery>())
+            .and_then(move |agent, query| {
+                get_agent_info(
+                    Arc::clone(&db),
+                    Arc::clone(&collision_monitor),
+                    Arc::clone(&latest_tick),
+                    agent,
+                    query,
+                )
+            })
     };
 
     agents_route(db)
 }
+
+/// [AgentsQuery] defines how `/agents` filters and pages the known agents, and whether it lists
+/// full states instead of device ids.
+#[derive(Debug, Deserialize)]
+struct AgentsQuery {
+    full: Option<bool>,
+    state: Option<MotionState>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+pub(crate) fn agents_list(
+    db: Arc<sled::Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_agents(
+        db: Arc<sled::Db>,
+        query: AgentsQuery,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        // sled iterates in key order, so pages are stable across calls.
+        let robots: Vec<Robot> = stored_robots(&db)
+            .into_iter()
+            .filter(|robot| query.state.is_none() || query.state == Some(robot.state))
+            .collect();
+        let total = robots.len();
+        let page = robots
+            .into_iter()
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(usize::MAX));
+
+        let items = if query.full.unwrap_or(false) {
+            serde_json::to_value(page.collect::<Vec<Robot>>())
+        } else {
+            serde_json::to_value(page.map(|robot| robot.device_id).collect::<Vec<String>>())
+        };
+
+        let body = match items {
+            Ok(items) => serde_json::json!({ "total": total, "items": items }).to_string(),
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("agents")
+        .and(warp::get())
+        .and(warp::query::<AgentsQuery>())
+        .and_then(move |query| get_agents(Arc::clone(&db), query))
+}
+
+pub(crate) fn dashboard(
+    db: Arc<sled::Db>,
+    dashboard: Arc<RwLock<Dashboard>>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_dashboard(
+        db: Arc<sled::Db>,
+        dashboard: Arc<RwLock<Dashboard>>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_dashboard {
+            return Err(warp::reject::not_found());
+        }
+
+        let robots = stored_robots(&db);
+        let view = dashboard
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .view(&robots, &config);
+
+        let body = match serde_json::to_string(&view) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("dashboard").and(warp::get()).and_then(move || {
+        get_dashboard(Arc::clone(&db), Arc::clone(&dashboard), Arc::clone(&config))
+    })
+}
+
+pub(crate) fn fleet_freeze(
+    db: Arc<sled::Db>,
+    fleet_freeze: Arc<Mutex<FleetFreeze>>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn toggle_freeze(
+        db: Arc<sled::Db>,
+        fleet_freeze: Arc<Mutex<FleetFreeze>>,
+        config: Arc<CollisionMonitorConfig>,
+        action: String,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_fleet_freeze {
+            return Err(warp::reject::not_found());
+        }
+
+        let mut freeze = fleet_freeze.lock().unwrap_or_else(PoisonError::into_inner);
+        let fleet = match action.as_str() {
+            "freeze" => {
+                if freeze.is_frozen() {
+                    return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                        "the fleet is already frozen",
+                    )));
+                }
+                freeze.freeze(&stored_robots(&db), &config.freeze_snapshot_path)
+            }
+            "unfreeze" => {
+                if !freeze.is_frozen() {
+                    return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                        "the fleet is not frozen",
+                    )));
+                }
+                freeze.unfreeze(&config.freeze_snapshot_path)
+            }
+            _ => return Err(warp::reject::not_found()),
+        }
+        .map_err(|e| {
+            log::warn!("Fleet {} failed: {}", action, e);
+            warp::reject::custom(CollisionMonitorError::SnapshotFailure)
+        })?;
+
+        for robot in &fleet {
+            db.insert(
+                &robot.device_id,
+                serde_json::to_string(robot)
+                    .map_err(serde_failure)?
+                    .as_bytes()
+                    .to_vec(),
+            )
+            .map_err(db_failure)?;
+        }
+
+        log::info!("Fleet {} applied to {} robots", action, fleet.len());
+
+        let body = match serde_json::to_string(&fleet) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("fleet" / String)
+        .and(warp::post())
+        .and_then(move |action| {
+            toggle_freeze(
+                Arc::clone(&db),
+                Arc::clone(&fleet_freeze),
+                Arc::clone(&config),
+                action,
+            )
+        })
+}
+
+pub(crate) fn utilization(
+    utilization: Arc<RwLock<Utilization>>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_utilization(
+        utilization: Arc<RwLock<Utilization>>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_utilization_stats {
+            return Err(warp::reject::not_found());
+        }
+
+        let report = utilization
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .report();
+
+        let body = match serde_json::to_string(&report) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("stats" / "utilization")
+        .and(warp::get())
+        .and_then(move || get_utilization(Arc::clone(&utilization), Arc::clone(&config)))
+}
+
+/// [HeatmapResponse] defines the conflict counts per grid cell and the size of the cells.
+#[derive(Debug, Serialize)]
+struct HeatmapResponse {
+    cell_size: f64,
+    cells: Vec<HeatmapCell>,
+}
+
+pub(crate) fn heatmap(
+    heatmap: Arc<RwLock<Heatmap>>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_heatmap(
+        heatmap: Arc<RwLock<Heatmap>>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_heatmap {
+            return Err(warp::reject::not_found());
+        }
+
+        let cells = heatmap
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .cells();
+
+        let body = match serde_json::to_string(&HeatmapResponse {
+            cell_size: config.heatmap_cell_size,
+            cells,
+        }) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("heatmap")
+        .and(warp::get())
+        .and_then(move || get_heatmap(Arc::clone(&heatmap), Arc::clone(&config)))
+}
+
+pub(crate) fn metrics(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_metrics(metrics: Arc<Metrics>) -> Result<impl warp::Reply, warp::Rejection> {
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header("content-type", "text/plain; version=0.0.4")
+            .body(metrics.render()))
+    }
+
+    warp::path!("metrics")
+        .and(warp::get())
+        .and_then(move || get_metrics(Arc::clone(&metrics)))
+}
+
+pub(crate) fn unacked(
+    acks: Arc<RwLock<AckTracker>>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_unacked(
+        acks: Arc<RwLock<AckTracker>>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_ack_tracking {
+            return Err(warp::reject::not_found());
+        }
+
+        let unacked: Vec<UnackedRobot> = acks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .report(config.ack_deadline_rounds)
+            .into_iter()
+            .map(|(device_id, missed_rounds)| UnackedRobot {
+                device_id,
+                missed_rounds,
+            })
+            .collect();
+
+        let body = match serde_json::to_string(&unacked) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("unacked")
+        .and(warp::get())
+        .and_then(move || get_unacked(Arc::clone(&acks), Arc::clone(&config)))
+}
+
+/// [CollisionCheckRequest] defines a hypothetical robot position. A full robot state is accepted too.
+#[derive(Debug, Deserialize)]
+struct CollisionCheckRequest {
+    device_id: Option<String>,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    theta: f64,
+    position_confidence: Option<f64>,
+}
+
+/// [CollisionCheckResponse] defines whether a hypothetical position conflicts with the fleet.
+#[derive(Debug, Serialize)]
+struct CollisionCheckResponse {
+    collides: bool,
+    conflicting_device_ids: Vec<String>,
+}
+
+pub(crate) fn collision_check(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn check_collision(
+        db: Arc<sled::Db>,
+        collision_monitor: Arc<CollisionMonitor>,
+        request: CollisionCheckRequest,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !collision_monitor.config.enable_collision_check {
+            return Err(warp::reject::not_found());
+        }
+
+        let mut candidate = Robot {
+            x: request.x,
+            y: request.y,
+            theta: request.theta,
+            device_id: request.device_id.unwrap_or_default(),
+            ..Default::default()
+        };
+        if let Some(position_confidence) = request.position_confidence {
+            candidate.position_confidence = position_confidence;
+        }
+
+        let conflicting_device_ids =
+            collision_monitor.conflicts_with(&candidate, &stored_robots(&db));
+
+        let body = match serde_json::to_string(&CollisionCheckResponse {
+            collides: !conflicting_device_ids.is_empty(),
+            conflicting_device_ids,
+        }) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    let collision_monitor = Arc::new(CollisionMonitor::new((*config).clone()));
+
+    warp::path!("collision-check")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(move |request| {
+            check_collision(Arc::clone(&db), Arc::clone(&collision_monitor), request)
+        })
+}
+
+pub(crate) fn zones(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_zones(
+        db: Arc<sled::Db>,
+        collision_monitor: Arc<CollisionMonitor>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let occupancy = collision_monitor.zone_occupancy(&stored_robots(&db));
+
+        let body = match serde_json::to_string(&occupancy) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    let collision_monitor = Arc::new(CollisionMonitor::new((*config).clone()));
+
+    warp::path!("zones")
+        .and(warp::get())
+        .and_then(move || get_zones(Arc::clone(&db), Arc::clone(&collision_monitor)))
+}
+
+pub(crate) fn conflict_graph(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_conflict_graph(
+        db: Arc<sled::Db>,
+        collision_monitor: Arc<CollisionMonitor>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !collision_monitor.config.enable_conflict_graph {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "text/vnd.graphviz")
+            .body(collision_monitor.conflict_graph_dot(&stored_robots(&db))))
+    }
+
+    let collision_monitor = Arc::new(CollisionMonitor::new((*config).clone()));
+
+    warp::path!("conflicts.dot")
+        .and(warp::get())
+        .and_then(move || get_conflict_graph(Arc::clone(&db), Arc::clone(&collision_monitor)))
+}
+
+/// [ResetResponse] defines the body of the `/admin/reset` endpoint.
+#[derive(Debug, Serialize)]
+struct ResetResponse {
+    // number of keys removed across the agent states, their history and last-seen times
+    removed: usize,
+}
+
+pub(crate) fn reset(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn reset_db(
+        db: Arc<sled::Db>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_admin_routes {
+            return Err(warp::reject::not_found());
+        }
+
+        let removed = clear_db(&db).map_err(db_failure)?;
+        log::warn!("Reset the monitor, removing {} keys", removed);
+
+        let body = serde_json::to_string(&ResetResponse { removed })
+            .map_err(serde_failure)?
+            .as_bytes()
+            .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("admin" / "reset")
+        .and(warp::post())
+        .and_then(move || reset_db(Arc::clone(&db), Arc::clone(&config)))
+}
+
+/// [HaltResponse] defines the body of the `/admin/halt` and `/admin/resume` endpoints.
+#[derive(Debug, Serialize)]
+struct HaltResponse {
+    halted: bool,
+}
+
+pub(crate) fn halt(
+    halt: Arc<AtomicBool>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn toggle_halt(
+        halt: Arc<AtomicBool>,
+        config: Arc<CollisionMonitorConfig>,
+        halted: bool,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !config.enable_admin_routes {
+            return Err(warp::reject::not_found());
+        }
+
+        halt.store(halted, Ordering::SeqCst);
+
+        if halted {
+            log::warn!("Fleet halted by the operator");
+        } else {
+            log::info!("Fleet halt cleared by the operator");
+        }
+
+        let body = serde_json::to_string(&HaltResponse { halted })
+            .map_err(serde_failure)?
+            .as_bytes()
+            .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    let halt_fleet = {
+        let (halt, config) = (Arc::clone(&halt), Arc::clone(&config));
+        warp::path!("admin" / "halt")
+            .and(warp::post())
+            .and_then(move || toggle_halt(Arc::clone(&halt), Arc::clone(&config), true))
+    };
+    let resume_fleet = warp::path!("admin" / "resume")
+        .and(warp::post())
+        .and_then(move || toggle_halt(Arc::clone(&halt), Arc::clone(&config), false));
+
+    halt_fleet.or(resume_fleet).unify()
+}
+
+/// `db_failure` logs a failed read or write of the DB and rejects the request with it as a server
+/// error, so a failing DB never takes the server down.
+fn db_failure<E: std::fmt::Debug>(error: E) -> warp::Rejection {
+    log::error!("DB operation failed: {:?}", error);
+    warp::reject::custom(CollisionMonitorError::StorageFailure)
+}
+
+/// `serde_failure` logs a record that could not be serialized and rejects the request with it.
+fn serde_failure(error: serde_json::Error) -> warp::Rejection {
+    log::error!("Serialization failed: {:?}", error);
+    warp::reject::custom(CollisionMonitorError::DeserializationFailure)
+}
+
+/// `clear_db` empties every tree of the DB, returning how many keys were removed.
+fn clear_db(db: &sled::Db) -> sled::Result<usize> {
+    let mut removed = db.len();
+    db.clear()?;
+
+    for name in db.tree_names() {
+        if name == db.name() {
+            continue;
+        }
+        let tree = db.open_tree(name)?;
+        removed += tree.len();
+        tree.clear()?;
+    }
+
+    Ok(removed)
+}
+
+/// [TrajectoryQuery] defines how many of the latest historical positions to export.
+#[derive(Debug, Deserialize)]
+struct TrajectoryQuery {
+    limit: Option<usize>,
+}
+
+pub(crate) fn trajectory(
+    db: Arc<sled::Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_trajectory(
+        db: Arc<sled::Db>,
+        agent_identifier: String,
+        query: TrajectoryQuery,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let states = history::recent(&db, &agent_identifier, query.limit.unwrap_or(100))
+            .map_err(db_failure)?;
+        if states.is_empty() {
+            return Err(warp::reject::custom(CollisionMonitorError::MissingHistory));
+        }
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "text/csv")
+            .header(
+                http::header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}_trajectory.csv\"",
+                    agent_identifier
+                ),
+            )
+            .body(history::trajectory_csv(&states)))
+    }
+
+    warp::path!("state" / String / "trajectory.csv")
+        .and(warp::get())
+        .and(warp::query::<TrajectoryQuery>())
+        .and_then(move |agent, query| get_trajectory(Arc::clone(&db), agent, query))
+}
+
+pub(crate) fn set_path(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+    reroutes: Arc<Mutex<HashMap<String, Vec<Path>>>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn put_path(
+        db: Arc<sled::Db>,
+        config: Arc<CollisionMonitorConfig>,
+        reroutes: Arc<Mutex<HashMap<String, Vec<Path>>>>,
+        agent_identifier: String,
+        path: Vec<Path>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let db_record = match db.get(&agent_identifier).map_err(db_failure)? {
+            Some(state) => state,
+            None => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::IncorrectDBRecord,
+                ));
+            }
+        };
+
+        let mut robot: Robot = match serde_json::from_slice(&db_record) {
+            Ok(robot) => robot,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        };
+
+        // the new path has to start where the robot currently is.
+        match path.first() {
+            Some(start) if robot.is_at(start, config.position_epsilon) => {}
+            _ => {
+                return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                    "the new path must start at the agent's current position",
+                )))
+            }
+        }
+
+        robot.path = path.clone();
+        robot.path_index = 0;
+
+        let body = match serde_json::to_string(&robot) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        db.insert(&agent_identifier, body.clone())
+            .map_err(db_failure)?;
+        // hand the path to the RPC server, which applies it to the robot's next state.
+        reroutes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(agent_identifier.clone(), path);
+
+        log::info!(
+            "Rerouted {} along {} points",
+            agent_identifier,
+            robot.path.len()
+        );
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("state" / String / "path")
+        .and(warp::put())
+        .and(warp::body::json())
+        .and_then(move |agent, path| {
+            put_path(
+                Arc::clone(&db),
+                Arc::clone(&config),
+                Arc::clone(&reroutes),
+                agent,
+                path,
+            )
+        })
+}
+
+#[derive(Debug, Deserialize)]
+struct BatteryUpdate {
+    battery_level: f64,
+}
+
+pub(crate) fn set_battery(
+    db: Arc<sled::Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn patch_battery(
+        db: Arc<sled::Db>,
+        agent_identifier: String,
+        update: BatteryUpdate,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if !(0.0..=100.0).contains(&update.battery_level) {
+            return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                "battery_level must be between 0 and 100",
+            )));
+        }
+
+        let db_record = match db.get(&agent_identifier).map_err(db_failure)? {
+            Some(state) => state,
+            None => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::IncorrectDBRecord,
+                ));
+            }
+        };
+
+        let mut robot: Robot = match serde_json::from_slice(&db_record) {
+            Ok(robot) => robot,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        };
+        robot.battery_level = update.battery_level;
+
+        let body = match serde_json::to_string(&robot) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        };
+
+        db.insert(&agent_identifier, body.as_bytes())
+            .map_err(db_failure)?;
+
+        log::info!(
+            "Set battery level of {} to {}",
+            agent_identifier,
+            update.battery_level
+        );
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("state" / String / "battery")
+        .and(warp::patch())
+        .and(warp::body::json())
+        .and_then(move |agent, update| patch_battery(Arc::clone(&db), agent, update))
+}
+
+/// [ProgressResponse] defines how far a robot is along its path.
+#[derive(Debug, Serialize)]
+struct ProgressResponse {
+    device_id: String,
+    path_index: usize,
+    path_length: usize,
+    progress: f64,
+    complete: bool,
+}
+
+pub(crate) fn progress(
+    db: Arc<sled::Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_progress(
+        db: Arc<sled::Db>,
+        agent_identifier: String,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let db_record = match db.get(&agent_identifier).map_err(db_failure)? {
+            Some(state) => state,
+            None => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::IncorrectDBRecord,
+                ));
+            }
+        };
+
+        let robot: Robot = match serde_json::from_slice(&db_record) {
+            Ok(robot) => robot,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        };
+
+        let body = match serde_json::to_string(&ProgressResponse {
+            device_id: robot.device_id.clone(),
+            path_index: robot.path_index,
+            path_length: robot.path.len(),
+            progress: robot.path_progress(),
+            complete: robot.path_complete(),
+        }) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("progress" / String)
+        .and(warp::get())
+        .and_then(move |agent| get_progress(Arc::clone(&db), agent))
+}
+
+pub(crate) fn history(
+    db: Arc<sled::Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_history(
+        db: Arc<sled::Db>,
+        agent_identifier: String,
+        query: TrajectoryQuery,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let states = history::recent(&db, &agent_identifier, query.limit.unwrap_or(100))
+            .map_err(db_failure)?;
+        if states.is_empty() {
+            return Err(warp::reject::custom(CollisionMonitorError::MissingHistory));
+        }
+
+        let body = match serde_json::to_string(&states) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("history" / String)
+        .and(warp::get())
+        .and(warp::query::<TrajectoryQuery>())
+        .and_then(move |agent, query| get_history(Arc::clone(&db), agent, query))
+}
+
+pub(crate) fn evict(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn delete_agent(
+        db: Arc<sled::Db>,
+        config: Arc<CollisionMonitorConfig>,
+        agent_identifier: String,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if db.remove(&agent_identifier).map_err(db_failure)?.is_none() {
+            return Err(warp::reject::custom(
+                CollisionMonitorError::IncorrectDBRecord,
+            ));
+        }
+
+        if config.record_history {
+            let removed = history::clear(&db, &agent_identifier).map_err(db_failure)?;
+            log::info!("Cleared {} history states of {}", removed, agent_identifier);
+        }
+        staleness::forget(&db, &agent_identifier).map_err(db_failure)?;
+        log::info!("Evicted {} from the monitor", agent_identifier);
+
+        Ok(http::StatusCode::NO_CONTENT)
+    }
+
+    warp::path!("state" / String)
+        .and(warp::delete())
+        .and_then(move |agent| delete_agent(Arc::clone(&db), Arc::clone(&config), agent))
+}
+
+pub(crate) fn stale(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn get_stale(
+        db: Arc<sled::Db>,
+        config: Arc<CollisionMonitorConfig>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let stale_after_ms = match config.stale_after_ms {
+            Some(stale_after_ms) => stale_after_ms,
+            None => return Err(warp::reject::not_found()),
+        };
+
+        let stale: Vec<StaleRobot> =
+            staleness::stale(&db, chrono::Utc::now().timestamp_millis(), stale_after_ms)
+                .map_err(db_failure)?
+                .into_iter()
+                .map(|(robot, last_seen_ms)| StaleRobot {
+                    device_id: robot.device_id,
+                    last_seen_ms,
+                })
+                .collect();
+
+        let body = match serde_json::to_string(&stale) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    warp::path!("stale")
+        .and(warp::get())
+        .and_then(move || get_stale(Arc::clone(&db), Arc::clone(&config)))
+}
+
+pub(crate) fn control(
+    db: Arc<sled::Db>,
+    commands: Arc<Mutex<HashMap<String, MotionState>>>,
+    events: broadcast::Sender<Robot>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("ws")
+        .and(warp::ws())
+        .map(move |ws: warp::ws::Ws| {
+            let (db, commands, updates) =
+                (Arc::clone(&db), Arc::clone(&commands), events.subscribe());
+            ws.on_upgrade(move |socket| control::session(socket, db, commands, updates))
+        })
+}
+
+pub(crate) fn events(
+    events: broadcast::Sender<Robot>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("events").and(warp::get()).map(move || {
+        warp::sse::reply(warp::sse::keep_alive().stream(event_stream(events.subscribe())))
+    })
+}
+
+/// `event_stream` turns the state updates of a subscription into `state` events carrying the robot
+/// state as JSON. A subscriber too slow to keep up skips the updates it fell behind on, so it never
+/// holds up the monitor.
+fn event_stream(
+    receiver: broadcast::Receiver<Robot>,
+) -> impl Stream<Item = Result<warp::sse::Event, Infallible>> {
+    BroadcastStream::new(receiver).filter_map(|update| match update {
+        Ok(robot) => match warp::sse::Event::default().event("state").json_data(&robot) {
+            Ok(event) => Some(Ok(event)),
+            Err(e) => {
+                log::warn!(
+                    "Could not serialize state of {:?}: {:?}",
+                    robot.device_id,
+                    e
+                );
+                None
+            }
+        },
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+            log::warn!(
+                "Events subscriber lagged behind, skipping {} updates",
+                skipped
+            );
+            None
+        }
+    })
+}
+
+pub(crate) fn batch(
+    db: Arc<sled::Db>,
+    config: Arc<CollisionMonitorConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    async fn post_batch(
+        db: Arc<sled::Db>,
+        collision_monitor: Arc<CollisionMonitor>,
+        robots: Vec<Robot>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let mut device_ids: Vec<&str> = robots
+            .iter()
+            .map(|robot| robot.device_id.as_str())
+            .collect();
+        device_ids.sort_unstable();
+        if device_ids.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                "the batch holds more than one state for an agent",
+            )));
+        }
+
+        let updated_states = match collision_monitor.trigger_collision_monitor(robots.clone()) {
+            Ok(updated_states) => updated_states,
+            Err(MonitorError::IncompleteAgentSet { received, expected }) => {
+                log::warn!(
+                    "Rejecting batch of {} states, {} agents are configured",
+                    received,
+                    expected
+                );
+                return Err(warp::reject::custom(CollisionMonitorError::IncorrectInput(
+                    "the batch must hold exactly one state for each of num_agents agents",
+                )));
+            }
+            Err(MonitorError::DeadlockUnresolvable { device_ids }) => {
+                log::error!(
+                    "Deadlock between {:?} cannot be resolved, holding the fleet",
+                    device_ids
+                );
+                CollisionMonitor::pause_robot_states(robots)
+            }
+        };
+
+        for state in &updated_states {
+            let record = match serde_json::to_vec(state) {
+                Ok(record) => record,
+                Err(_) => {
+                    return Err(warp::reject::custom(
+                        CollisionMonitorError::DeserializationFailure,
+                    ));
+                }
+            };
+            db.insert(&state.device_id, record).map_err(db_failure)?;
+
+            if collision_monitor.config.record_history {
+                history::record(&db, state, collision_monitor.config.history_limit)
+                    .map_err(db_failure)?;
+            }
+        }
+
+        let body = match serde_json::to_string(&updated_states) {
+            Ok(str) => str,
+            Err(_) => {
+                return Err(warp::reject::custom(
+                    CollisionMonitorError::DeserializationFailure,
+                ));
+            }
+        }
+        .as_bytes()
+        .to_vec();
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body))
+    }
+
+    let collision_monitor = Arc::new(CollisionMonitor::new((*config).clone()));
+
+    warp::path!("batch")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(move |robots| post_batch(Arc::clone(&db), Arc::clone(&collision_monitor), robots))
+}
+
+/// `stored_robots` reads every robot state saved in the DB, skipping records that fail to deserialize.
+fn stored_robots(db: &sled::Db) -> Vec<Robot> {
+    db.iter()
+        .values()
+        .filter_map(|value| value.ok())
+        .filter_map(|value| serde_json::from_slice::<Robot>(&value).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_config;
+    use std::time::Duration;
+
+    /// `temporary_db` opens a throwaway sled DB for route tests.
+    fn temporary_db() -> Arc<sled::Db> {
+        Arc::new(
+            sled::Config::new()
+                .temporary(true)
+                .open()
+                .expect("Failed to open sled db"),
+        )
+    }
+
+    /// `temporary_path` returns a unique path in the system temp directory.
+    fn temporary_path(name: &str) -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+
+        std::env::temp_dir().join(format!("{}_{}_{}", std::process::id(), nanos, name))
+    }
+
+    /// `stored_robot` saves a robot state with the given device id into the DB.
+    fn stored_robot(db: &sled::Db, device_id: &str, state: MotionState, battery_level: f64) {
+        let robot = Robot {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            loaded: false,
+            timestamp: 0,
+            path: vec![Path {
+                x: 0.0,
+                y: 0.0,
+                theta: 0.0,
+            }],
+            device_id: device_id.to_string(),
+            state,
+            battery_level,
+            ..Default::default()
+        };
+
+        db.insert(device_id, serde_json::to_vec(&robot).unwrap())
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_routes_dashboard() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Pause, 90.0);
+        stored_robot(&db, "robot2", MotionState::Pause, 10.0);
+        stored_robot(&db, "robot3", MotionState::Resume, 50.0);
+
+        let robots = stored_robots(&db);
+        let fleet_dashboard = Arc::new(RwLock::new(Dashboard::default()));
+        fleet_dashboard.write().unwrap().record_round(
+            Duration::from_millis(4),
+            &[("robot1".to_string(), "robot2".to_string())],
+            &robots,
+            10,
+        );
+
+        let config = Arc::new(CollisionMonitorConfig {
+            enable_dashboard: true,
+            ..test_config()
+        });
+        let response = warp::test::request()
+            .method("GET")
+            .path("/dashboard")
+            .reply(&dashboard(db, fleet_dashboard, config))
+            .await;
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let blob: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(blob["counts"]["agents"], 3);
+        assert_eq!(blob["counts"]["paused"], 2);
+        assert_eq!(blob["counts"]["resumed"], 1);
+        assert_eq!(blob["round_latencies_ms"][0], 4.0);
+        assert_eq!(blob["top_conflict_robots"][0]["conflicts"], 1);
+        assert_eq!(
+            blob["deadlock_groups"],
+            serde_json::json!([["robot1", "robot2"]])
+        );
+        assert_eq!(blob["low_battery_robots"][0]["device_id"], "robot2");
+    }
+
+    #[tokio::test]
+    async fn test_routes_fleet_freeze_round_trip() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        stored_robot(&db, "robot2", MotionState::Pause, 40.0);
+        let fleet_before: Vec<Robot> = stored_robots(&db);
+
+        let snapshot_path = temporary_path("fleet_snapshot.json");
+        let config = Arc::new(CollisionMonitorConfig {
+            enable_fleet_freeze: true,
+            freeze_snapshot_path: snapshot_path.to_string_lossy().to_string(),
+            ..test_config()
+        });
+        let filter = fleet_freeze(
+            Arc::clone(&db),
+            Arc::new(Mutex::new(FleetFreeze::default())),
+            config,
+        );
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/fleet/freeze")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(stored_robots(&db)
+            .iter()
+            .all(|robot| robot.state == MotionState::Pause));
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/fleet/unfreeze")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let fleet_after = stored_robots(&db);
+        assert_eq!(
+            serde_json::to_value(&fleet_after).unwrap(),
+            serde_json::to_value(&fleet_before).unwrap()
+        );
+
+        std::fs::remove_file(snapshot_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_routes_collision_check() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+
+        let config = Arc::new(CollisionMonitorConfig {
+            enable_collision_check: true,
+            ..test_config()
+        });
+        let filter = collision_check(Arc::clone(&db), config);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/collision-check")
+            .json(&serde_json::json!({ "x": 0.5, "y": 0.5 }))
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["collides"], true);
+        assert_eq!(
+            body["conflicting_device_ids"],
+            serde_json::json!(["robot1"])
+        );
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/collision-check")
+            .json(&serde_json::json!({ "x": 5.0, "y": 5.0, "theta": 0.0 }))
+            .reply(&filter)
+            .await;
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["collides"], false);
+        assert_eq!(body["conflicting_device_ids"], serde_json::json!([]));
+
+        // the check never mutates the stored fleet.
+        assert_eq!(stored_robots(&db).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_routes_history_bounded() {
+        let db = temporary_db();
+        for timestamp in [50, 10, 40, 20, 30] {
+            let robot = Robot {
+                timestamp,
+                device_id: "robot1".to_string(),
+                ..Default::default()
+            };
+            history::record(&db, &robot, Some(3)).unwrap();
+        }
+        let filter = history(Arc::clone(&db)).recover(crate::error_codes::handle_rejection);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/history/robot1")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let states: Vec<Robot> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            states
+                .iter()
+                .map(|state| state.timestamp)
+                .collect::<Vec<_>>(),
+            vec![30, 40, 50]
+        );
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/history/robot1?limit=2")
+            .reply(&filter)
+            .await;
+        let states: Vec<Robot> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            states
+                .iter()
+                .map(|state| state.timestamp)
+                .collect::<Vec<_>>(),
+            vec![40, 50]
+        );
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/history/robot2")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_trajectory_csv() {
+        let db = temporary_db();
+        for (timestamp, x) in [(30, 3.0), (10, 1.0), (20, 2.0)] {
+            let robot = Robot {
+                x,
+                timestamp,
+                device_id: "robot1".to_string(),
+                ..Default::default()
+            };
+            history::record(&db, &robot, None).unwrap();
+        }
+        let filter = trajectory(Arc::clone(&db)).recover(crate::error_codes::handle_rejection);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/state/robot1/trajectory.csv?limit=2")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/csv");
+        assert_eq!(
+            response.headers()["content-disposition"],
+            "attachment; filename=\"robot1_trajectory.csv\""
+        );
+        assert_eq!(
+            std::str::from_utf8(response.body()).unwrap(),
+            "timestamp,x,y,theta,state,battery\n\
+             20,2,0,0,resume,100\n\
+             30,3,0,0,resume,100\n"
+        );
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/state/robot2/trajectory.csv")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_conflict_graph_dot() {
+        let db = temporary_db();
+        for (device_id, x, y, state) in [
+            ("robot1", 0.0, 0.0, MotionState::Pause),
+            ("robot2", 0.5, 0.0, MotionState::Pause),
+            ("robot3", 0.25, 0.4, MotionState::Resume),
+        ] {
+            let robot = Robot {
+                x,
+                y,
+                device_id: device_id.to_string(),
+                state,
+                ..Default::default()
+            };
+            db.insert(device_id, serde_json::to_vec(&robot).unwrap())
+                .unwrap();
+        }
+
+        let config = Arc::new(CollisionMonitorConfig {
+            enable_conflict_graph: true,
+            ..test_config()
+        });
+        let response = warp::test::request()
+            .method("GET")
+            .path("/conflicts.dot")
+            .reply(&conflict_graph(db, config))
+            .await;
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/vnd.graphviz");
+        assert_eq!(
+            std::str::from_utf8(response.body()).unwrap(),
+            "graph conflicts {\n\
+             \x20   \"robot1\" [label=\"robot1\\npause\", color=orange];\n\
+             \x20   \"robot2\" [label=\"robot2\\npause\", color=orange];\n\
+             \x20   \"robot3\" [label=\"robot3\\nresume\", color=green];\n\
+             \x20   \"robot1\" -- \"robot2\";\n\
+             \x20   \"robot1\" -- \"robot3\";\n\
+             \x20   \"robot2\" -- \"robot3\";\n\
+             }\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_routes_state_predicted_next() {
+        let db = temporary_db();
+        let robot = Robot {
+            path: vec![
+                Path {
+                    x: 0.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 1.0,
+                    y: 2.0,
+                    theta: 0.0,
+                },
+            ],
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        db.insert("robot1", serde_json::to_vec(&robot).unwrap())
+            .unwrap();
+        let filter = agents(db, Arc::new(test_config()), Arc::new(AtomicU64::new(0)));
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/state/robot1?include=predicted")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body["predicted_next"],
+            serde_json::json!({ "x": 1.0, "y": 2.0, "theta": 0.0 })
+        );
+        assert_eq!(body["x"], 0.0);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/state/robot1")
+            .reply(&filter)
+            .await;
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert!(body.get("predicted_next").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_routes_state_freshness() {
+        let db = temporary_db();
+        let latest_tick = Arc::new(AtomicU64::new(0));
+        let filter = agents(
+            Arc::clone(&db),
+            Arc::new(test_config()),
+            Arc::clone(&latest_tick),
+        );
+        let store = |device_id: &str, tick: u64| {
+            let robot = Robot {
+                device_id: device_id.to_string(),
+                tick,
+                ..Default::default()
+            };
+            db.insert(device_id, serde_json::to_vec(&robot).unwrap())
+                .unwrap();
+        };
+        let fresh = |device_id: &str| {
+            let filter = filter.clone();
+            let path = format!("/state/{}", device_id);
+            async move {
+                let response = warp::test::request()
+                    .method("GET")
+                    .path(&path)
+                    .reply(&filter)
+                    .await;
+                let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+                body["fresh"].clone()
+            }
+        };
+
+        // both robots take part in the first tick.
+        store("robot1", 1);
+        store("robot2", 1);
+        latest_tick.store(1, Ordering::SeqCst);
+        assert_eq!(fresh("robot1").await, true);
+        assert_eq!(fresh("robot2").await, true);
+
+        // robot2 misses the second one.
+        store("robot1", 2);
+        latest_tick.store(2, Ordering::SeqCst);
+        assert_eq!(fresh("robot1").await, true);
+        assert_eq!(fresh("robot2").await, false);
+    }
+
+    #[tokio::test]
+    async fn test_routes_not_found() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 50.0);
+        let filter = agents(db, Arc::new(test_config()), Arc::new(AtomicU64::new(0)))
+            .recover(crate::error_codes::handle_rejection);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/state/robot2")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["code"], 2102);
+        assert_eq!(body["error"], "IncorrectDBRecord");
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/unknown")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": 2106,
+                "error": "UnknownRoute",
+                "message": "no endpoint matches the request",
+            })
+        );
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/state/robot1")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_routes_corrupt_record() {
+        let db = temporary_db();
+        db.insert("empty", Vec::new()).unwrap();
+        db.insert("corrupt", &b"{\"x\": 1.0,"[..]).unwrap();
+        let filter = agents(
+            Arc::clone(&db),
+            Arc::new(test_config()),
+            Arc::new(AtomicU64::new(0)),
+        )
+        .or(progress(Arc::clone(&db)))
+        .or(set_battery(Arc::clone(&db)))
+        .recover(crate::error_codes::handle_rejection);
+
+        for device_id in ["empty", "corrupt"] {
+            let requests = [
+                warp::test::request()
+                    .method("GET")
+                    .path(&format!("/state/{}", device_id)),
+                warp::test::request()
+                    .method("GET")
+                    .path(&format!("/progress/{}", device_id)),
+                warp::test::request()
+                    .method("PATCH")
+                    .path(&format!("/state/{}/battery", device_id))
+                    .json(&serde_json::json!({ "battery_level": 50.0 })),
+            ];
+            for request in requests {
+                let response = request.reply(&filter).await;
+                assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+                let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+                assert_eq!(body["error"], "DeserializationFailure");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_evict() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 50.0);
+        stored_robot(&db, "robot2", MotionState::Resume, 50.0);
+        for device_id in ["robot1", "robot2"] {
+            let robot: Robot =
+                serde_json::from_slice(&db.get(device_id).unwrap().unwrap()).unwrap();
+            history::record(&db, &robot, None).unwrap();
+        }
+        let config = Arc::new(CollisionMonitorConfig {
+            record_history: true,
+            ..test_config()
+        });
+        let filter = evict(Arc::clone(&db), config).recover(crate::error_codes::handle_rejection);
+
+        let response = warp::test::request()
+            .method("DELETE")
+            .path("/state/robot1")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NO_CONTENT);
+        assert!(db.get("robot1").unwrap().is_none());
+        assert!(history::recent(&db, "robot1", 10).unwrap().is_empty());
+        // other robots are left alone.
+        assert!(db.get("robot2").unwrap().is_some());
+        assert_eq!(history::recent(&db, "robot2", 10).unwrap().len(), 1);
+
+        let response = warp::test::request()
+            .method("DELETE")
+            .path("/state/robot1")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_stale() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 50.0);
+        stored_robot(&db, "robot2", MotionState::Resume, 50.0);
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        staleness::touch(&db, "robot1", now_ms).unwrap();
+        // robot2 last reported a minute ago.
+        staleness::touch(&db, "robot2", now_ms - 60_000).unwrap();
+
+        let config = Arc::new(CollisionMonitorConfig {
+            stale_after_ms: Some(10_000),
+            ..test_config()
+        });
+        let response = warp::test::request()
+            .method("GET")
+            .path("/stale")
+            .reply(&stale(Arc::clone(&db), config))
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(response.body()).unwrap(),
+            serde_json::json!([{ "device_id": "robot2", "last_seen_ms": now_ms - 60_000 }])
+        );
+
+        // without `stale_after_ms` staleness is not tracked.
+        let response = warp::test::request()
+            .method("GET")
+            .path("/stale")
+            .reply(&stale(db, Arc::new(test_config())))
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_event_stream() {
+        let (sender, _) = broadcast::channel(1);
+        let mut stream = Box::pin(event_stream(sender.subscribe()));
+
+        let robot = |device_id: &str| Robot {
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+        sender.send(robot("robot1")).unwrap();
+
+        let event = stream.next().await.unwrap().unwrap().to_string();
+        assert!(event.starts_with("event:state\n"));
+        assert!(event.contains("\"device_id\":\"robot1\""));
+
+        // a subscriber falling behind skips to the latest update instead of blocking the sender.
+        sender.send(robot("robot2")).unwrap();
+        sender.send(robot("robot3")).unwrap();
+        let event = stream.next().await.unwrap().unwrap().to_string();
+        assert!(event.contains("\"device_id\":\"robot3\""));
+    }
+
+    #[tokio::test]
+    async fn test_routes_control_pause_round_trip() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        let commands = Arc::new(Mutex::new(HashMap::new()));
+        let (events, _) = broadcast::channel(4);
+        let filter = control(Arc::clone(&db), Arc::clone(&commands), events.clone());
+
+        let mut client = warp::test::ws()
+            .path("/ws")
+            .handshake(filter)
+            .await
+            .unwrap();
+        async fn receive(client: &mut warp::test::WsClient) -> serde_json::Value {
+            let frame = client.recv().await.unwrap();
+            serde_json::from_str(frame.to_str().unwrap()).unwrap()
+        }
+
+        client.send_text(r#"{"cmd":"pause","id":"robot1"}"#).await;
+        assert_eq!(
+            receive(&mut client).await,
+            serde_json::json!({ "type": "ack", "cmd": "pause", "id": "robot1" })
+        );
+        let stored: Robot = serde_json::from_slice(&db.get("robot1").unwrap().unwrap()).unwrap();
+        assert_eq!(stored.state, MotionState::Pause);
+        assert_eq!(commands.lock().unwrap()["robot1"], MotionState::Pause);
+
+        // the state the monitor then sends the robot is streamed back to the console.
+        events.send(stored).unwrap();
+        let frame = receive(&mut client).await;
+        assert_eq!(frame["type"], "state");
+        assert_eq!(frame["state"]["state"], "pause");
+
+        // commands are validated before anything is changed.
+        for (command, message) in [
+            (
+                r#"{"cmd":"pause","id":"robot9"}"#,
+                "no record exists for robot9",
+            ),
+            (
+                r#"{"cmd":"reroute","id":"robot1"}"#,
+                "unsupported command reroute",
+            ),
+        ] {
+            client.send_text(command).await;
+            assert_eq!(
+                receive(&mut client).await,
+                serde_json::json!({ "type": "error", "message": message })
+            );
+        }
+        assert_eq!(commands.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_routes_batch() {
+        let db = temporary_db();
+        let filter = batch(Arc::clone(&db), Arc::new(test_config()))
+            .recover(crate::error_codes::handle_rejection);
+        let robot = |device_id: &str, xs: &[f64]| Robot {
+            x: xs[0],
+            path: xs
+                .iter()
+                .map(|&x| Path {
+                    x,
+                    y: 0.0,
+                    theta: 0.0,
+                })
+                .collect(),
+            device_id: device_id.to_string(),
+            ..Default::default()
+        };
+
+        // two overlapping robots.
+        let response = warp::test::request()
+            .method("POST")
+            .path("/batch")
+            .json(&[robot("robot1", &[0.0, 1.0]), robot("robot2", &[0.5, 1.5])])
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let resolved: Vec<Robot> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(resolved.len(), 2);
+        for resolved_state in &resolved {
+            let stored: Robot =
+                serde_json::from_slice(&db.get(&resolved_state.device_id).unwrap().unwrap())
+                    .unwrap();
+            assert_eq!(
+                (stored.x, stored.state),
+                (resolved_state.x, resolved_state.state)
+            );
+        }
+        // only one of them moves on.
+        assert_eq!(
+            resolved
+                .iter()
+                .filter(|robot| robot.state == MotionState::Pause)
+                .count(),
+            1
+        );
+
+        // a batch missing an agent is rejected without touching the DB.
+        let response = warp::test::request()
+            .method("POST")
+            .path("/batch")
+            .json(&[robot("robot3", &[5.0, 6.0])])
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(response.body()).unwrap()["message"],
+            "the batch must hold exactly one state for each of num_agents agents"
+        );
+        assert!(db.get("robot3").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_routes_agents_list() {
+        let db = temporary_db();
+        let filter = agents_list(Arc::clone(&db));
+        let get = |path: &'static str| {
+            let filter = filter.clone();
+            async move {
+                let response = warp::test::request()
+                    .method("GET")
+                    .path(path)
+                    .reply(&filter)
+                    .await;
+                assert_eq!(response.status(), http::StatusCode::OK);
+                serde_json::from_slice::<serde_json::Value>(response.body()).unwrap()
+            }
+        };
+
+        assert_eq!(
+            get("/agents").await,
+            serde_json::json!({ "total": 0, "items": [] })
+        );
+
+        stored_robot(&db, "robot3", MotionState::Pause, 40.0);
+        stored_robot(&db, "robot2", MotionState::Resume, 60.0);
+        stored_robot(&db, "robot1", MotionState::Pause, 90.0);
+
+        assert_eq!(
+            get("/agents").await,
+            serde_json::json!({ "total": 3, "items": ["robot1", "robot2", "robot3"] })
+        );
+        assert_eq!(
+            get("/agents?state=pause").await,
+            serde_json::json!({ "total": 2, "items": ["robot1", "robot3"] })
+        );
+        assert_eq!(
+            get("/agents?state=Pause&limit=1&offset=1").await,
+            serde_json::json!({ "total": 2, "items": ["robot3"] })
+        );
+
+        let page = get("/agents?full=true&state=resume").await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["device_id"], "robot2");
+        assert_eq!(page["items"][0]["battery_level"], 60.0);
+    }
+
+    #[tokio::test]
+    async fn test_routes_agents_list_offset_boundaries() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Pause, 90.0);
+        stored_robot(&db, "robot2", MotionState::Pause, 40.0);
+        let filter = agents_list(Arc::clone(&db));
+
+        for (path, items) in [
+            (
+                "/agents?offset=0&limit=2",
+                serde_json::json!(["robot1", "robot2"]),
+            ),
+            ("/agents?offset=1&limit=5", serde_json::json!(["robot2"])),
+            ("/agents?offset=2", serde_json::json!([])),
+            ("/agents?offset=10&limit=1", serde_json::json!([])),
+            ("/agents?limit=0", serde_json::json!([])),
+        ] {
+            let response = warp::test::request()
+                .method("GET")
+                .path(path)
+                .reply(&filter)
+                .await;
+            let page: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(page["total"], 2, "{}", path);
+            assert_eq!(page["items"], items, "{}", path);
+        }
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/agents?state=moving")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_routes_health() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        let filter = health(Arc::clone(&db), Arc::new(test_config()));
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/health")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "db": "ok", "agents_known": 1, "num_agents": 2 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_routes_config_redacts_password() {
+        let filter = super::config(Arc::new(CollisionMonitorConfig {
+            queue_hub_pw: "hunter2".to_string(),
+            ..test_config()
+        }));
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/config")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["queue_hub_pw"], "<redacted>");
+        assert_eq!(body["num_agents"], 2);
+        assert!(!String::from_utf8_lossy(response.body()).contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_routes_admin_reset() {
+        let db = temporary_db();
+        let reset_request = || warp::test::request().method("POST").path("/admin/reset");
+
+        let disabled = reset(Arc::clone(&db), Arc::new(test_config()));
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        let response = reset_request().reply(&disabled).await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(db.len(), 1);
+
+        let enabled = reset(
+            Arc::clone(&db),
+            Arc::new(CollisionMonitorConfig {
+                enable_admin_routes: true,
+                ..test_config()
+            }),
+        );
+        stored_robot(&db, "robot2", MotionState::Pause, 80.0);
+        let state = Robot {
+            device_id: "robot2".to_string(),
+            ..Default::default()
+        };
+        history::record(&db, &state, None).unwrap();
+        staleness::touch(&db, "robot1", 0).unwrap();
+
+        let response = reset_request().reply(&enabled).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "removed": 4 }));
+        assert!(db.is_empty());
+        assert!(history::recent(&db, "robot2", 10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_routes_admin_halt() {
+        let halted = Arc::new(AtomicBool::new(false));
+        let request = |action: &str| {
+            warp::test::request()
+                .method("POST")
+                .path(&format!("/admin/{}", action))
+        };
+
+        let disabled = halt(Arc::clone(&halted), Arc::new(test_config()));
+        let response = request("halt").reply(&disabled).await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        assert!(!halted.load(Ordering::SeqCst));
+
+        let filter = halt(
+            Arc::clone(&halted),
+            Arc::new(CollisionMonitorConfig {
+                enable_admin_routes: true,
+                ..test_config()
+            }),
+        );
+        let response = request("halt").reply(&filter).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.body(), r#"{"halted":true}"#);
+        assert!(halted.load(Ordering::SeqCst));
+
+        let response = request("resume").reply(&filter).await;
+        assert_eq!(response.body(), r#"{"halted":false}"#);
+        assert!(!halted.load(Ordering::SeqCst));
+
+        let response = request("pause").reply(&filter).await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_metrics() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.record_collisions(4);
+        let filter = super::metrics(Arc::clone(&metrics));
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/plain; version=0.0.4"
+        );
+
+        let body = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(body.contains("collisions_detected_total 4\n"));
+    }
+
+    #[tokio::test]
+    async fn test_routes_progress() {
+        let db = temporary_db();
+        let robot = Robot {
+            path: (0..5)
+                .map(|x| Path {
+                    x: x as f64,
+                    y: 0.0,
+                    theta: 0.0,
+                })
+                .collect(),
+            path_index: 1,
+            x: 1.0,
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        db.insert("robot1", serde_json::to_vec(&robot).unwrap())
+            .unwrap();
+        let filter = progress(Arc::clone(&db)).recover(crate::error_codes::handle_rejection);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/progress/robot1")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "device_id": "robot1",
+                "path_index": 1,
+                "path_length": 5,
+                "progress": 0.25,
+                "complete": false,
+            })
+        );
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/progress/robot2")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_set_path() {
+        let db = temporary_db();
+        let robot = Robot {
+            x: 1.0,
+            path: vec![
+                Path {
+                    x: 1.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: 2.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+            ],
+            path_index: 1,
+            device_id: "robot1".to_string(),
+            ..Default::default()
+        };
+        db.insert("robot1", serde_json::to_vec(&robot).unwrap())
+            .unwrap();
+        let reroutes = Arc::new(Mutex::new(HashMap::new()));
+        let filter = set_path(
+            Arc::clone(&db),
+            Arc::new(test_config()),
+            Arc::clone(&reroutes),
+        )
+        .recover(crate::error_codes::handle_rejection);
+
+        // a start off the robot's position by floating-point noise still matches it.
+        let new_path = serde_json::json!([
+            { "x": 1.0 + 1e-10, "y": 0.0, "theta": 0.0 },
+            { "x": 1.0, "y": 1.0, "theta": 1.57 },
+            { "x": 1.0, "y": 2.0, "theta": 1.57 },
+        ]);
+        let response = warp::test::request()
+            .method("PUT")
+            .path("/state/robot1/path")
+            .json(&new_path)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let stored: Robot = serde_json::from_slice(&db.get("robot1").unwrap().unwrap()).unwrap();
+        assert_eq!(stored.path.len(), 3);
+        assert_eq!((stored.path[1].x, stored.path[1].y), (1.0, 1.0));
+        assert_eq!(stored.path_index, 0);
+        assert_eq!(reroutes.lock().unwrap()["robot1"].len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_routes_set_path_rejects_mismatched_start() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        let reroutes = Arc::new(Mutex::new(HashMap::new()));
+        let filter = set_path(
+            Arc::clone(&db),
+            Arc::new(test_config()),
+            Arc::clone(&reroutes),
+        )
+        .recover(crate::error_codes::handle_rejection);
+
+        let response = warp::test::request()
+            .method("PUT")
+            .path("/state/robot1/path")
+            .json(&serde_json::json!([
+                { "x": 5.0, "y": 5.0, "theta": 0.0 },
+                { "x": 6.0, "y": 5.0, "theta": 0.0 },
+            ]))
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+        let stored: Robot = serde_json::from_slice(&db.get("robot1").unwrap().unwrap()).unwrap();
+        assert_eq!(stored.path.len(), 1);
+        assert!(reroutes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_routes_set_battery() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        let filter = set_battery(Arc::clone(&db)).recover(crate::error_codes::handle_rejection);
+        let patch = |battery_level: serde_json::Value| {
+            warp::test::request()
+                .method("PATCH")
+                .path("/state/robot1/battery")
+                .json(&serde_json::json!({ "battery_level": battery_level }))
+                .reply(&filter)
+        };
+
+        let response = patch(42.5.into()).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let returned: Robot = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(returned.battery_level, 42.5);
+        let stored: Robot = serde_json::from_slice(&db.get("robot1").unwrap().unwrap()).unwrap();
+        assert_eq!(stored.battery_level, 42.5);
+        assert_eq!(stored.state, MotionState::Resume);
+    }
+
+    #[tokio::test]
+    async fn test_routes_set_battery_rejects_out_of_range() {
+        let db = temporary_db();
+        stored_robot(&db, "robot1", MotionState::Resume, 90.0);
+        let filter = set_battery(Arc::clone(&db)).recover(crate::error_codes::handle_rejection);
+
+        for battery_level in [-1.0, 100.5] {
+            let response = warp::test::request()
+                .method("PATCH")
+                .path("/state/robot1/battery")
+                .json(&serde_json::json!({ "battery_level": battery_level }))
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(body["error"], "IncorrectInput");
+        }
+
+        let stored: Robot = serde_json::from_slice(&db.get("robot1").unwrap().unwrap()).unwrap();
+        assert_eq!(stored.battery_level, 90.0);
+    }
+}
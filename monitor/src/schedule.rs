@@ -0,0 +1,135 @@
+use crate::collision_monitor::{CollisionMonitor, MotionState, Robot};
+use crate::config::CollisionMonitorConfig;
+use crate::preview;
+
+/// `MAX_SCHEDULE_TICKS` bounds how many ticks a schedule may take before planning gives up.
+const MAX_SCHEDULE_TICKS: usize = 10_000;
+
+/// `schedule_snapshot` loads a JSON array of robot states from `snapshot_path` and schedules it.
+pub(crate) fn schedule_snapshot(
+    config: CollisionMonitorConfig,
+    snapshot_path: &str,
+) -> Result<Vec<Vec<MotionState>>, String> {
+    schedule(config, preview::load_snapshot(snapshot_path)?)
+}
+
+/// `schedule` plans the robots' full paths up front: it runs the fleet to completion, resolving
+/// each tick the way the monitor would live, and returns for each tick the state each robot takes,
+/// in the order of `robots`, so that the fleet can run the schedule without live monitoring.
+/// Unlike live robots, which keep a pause until the monitor lifts it, a robot paused in one tick
+/// asks to resume in the next. Planning fails when the fleet gets back to a state it was already
+/// in, which would never complete, or when it takes more than `MAX_SCHEDULE_TICKS` ticks.
+pub(crate) fn schedule(
+    config: CollisionMonitorConfig,
+    robots: Vec<Robot>,
+) -> Result<Vec<Vec<MotionState>>, String> {
+    // every robot paused last tick asks to move on, and is held again if it still can't.
+    let result =
+        CollisionMonitor::new(config).run_to_completion(robots, MAX_SCHEDULE_TICKS, |robots| {
+            for robot in robots.iter_mut() {
+                if robot.state == MotionState::Pause {
+                    robot.state = MotionState::Resume;
+                }
+            }
+        });
+
+    if result.deadlocked {
+        return Err(format!(
+            "Fleet deadlocks at tick {} and cannot complete its paths",
+            result.snapshots.len()
+        ));
+    }
+    if !result.completed {
+        return Err(format!(
+            "Fleet did not complete its paths within {} ticks",
+            MAX_SCHEDULE_TICKS
+        ));
+    }
+
+    Ok(result
+        .snapshots
+        .iter()
+        .map(|robots| robots.iter().map(|robot| robot.state).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision_monitor::Path;
+    use crate::config::{test_config, ResolutionStrategy};
+
+    fn robot(device_id: &str, points: &[(f64, f64)]) -> Robot {
+        let path: Vec<Path> = points
+            .iter()
+            .map(|&(x, y)| Path { x, y, theta: 0.0 })
+            .collect();
+
+        Robot {
+            x: path[0].x,
+            y: path[0].y,
+            path,
+            device_id: device_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_schedule_two_robot_crossing() {
+        let config = CollisionMonitorConfig {
+            lookahead_steps: Some(1),
+            resolution_strategy: ResolutionStrategy::PauseLowerPriority,
+            ..test_config()
+        };
+        // left alone, both robots would reach the crossing at (4, 0) on the second tick.
+        let mut robots = vec![
+            robot("robot1", &[(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (6.0, 0.0)]),
+            robot(
+                "robot2",
+                &[(4.0, -4.0), (4.0, -2.0), (4.0, 0.0), (4.0, 2.0)],
+            ),
+        ];
+        robots[0].priority = 1;
+
+        let ticks = schedule(config.clone(), robots.clone()).unwrap();
+        // robot2 waits a tick for robot1 to clear the crossing.
+        assert_eq!(
+            ticks,
+            vec![
+                vec![MotionState::Resume, MotionState::Resume],
+                vec![MotionState::Resume, MotionState::Pause],
+                vec![MotionState::Resume, MotionState::Resume],
+                vec![MotionState::Resume, MotionState::Resume],
+            ]
+        );
+
+        // following the schedule, the robots reach the ends of their paths without ever colliding.
+        let collision_monitor = CollisionMonitor::new(config);
+        let mut fleet = robots;
+        for states in &ticks {
+            for (robot, state) in fleet.iter_mut().zip(states) {
+                if *state == MotionState::Resume {
+                    if let Some(point) = robot.path.get(robot.path_index + 1).cloned() {
+                        robot.x = point.x;
+                        robot.y = point.y;
+                        robot.path_index += 1;
+                    }
+                }
+                robot.state = *state;
+            }
+            assert!(collision_monitor.conflicting_pairs(&fleet).is_empty());
+        }
+        assert!(fleet.iter().all(Robot::path_complete));
+    }
+
+    #[test]
+    fn test_schedule_fails_on_deadlock() {
+        // head-on robots swapping places can never get past each other.
+        let robots = vec![
+            robot("robot1", &[(0.0, 0.0), (0.5, 0.0), (1.0, 0.0)]),
+            robot("robot2", &[(1.0, 0.0), (0.5, 0.0), (0.0, 0.0)]),
+        ];
+
+        assert!(schedule(test_config(), robots).is_err());
+    }
+}
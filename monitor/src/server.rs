@@ -1,28 +1,196 @@
-use crate::collision_monitor::{CollisionMonitor, Robot};
-use crate::config::CollisionMonitorConfig;
+use crate::acks::AckTracker;
+use crate::collision_monitor::{CollisionMonitor, MonitorError, MotionState, Path, Robot};
+use crate::config::{CollisionMonitorConfig, DeadlockDetector};
+use crate::connection;
+use crate::correlation::{CorrelationTracker, RecentCorrelationIds};
+use crate::dashboard::Dashboard;
+use crate::freeze::FleetFreeze;
+use crate::heatmap::Heatmap;
+use crate::history;
+#[cfg(feature = "sqlite")]
+use crate::incidents::{Incident, IncidentSink};
+#[cfg(feature = "kafka")]
+use crate::kafka::{DecisionMessage, DecisionSink};
+use crate::metrics::Metrics;
+use crate::slow_start::SlowStart;
+use crate::smoothing::PositionFilter;
+use crate::staleness;
+use crate::timeout_detector::TimeoutDetector;
+use crate::utilization::Utilization;
+use crate::wal::{RoundWal, WalEntry};
 use amiquip::{
-    AmqpProperties, Connection, ConsumerMessage, ConsumerOptions, Exchange, Publish,
-    QueueDeclareOptions, Result,
+    AmqpProperties, ConsumerMessage, ConsumerOptions, Exchange, Publish, QueueDeclareOptions,
 };
-use std::sync::Arc;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Select};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// how often the consumer loop checks for a shutdown request while no message arrives
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// how many times longer or shorter than its peers' median a robot's reporting interval may be
+/// before the monitor warns that it breaks the batch assumption
+const INTERVAL_DEVIATION_FACTOR: f64 = 2.0;
 
 pub(crate) struct Server;
 
+/// [SharedState] defines the state shared between the RPC server and the REST API.
+#[derive(Clone)]
+pub(crate) struct SharedState {
+    pub dashboard: Arc<RwLock<Dashboard>>,
+    pub fleet_freeze: Arc<Mutex<FleetFreeze>>,
+    pub utilization: Arc<RwLock<Utilization>>,
+    pub heatmap: Arc<RwLock<Heatmap>>,
+    pub acks: Arc<RwLock<AckTracker>>,
+    pub metrics: Arc<Metrics>,
+    // paths set through the REST API, applied to each robot's next state
+    pub reroutes: Arc<Mutex<HashMap<String, Vec<Path>>>>,
+    // pause and resume commands sent over `/ws`, applied to each robot's next state
+    pub commands: Arc<Mutex<HashMap<String, MotionState>>>,
+    // set once the process is shutting down
+    pub shutdown: Arc<AtomicBool>,
+    // set through `/admin/halt` to pause every robot until `/admin/resume` clears it
+    pub halt: Arc<AtomicBool>,
+    // every state update sent to a robot, streamed to `/events` subscribers
+    pub events: broadcast::Sender<Robot>,
+    // tick of the latest batch resolved, telling `/state/{id}` whether a stored state is fresh
+    pub latest_tick: Arc<AtomicU64>,
+}
+
 impl Server {
+    /// `run` keeps the RPC server connected to the hub, reconnecting after `reconnect_backoff_ms`
+    /// whenever the connection is lost. Only the first connection recovers the round from the WAL;
+    /// after a reconnect the robots resend their states, so any stale round is dropped. It returns
+    /// once `shutdown` is set, leaving the WAL for the next start to recover.
+    pub(crate) fn run(config: CollisionMonitorConfig, db: Arc<sled::Db>, shared: SharedState) {
+        let backoff = Duration::from_millis(config.reconnect_backoff_ms);
+        loop {
+            let result = Self::start(config.clone(), Arc::clone(&db), shared.clone());
+            if shared.shutdown.load(Ordering::SeqCst) {
+                if let Err(e) = result {
+                    log::warn!("Connection to hub failed while shutting down: {:?}", e);
+                }
+                log::info!("RPC server shut down");
+                return;
+            }
+
+            match result {
+                Ok(()) => log::warn!(
+                    "Connection to hub closed, reconnecting in {}ms",
+                    backoff.as_millis()
+                ),
+                Err(e) => log::warn!(
+                    "Connection to hub failed: {:?}, reconnecting in {}ms",
+                    e,
+                    backoff.as_millis()
+                ),
+            }
+            Self::discard_round(&config);
+            std::thread::sleep(backoff);
+        }
+    }
+
+    /// `discard_round` clears the WAL of the round that was in flight when the connection was lost.
+    fn discard_round(config: &CollisionMonitorConfig) {
+        if let Some(path) = &config.wal_path {
+            if let Err(e) = RoundWal::open(path).and_then(|(mut wal, _)| wal.clear()) {
+                log::warn!("Could not clear WAL {}: {:?}", path, e);
+            }
+        }
+    }
+
     /// `start` spins up a Collission Monitor Server
-    pub(crate) fn start(config: CollisionMonitorConfig, db: Arc<sled::Db>) -> Result<()> {
-        let mut robot_states: Vec<Robot> = Vec::with_capacity(config.num_agents);
-        let mut reply_states: Vec<String> = Vec::with_capacity(config.num_agents);
-        let mut correlation_ids: Vec<String> = Vec::with_capacity(config.num_agents);
+    pub(crate) fn start(
+        config: CollisionMonitorConfig,
+        db: Arc<sled::Db>,
+        shared: SharedState,
+    ) -> connection::Result<()> {
+        let SharedState {
+            dashboard,
+            fleet_freeze,
+            utilization,
+            heatmap,
+            acks,
+            metrics,
+            reroutes,
+            commands,
+            shutdown,
+            halt,
+            events,
+            latest_tick,
+        } = shared;
+        let mut pending: HashMap<String, (Robot, String, String)> =
+            HashMap::with_capacity(config.num_agents.unwrap_or_default());
+        // latest reporting interval of each robot, compared against its peers' on every delivery.
+        let mut reporting_intervals: HashMap<String, u64> = HashMap::new();
+        let mut round_started = Instant::now();
+        let mut current_round: u64 = 0;
+        let mut correlation_tracker = CorrelationTracker::new(
+            config.correlation_id_window,
+            config.correlation_retry_rounds,
+        );
+        let mut recent_correlation_ids = config
+            .deduplicate_correlation_ids
+            .then(|| RecentCorrelationIds::new(config.dedup_window));
 
         // open connection.
-        let mut connection = Connection::insecure_open(&format!(
-            "amqp://{}:{}@{}:{}",
-            config.queue_hub_user, config.queue_hub_pw, config.hostname, config.hub_listening_port
-        ))?;
+        let mut connection = connection::connect(&config)?;
+
+        // open the incidents export, if configured.
+        #[cfg(feature = "sqlite")]
+        let incident_sink = config.incidents_db_path.as_ref().and_then(|path| {
+            IncidentSink::open(path)
+                .map_err(|e| log::warn!("Could not open incidents db {}: {:?}", path, e))
+                .ok()
+        });
+        #[cfg(not(feature = "sqlite"))]
+        if config.incidents_db_path.is_some() {
+            log::warn!("incidents_db_path is set but the monitor was built without `sqlite`");
+        }
+
+        // start the Kafka export, if configured.
+        #[cfg(feature = "kafka")]
+        let decision_sink = config.kafka_brokers.as_ref().and_then(|brokers| {
+            DecisionSink::start(brokers, config.kafka_topic.clone(), config.kafka_queue_size)
+                .map_err(|e| log::warn!("Could not start Kafka producer {}: {:?}", brokers, e))
+                .ok()
+        });
+        #[cfg(not(feature = "kafka"))]
+        if config.kafka_brokers.is_some() {
+            log::warn!("kafka_brokers is set but the monitor was built without `kafka`");
+        }
+
+        // set up position smoothing, if configured.
+        let mut position_filter = config.position_smoothing.map(PositionFilter::new);
+
+        // set up the timeout-based deadlock detector, if selected.
+        let mut timeout_detector = (config.deadlock_detector == DeadlockDetector::Timeout)
+            .then(|| TimeoutDetector::new(Duration::from_millis(config.deadlock_abort_ms)));
 
         // start collision_monitor.
-        let collision_monitor = CollisionMonitor::new(config);
+        let collision_monitor = CollisionMonitor::new(config).with_metrics(Arc::clone(&metrics));
+
+        // recover the round in flight when the monitor last went down, if a WAL is configured.
+        let mut wal = collision_monitor.config.wal_path.as_ref().and_then(|path| {
+            let (wal, entries) = RoundWal::open(path)
+                .map_err(|e| log::warn!("Could not open WAL {}: {:?}", path, e))
+                .ok()?;
+            if !entries.is_empty() {
+                log::info!("Recovered {} states of an in-flight round", entries.len());
+            }
+            for entry in entries {
+                Self::buffer_state(
+                    &mut pending,
+                    entry.state,
+                    entry.reply_to,
+                    entry.correlation_id,
+                );
+            }
+            Some(wal)
+        });
 
         // open a channel - None says let the library choose the channel ID.
         let channel = connection.open_channel(None)?;
@@ -30,79 +198,1298 @@ impl Server {
         // get a handle to the default direct exchange.
         let exchange = Exchange::direct(&channel);
 
-        // declare the queue with routing key that will send/receive RPC requests.
-        let queue = channel.queue_declare("rpc_queue", QueueDeclareOptions::default())?;
+        // declare the queues with routing keys that will send/receive RPC requests, one per zone
+        // of the facility when it is sharded.
+        let queues: Vec<(Option<String>, String)> =
+            if collision_monitor.config.zone_queues.is_empty() {
+                vec![(None, "rpc_queue".to_string())]
+            } else {
+                collision_monitor
+                    .config
+                    .zone_queues
+                    .iter()
+                    .map(|zone_queue| (Some(zone_queue.zone.clone()), zone_queue.queue_name()))
+                    .collect()
+            };
 
-        // start a consumer.
-        let consumer = queue.consume(ConsumerOptions::default())?;
+        // start a consumer on each of them.
+        let consumers = queues
+            .iter()
+            .map(|(_, queue_name)| {
+                channel
+                    .queue_declare(queue_name.as_str(), QueueDeclareOptions::default())?
+                    .consume(ConsumerOptions::default())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let receivers: Vec<_> = consumers
+            .iter()
+            .map(|consumer| consumer.receiver())
+            .collect();
+
+        // throttle the backlog queued while disconnected, if configured.
+        let mut slow_start = collision_monitor.config.enable_slow_start.then(|| {
+            SlowStart::new(
+                Duration::from_millis(collision_monitor.config.slow_start_ms),
+                collision_monitor.config.slow_start_rate,
+                Instant::now(),
+            )
+        });
+
+        while !shutdown.load(Ordering::SeqCst) {
+            let timeout =
+                Self::poll_timeout(&collision_monitor.config, pending.len(), round_started);
+            let (zone, delivery) = match Self::receive(&receivers, timeout) {
+                Ok((idx, ConsumerMessage::Delivery(delivery))) => {
+                    (queues[idx].0.clone(), Some(delivery))
+                }
+                Ok(other) => {
+                    log::info!("Consumer ended: {:?}", other);
+                    break;
+                }
+                Err(RecvTimeoutError::Timeout) => (None, None),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            // the delivery completing the round, acknowledged once the round has been handled.
+            let delivery = match delivery {
+                Some(delivery) => {
+                    if let Some(slow_start) = slow_start.as_mut() {
+                        std::thread::sleep(slow_start.delay(Instant::now()));
+                    }
 
-        for (_, message) in consumer.receiver().iter().enumerate() {
-            match message {
-                ConsumerMessage::Delivery(delivery) => {
                     let (reply_to, corr_id) = match (
                         delivery.properties.reply_to(),
                         delivery.properties.correlation_id(),
                     ) {
                         (Some(r), Some(c)) => (r.clone(), c.clone()),
                         _ => {
-                            consumer.ack(delivery)?;
+                            delivery.ack(&channel)?;
                             continue;
                         }
                     };
 
-                    let robot_state: Robot = serde_json::from_slice(&delivery.body)
-                        .expect("could not deserialize robot state");
+                    if Self::is_duplicate(recent_correlation_ids.as_mut(), &corr_id) {
+                        log::info!("Skipping state resent with correlation id {:?}", corr_id);
+                        delivery.ack(&channel)?;
+                        continue;
+                    }
+
+                    let mut robot_state =
+                        match Self::decode_state(&collision_monitor.config, &delivery.body) {
+                            Ok(robot_state) => robot_state,
+                            Err(e) => {
+                                log::warn!(
+                                    "Skipping malformed state with correlation id {:?}: {}",
+                                    corr_id,
+                                    e
+                                );
+                                delivery.ack(&channel)?;
+                                continue;
+                            }
+                        };
+                    robot_state.zone = zone;
+
+                    if let Err(e) = collision_monitor.check_client_version(&robot_state) {
+                        log::warn!("Rejecting state from {:?}: {}", robot_state.device_id, e);
+                        delivery.ack(&channel)?;
+                        continue;
+                    }
+
+                    log::info!(
+                        "Received state from {:?} running client version {:?}",
+                        robot_state.device_id,
+                        robot_state.client_version
+                    );
+
+                    if let Err(e) = staleness::touch(
+                        &db,
+                        &robot_state.device_id,
+                        chrono::Utc::now().timestamp_millis(),
+                    ) {
+                        log::warn!(
+                            "Could not record when {:?} was last seen: {:?}",
+                            robot_state.device_id,
+                            e
+                        );
+                    }
 
-                    robot_states.push(robot_state);
-                    reply_states.push(reply_to);
-                    correlation_ids.push(corr_id);
+                    robot_state.expected_interval_ms =
+                        Self::reporting_interval(&db, &pending, &robot_state);
+                    if let Some(interval_ms) = robot_state.expected_interval_ms {
+                        reporting_intervals.insert(robot_state.device_id.clone(), interval_ms);
+                        if let Some(peer_median_ms) = Self::interval_outlier(
+                            &reporting_intervals,
+                            &robot_state.device_id,
+                            interval_ms,
+                        ) {
+                            log::warn!(
+                                "{:?} reports every {}ms while its peers report every {}ms, rounds may not line up",
+                                robot_state.device_id,
+                                interval_ms,
+                                peer_median_ms
+                            );
+                        }
+                    }
+
+                    if collision_monitor.config.track_correlation_ids {
+                        if let Some(first_round) =
+                            correlation_tracker.observe(&corr_id, current_round)
+                        {
+                            log::warn!(
+                                "Correlation id {:?} from {:?} reused in round {} (first seen in round {})",
+                                corr_id,
+                                robot_state.device_id,
+                                current_round,
+                                first_round
+                            );
+                        }
+                    }
 
-                    // now trigger collision monitoring once all states are collected
-                    if let Ok(updated_states) =
-                        collision_monitor.trigger_collision_monitor(robot_states.clone())
+                    if collision_monitor.config.enable_ack_tracking {
+                        acks.write()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .observe(&robot_state);
+                    }
+
+                    fleet_freeze
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .restore_state(&mut robot_state);
+
+                    if let Some(path) = reroutes
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&robot_state.device_id)
+                    {
+                        log::info!(
+                            "Applying new path of {} points to {:?}",
+                            path.len(),
+                            robot_state.device_id
+                        );
+                        robot_state.path = path;
+                        robot_state.path_index = 0;
+                    }
+
+                    if let Some(state) = commands
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&robot_state.device_id)
                     {
-                        for (idx, state) in updated_states.iter().enumerate() {
-                            log::info!(
-                                "Sending Updated State to ID {:?}: {:?}",
+                        log::info!(
+                            "Applying operator command {} to {:?}",
+                            state,
+                            robot_state.device_id
+                        );
+                        robot_state.state = state;
+                    }
+
+                    if let Some(filter) = position_filter.as_mut() {
+                        filter.apply(&mut robot_state);
+                    }
+
+                    if pending.is_empty() {
+                        round_started = Instant::now();
+                    }
+
+                    if let Some(wal) = wal.as_mut() {
+                        let entry = WalEntry {
+                            reply_to: reply_to.clone(),
+                            correlation_id: corr_id.clone(),
+                            state: robot_state.clone(),
+                        };
+                        if let Err(e) = wal.append(&entry) {
+                            log::warn!("Could not append state to WAL: {:?}", e);
+                        }
+                    }
+
+                    Self::buffer_state(&mut pending, robot_state, reply_to, corr_id);
+
+                    if collision_monitor.config.batch_window_ms.is_some() {
+                        // the state is held in the buffer until the batch window closes.
+                        delivery.ack(&channel)?;
+                        if !Self::batch_window_elapsed(
+                            &collision_monitor.config,
+                            pending.len(),
+                            round_started,
+                        ) {
+                            continue;
+                        }
+                        None
+                    } else {
+                        Some(delivery)
+                    }
+                }
+                None if Self::batch_window_elapsed(
+                    &collision_monitor.config,
+                    pending.len(),
+                    round_started,
+                ) =>
+                {
+                    None
+                }
+                None => continue,
+            };
+
+            let (robot_states, reply_states, correlation_ids) = Self::round_batch(&pending);
+
+            // robots gone silent take part in the round as obstacles, replies only go to live ones.
+            let live_robots = robot_states.len();
+
+            // now trigger collision monitoring once all states are collected
+            let frozen = fleet_freeze
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_frozen();
+            let round = Self::resolve_batch(
+                &collision_monitor,
+                &db,
+                &pending,
+                robot_states.clone(),
+                frozen,
+            );
+
+            let round = match round {
+                Err(MonitorError::IncompleteAgentSet { received, expected }) => {
+                    log::info!(
+                        "Waiting for agent records: {} of {} received",
+                        received,
+                        expected
+                    );
+                    Err(MonitorError::IncompleteAgentSet { received, expected })
+                }
+                Err(MonitorError::DeadlockUnresolvable { device_ids }) => {
+                    log::error!(
+                        "Deadlock between {:?} cannot be resolved, holding the fleet",
+                        device_ids
+                    );
+                    collision_monitor.hold_robot_states(robot_states.clone())
+                }
+                round => round,
+            };
+
+            if let Ok(mut updated_states) = round {
+                updated_states.truncate(live_robots);
+
+                if let Some(detector) = timeout_detector.as_mut().filter(|_| !frozen) {
+                    let stalled = detector.observe(&updated_states, Instant::now());
+                    if !stalled.is_empty() {
+                        log::warn!(
+                            "Deadlock timeout: {:?} paused for over {}ms, aborting",
+                            stalled
+                                .iter()
+                                .map(|&idx| &updated_states[idx].device_id)
+                                .collect::<Vec<_>>(),
+                            collision_monitor.config.deadlock_abort_ms
+                        );
+                        collision_monitor.abort_deadlock(&mut updated_states, &stalled);
+                    }
+                }
+
+                if collision_monitor.config.enable_ack_tracking {
+                    let mut acks = acks.write().unwrap_or_else(PoisonError::into_inner);
+                    let unacked = acks.unacked(collision_monitor.config.ack_deadline_rounds);
+                    if !unacked.is_empty() {
+                        collision_monitor.escalate_unacked(&mut updated_states, &unacked);
+                    }
+                    for state in updated_states.iter_mut() {
+                        state.round = Some(current_round);
+                        acks.sent(&state.device_id, current_round);
+                    }
+                }
+
+                if Self::apply_halt(&halt, &mut updated_states) {
+                    log::warn!("Fleet halted, pausing all {} robots", updated_states.len());
+                }
+
+                let suggested_publish_interval_ms = Self::suggested_publish_interval(
+                    &collision_monitor.config,
+                    round_started.elapsed(),
+                );
+
+                // ticks count resolved batches from 1, leaving 0 to states never resolved.
+                let tick = current_round + 1;
+                for (idx, state) in updated_states.iter_mut().enumerate() {
+                    state.suggested_publish_interval_ms = suggested_publish_interval_ms;
+                    state.tick = tick;
+
+                    if collision_monitor.config.dry_run {
+                        log::info!(
+                            "Dry run: would send updated state to ID {:?}: {:?}",
+                            state.device_id,
+                            state
+                        );
+                        continue;
+                    }
+
+                    log::info!(
+                        "Sending Updated State to ID {:?}: {:?}",
+                        state.device_id,
+                        state
+                    );
+                    // states go out in the wire format and units but are always stored as JSON, in
+                    // meters.
+                    let encoded = Self::encode_reply(&collision_monitor.config, state);
+                    let (payload, body) = match encoded {
+                        Ok(encoded) => encoded,
+                        Err(e) => {
+                            log::warn!(
+                                "Could not serialize state of {:?}, skipping it: {}",
                                 state.device_id,
-                                state
+                                e
                             );
-                            // if updated state found, publish it to it own queue.
-                            exchange
-                                .publish(Publish::with_properties(
-                                    serde_json::to_string(&state)
-                                        .expect("Could not serialize")
-                                        .as_bytes(),
-                                    reply_states[idx].clone(),
-                                    AmqpProperties::default()
-                                        .with_correlation_id(correlation_ids[idx].clone()),
-                                ))
-                                .expect("Failed to publish message");
-
-                            db.insert(
-                                &state.device_id,
-                                serde_json::to_string(&state)
-                                    .expect("Could not serialize")
-                                    .as_bytes()
-                                    .to_vec(),
-                            )
-                            .expect("Failed to insert record");
+                            continue;
                         }
+                    };
 
-                        robot_states.clear();
-                        correlation_ids.clear();
-                        reply_states.clear();
+                    // if updated state found, publish it to it own queue.
+                    exchange.publish(Publish::with_properties(
+                        &payload,
+                        reply_states[idx].clone(),
+                        AmqpProperties::default().with_correlation_id(correlation_ids[idx].clone()),
+                    ))?;
+
+                    if let Err(e) = db.insert(&state.device_id, body.as_bytes()) {
+                        log::warn!("Could not store state of {:?}: {:?}", state.device_id, e);
                     }
 
-                    consumer.ack(delivery)?;
+                    // a send only fails while nobody is subscribed.
+                    let _ = events.send(state.clone());
+
+                    if collision_monitor.config.record_history {
+                        if let Err(e) =
+                            history::record(&db, state, collision_monitor.config.history_limit)
+                        {
+                            log::warn!(
+                                "Could not record history of {:?}: {:?}",
+                                state.device_id,
+                                e
+                            );
+                        }
+                    }
                 }
-                other => {
-                    log::info!("Consumer ended: {:?}", other);
-                    break;
+
+                metrics.observe_round(&updated_states);
+                Self::prune_reporting_intervals(
+                    &db,
+                    &mut reporting_intervals,
+                    collision_monitor.config.stale_after_ms,
+                );
+
+                let conflicting_pairs = collision_monitor.conflicting_pairs(&robot_states);
+                for (device_a, device_b, separation) in
+                    collision_monitor.near_misses(&updated_states)
+                {
+                    log::warn!(
+                        "Near miss between {:?} and {:?}, {:.3} apart",
+                        device_a,
+                        device_b,
+                        separation
+                    );
+                }
+
+                #[cfg(feature = "sqlite")]
+                if let Some(sink) = &incident_sink {
+                    for incident in Incident::from_round(
+                        chrono::Utc::now().timestamp_millis(),
+                        &robot_states,
+                        &updated_states,
+                        &conflicting_pairs,
+                    ) {
+                        if let Err(e) = sink.record(&incident) {
+                            log::warn!("Could not export incident {:?}: {:?}", incident, e);
+                        }
+                    }
+                }
+
+                #[cfg(feature = "kafka")]
+                if let Some(sink) = &decision_sink {
+                    sink.publish(DecisionMessage::from_round(
+                        current_round,
+                        chrono::Utc::now().timestamp_millis(),
+                        &updated_states,
+                        &conflicting_pairs,
+                    ));
+                }
+
+                if collision_monitor.config.enable_dashboard {
+                    dashboard
+                        .write()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .record_round(
+                            round_started.elapsed(),
+                            &conflicting_pairs,
+                            &updated_states,
+                            collision_monitor.config.dashboard_window,
+                        );
+                }
+
+                if collision_monitor.config.enable_heatmap {
+                    heatmap
+                        .write()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .record_round(
+                            &robot_states,
+                            &conflicting_pairs,
+                            collision_monitor.config.heatmap_cell_size,
+                            collision_monitor.config.heatmap_decay,
+                        );
+                }
+
+                if collision_monitor.config.enable_utilization_stats {
+                    utilization
+                        .write()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .record_round(&updated_states, collision_monitor.config.utilization_window);
+                }
+
+                if let Some(wal) = wal.as_mut() {
+                    if let Err(e) = wal.clear() {
+                        log::warn!("Could not clear WAL: {:?}", e);
+                    }
+                }
+
+                latest_tick.store(tick, Ordering::SeqCst);
+                current_round += 1;
+                pending.clear();
+            }
+
+            if let Some(delivery) = delivery {
+                delivery.ack(&channel)?;
+            }
+        }
+
+        if shutdown.load(Ordering::SeqCst) {
+            // hand the deliveries received but not yet processed back to the hub.
+            for consumer in &consumers {
+                for message in consumer.receiver().try_iter() {
+                    if let ConsumerMessage::Delivery(delivery) = message {
+                        consumer.nack(delivery, true)?;
+                    }
+                }
+            }
+            log::info!("Shutting down, closing the connection to the hub");
+        }
+
+        Ok(connection.close()?)
+    }
+
+    /// `buffer_state` adds a robot state to the round in flight, keyed by device id, so that a state
+    /// resent before the round completes replaces the earlier one instead of counting as another
+    /// agent. It returns whether an earlier state was replaced.
+    fn buffer_state(
+        pending: &mut HashMap<String, (Robot, String, String)>,
+        robot_state: Robot,
+        reply_to: String,
+        correlation_id: String,
+    ) -> bool {
+        let device_id = robot_state.device_id.clone();
+        let replaced = pending
+            .insert(device_id.clone(), (robot_state, reply_to, correlation_id))
+            .is_some();
+        if replaced {
+            log::warn!(
+                "{:?} resent its state within the round, replacing it",
+                device_id
+            );
+        }
+
+        replaced
+    }
+
+    /// `is_duplicate` checks whether a correlation id was seen recently, so that a state published
+    /// again under it is skipped instead of counting toward the round a second time. It is always
+    /// false while deduplication is disabled.
+    fn is_duplicate(
+        recent_correlation_ids: Option<&mut RecentCorrelationIds>,
+        correlation_id: &str,
+    ) -> bool {
+        recent_correlation_ids
+            .map(|recent| recent.observe(correlation_id))
+            .unwrap_or(false)
+    }
+
+    /// `round_batch` splits the buffered round into robot states, reply queues and correlation ids,
+    /// ordered by device id.
+    fn round_batch(
+        pending: &HashMap<String, (Robot, String, String)>,
+    ) -> (Vec<Robot>, Vec<String>, Vec<String>) {
+        let mut entries: Vec<_> = pending.iter().collect();
+        entries.sort_by_key(|(device_id, _)| *device_id);
+
+        let mut robot_states = Vec::with_capacity(entries.len());
+        let mut reply_states = Vec::with_capacity(entries.len());
+        let mut correlation_ids = Vec::with_capacity(entries.len());
+        for (_, (robot_state, reply_to, correlation_id)) in entries {
+            robot_states.push(robot_state.clone());
+            reply_states.push(reply_to.clone());
+            correlation_ids.push(correlation_id.clone());
+        }
+
+        (robot_states, reply_states, correlation_ids)
+    }
+
+    /// `resolve_batch` resolves the states received in a round, or pauses them all while the fleet is
+    /// `frozen`. Only these live states count towards `num_agents`; stale robots are added as
+    /// obstacles once the agent set is complete.
+    fn resolve_batch(
+        collision_monitor: &CollisionMonitor,
+        db: &sled::Db,
+        pending: &HashMap<String, (Robot, String, String)>,
+        mut robot_states: Vec<Robot>,
+        frozen: bool,
+    ) -> Result<Vec<Robot>, MonitorError> {
+        collision_monitor.check_agent_set(&robot_states)?;
+
+        if let Some(stale_after_ms) = collision_monitor.config.stale_after_ms {
+            robot_states.extend(Self::stale_obstacles(db, pending, stale_after_ms));
+        }
+
+        if frozen {
+            Ok(CollisionMonitor::pause_robot_states(robot_states))
+        } else {
+            collision_monitor.resolve_robot_states(robot_states)
+        }
+    }
+
+    /// `stale_obstacles` returns the stored states of the stale robots missing from the round in
+    /// flight, stopped where they were last seen. Robots never heard from, such as seeded ones, have
+    /// no known position to hold and are left out.
+    fn stale_obstacles(
+        db: &sled::Db,
+        pending: &HashMap<String, (Robot, String, String)>,
+        stale_after_ms: u64,
+    ) -> Vec<Robot> {
+        let stale =
+            match staleness::stale(db, chrono::Utc::now().timestamp_millis(), stale_after_ms) {
+                Ok(stale) => stale,
+                Err(e) => {
+                    log::warn!("Could not read stale robots: {:?}", e);
+                    return Vec::new();
                 }
+            };
+
+        stale
+            .into_iter()
+            .filter(|(robot, last_seen_ms)| {
+                last_seen_ms.is_some() && !pending.contains_key(&robot.device_id)
+            })
+            .map(|(mut robot, _)| {
+                log::info!(
+                    "Holding stale {:?} in place as an obstacle",
+                    robot.device_id
+                );
+                robot.state = MotionState::Stopped;
+                robot
+            })
+            .collect()
+    }
+
+    /// `reporting_interval` derives how often a robot reports from the timestamp of its previous
+    /// state, buffered in the round in flight or else stored in the DB. A state that is not newer
+    /// than the previous one keeps the interval derived before.
+    fn reporting_interval(
+        db: &sled::Db,
+        pending: &HashMap<String, (Robot, String, String)>,
+        robot: &Robot,
+    ) -> Option<u64> {
+        let previous = match pending.get(&robot.device_id) {
+            Some((previous, _, _)) => Some(previous.clone()),
+            None => db
+                .get(&robot.device_id)
+                .ok()
+                .flatten()
+                .and_then(|record| serde_json::from_slice::<Robot>(&record).ok()),
+        }?;
+
+        if robot.timestamp > previous.timestamp {
+            Some((robot.timestamp - previous.timestamp) as u64)
+        } else {
+            previous.expected_interval_ms
+        }
+    }
+
+    /// `interval_outlier` compares a robot's reporting interval with the median interval of the
+    /// other robots in `reporting_intervals`, returning the median when they differ by more than
+    /// `INTERVAL_DEVIATION_FACTOR`.
+    fn interval_outlier(
+        reporting_intervals: &HashMap<String, u64>,
+        device_id: &str,
+        interval_ms: u64,
+    ) -> Option<u64> {
+        let mut peer_intervals: Vec<u64> = reporting_intervals
+            .iter()
+            .filter(|(peer_id, _)| peer_id.as_str() != device_id)
+            .map(|(_, &peer_interval_ms)| peer_interval_ms)
+            .collect();
+        if peer_intervals.is_empty() {
+            return None;
+        }
+
+        peer_intervals.sort_unstable();
+        let median_ms = peer_intervals[peer_intervals.len() / 2];
+        let ratio = interval_ms as f64 / median_ms.max(1) as f64;
+        if (1.0 / INTERVAL_DEVIATION_FACTOR..=INTERVAL_DEVIATION_FACTOR).contains(&ratio) {
+            None
+        } else {
+            Some(median_ms)
+        }
+    }
+
+    /// `prune_reporting_intervals` drops the intervals of robots evicted from the DB or gone stale,
+    /// so that robots no longer reporting do not skew the median their peers are compared with.
+    fn prune_reporting_intervals(
+        db: &sled::Db,
+        reporting_intervals: &mut HashMap<String, u64>,
+        stale_after_ms: Option<u64>,
+    ) {
+        let stale: HashSet<String> = match stale_after_ms.map(|stale_after_ms| {
+            staleness::stale(db, chrono::Utc::now().timestamp_millis(), stale_after_ms)
+        }) {
+            Some(Ok(stale)) => stale
+                .into_iter()
+                .map(|(robot, _)| robot.device_id)
+                .collect(),
+            Some(Err(e)) => {
+                log::warn!("Could not read stale robots: {:?}", e);
+                HashSet::new()
+            }
+            None => HashSet::new(),
+        };
+
+        reporting_intervals.retain(|device_id, _| {
+            !stale.contains(device_id) && db.contains_key(device_id).unwrap_or(true)
+        });
+    }
+
+    /// `batch_window_elapsed` checks whether the batch window of the round in flight has closed, so
+    /// that the states buffered so far make up the round. It is always false without a window.
+    fn batch_window_elapsed(
+        config: &CollisionMonitorConfig,
+        pending: usize,
+        round_started: Instant,
+    ) -> bool {
+        match config.batch_window_ms {
+            Some(window_ms) => {
+                pending > 0 && round_started.elapsed() >= Duration::from_millis(window_ms)
+            }
+            None => false,
+        }
+    }
+
+    /// `receive` waits up to `timeout` for a message on any of `receivers`, returning it along with
+    /// the index of the receiver it came from.
+    fn receive<T>(
+        receivers: &[&Receiver<T>],
+        timeout: Duration,
+    ) -> Result<(usize, T), RecvTimeoutError> {
+        let mut select = Select::new();
+        for receiver in receivers {
+            select.recv(receiver);
+        }
+
+        let operation = select
+            .select_timeout(timeout)
+            .map_err(|_| RecvTimeoutError::Timeout)?;
+        let idx = operation.index();
+        operation
+            .recv(receivers[idx])
+            .map(|message| (idx, message))
+            .map_err(|_| RecvTimeoutError::Disconnected)
+    }
+
+    /// `poll_timeout` returns how long the consumer loop waits for the next message: the shutdown
+    /// poll interval, cut short when the batch window of the round in flight closes sooner.
+    fn poll_timeout(
+        config: &CollisionMonitorConfig,
+        pending: usize,
+        round_started: Instant,
+    ) -> Duration {
+        match config.batch_window_ms {
+            Some(window_ms) if pending > 0 => Duration::from_millis(window_ms)
+                .saturating_sub(round_started.elapsed())
+                .min(SHUTDOWN_POLL_INTERVAL),
+            _ => SHUTDOWN_POLL_INTERVAL,
+        }
+    }
+
+    /// `apply_halt` overrides every resolved state with `Pause` while the fleet is halted. It
+    /// returns whether the fleet is halted.
+    fn apply_halt(halt: &AtomicBool, updated_states: &mut [Robot]) -> bool {
+        if !halt.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        for state in updated_states.iter_mut() {
+            state.state = MotionState::Pause;
+        }
+
+        true
+    }
+
+    /// `suggested_publish_interval` asks robots to publish less frequently when rounds take longer
+    /// than the configured backpressure latency, scaling the base interval by the overshoot.
+    fn suggested_publish_interval(
+        config: &CollisionMonitorConfig,
+        round_latency: Duration,
+    ) -> Option<u64> {
+        if !config.enable_backpressure {
+            return None;
+        }
+
+        let round_latency_ms = round_latency.as_secs_f64() * 1000.0;
+        if round_latency_ms <= config.backpressure_latency_ms {
+            return None;
+        }
+
+        let load_factor = round_latency_ms / config.backpressure_latency_ms;
+        let interval_ms = (config.base_publish_interval_ms as f64 * load_factor).ceil() as u64;
+
+        Some(interval_ms.min(config.max_publish_interval_ms))
+    }
+
+    /// `decode_state` decodes a state received from a robot in the configured wire format,
+    /// converting it from `units` to meters.
+    fn decode_state(config: &CollisionMonitorConfig, body: &[u8]) -> Result<Robot, String> {
+        let mut robot_state: Robot = config.wire_format.decode(body)?;
+        robot_state.scale_lengths(config.units.meters_per_unit());
+
+        Ok(robot_state)
+    }
+
+    /// `encode_reply` encodes the state sent back to a robot, in the configured wire format and
+    /// `units`, along with the JSON stored for it.
+    fn encode_reply(
+        config: &CollisionMonitorConfig,
+        state: &Robot,
+    ) -> Result<(Vec<u8>, String), String> {
+        let mut wire_state = state.clone();
+        wire_state.scale_lengths(1.0 / config.units.meters_per_unit());
+        let payload = wire_state
+            .to_value(config.compact_state_encoding)
+            .map_err(|e| e.to_string())
+            .and_then(|value| config.wire_format.encode(&value))?;
+        let body = state
+            .to_value(config.compact_state_encoding)
+            .map_err(|e| e.to_string())?
+            .to_string();
+
+        Ok((payload, body))
+    }
+
+    /// `seed_states` loads the configured seed states, checks them for collisions and saves them to DB.
+    pub(crate) fn seed_states(
+        config: &CollisionMonitorConfig,
+        db: &sled::Db,
+    ) -> Result<(), String> {
+        let seed_states_path = match &config.seed_states_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let contents = std::fs::read(seed_states_path)
+            .map_err(|e| format!("Failed to open seed states file: {:?}", e))?;
+        let mut seed_states: Vec<Robot> = serde_json::from_slice(&contents)
+            .map_err(|e| format!("Failed to deserialize seed states: {:?}", e))?;
+        for state in seed_states.iter_mut() {
+            state.scale_lengths(config.units.meters_per_unit());
+        }
+
+        let collision_monitor = CollisionMonitor::new(config.clone());
+        collision_monitor.check_obstacles(&seed_states)?;
+        collision_monitor.check_seed_states(&mut seed_states)?;
+
+        for state in &seed_states {
+            db.insert(
+                &state.device_id,
+                serde_json::to_string(&state)
+                    .expect("Could not serialize")
+                    .as_bytes()
+                    .to_vec(),
+            )
+            .expect("Failed to insert record");
+        }
+
+        log::info!("Seeded {} robot states", seed_states.len());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{test_config, ResolutionStrategy, Units};
+
+    #[test]
+    fn test_server_suggested_publish_interval() {
+        let config = CollisionMonitorConfig {
+            enable_backpressure: true,
+            backpressure_latency_ms: 50.0,
+            base_publish_interval_ms: 10,
+            max_publish_interval_ms: 100,
+            ..test_config()
+        };
+
+        // rounds within the latency budget leave robots at their own interval.
+        assert_eq!(
+            Server::suggested_publish_interval(&config, Duration::from_millis(20)),
+            None
+        );
+
+        // a round taking three times the budget triples the suggested interval.
+        assert_eq!(
+            Server::suggested_publish_interval(&config, Duration::from_millis(150)),
+            Some(30)
+        );
+
+        // the suggested interval never exceeds the configured maximum.
+        assert_eq!(
+            Server::suggested_publish_interval(&config, Duration::from_secs(10)),
+            Some(100)
+        );
+
+        let disabled_config = CollisionMonitorConfig {
+            enable_backpressure: false,
+            ..config
+        };
+        assert_eq!(
+            Server::suggested_publish_interval(&disabled_config, Duration::from_secs(10)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_server_duplicate_send_replaces_buffered_state() {
+        let collision_monitor = CollisionMonitor::new(test_config());
+        let robot = |device_id: &str, x: f64| Robot {
+            device_id: device_id.to_string(),
+            x,
+            ..Default::default()
+        };
+        let mut pending = HashMap::new();
+
+        assert!(!Server::buffer_state(
+            &mut pending,
+            robot("robot1", 0.0),
+            "reply1".to_string(),
+            "corr1".to_string(),
+        ));
+        assert!(Server::buffer_state(
+            &mut pending,
+            robot("robot1", 1.0),
+            "reply1".to_string(),
+            "corr2".to_string(),
+        ));
+
+        // the resend does not complete the round on its own.
+        let (robot_states, _, _) = Server::round_batch(&pending);
+        assert!(matches!(
+            collision_monitor.trigger_collision_monitor(robot_states),
+            Err(MonitorError::IncompleteAgentSet {
+                received: 1,
+                expected: 2
+            })
+        ));
+
+        Server::buffer_state(
+            &mut pending,
+            robot("robot0", 0.0),
+            "reply0".to_string(),
+            "corr0".to_string(),
+        );
+        let (robot_states, reply_states, correlation_ids) = Server::round_batch(&pending);
+        assert_eq!(
+            robot_states
+                .iter()
+                .map(|robot| (robot.device_id.as_str(), robot.x))
+                .collect::<Vec<_>>(),
+            vec![("robot0", 0.0), ("robot1", 1.0)]
+        );
+        assert_eq!(reply_states, vec!["reply0", "reply1"]);
+        assert_eq!(correlation_ids, vec!["corr0", "corr2"]);
+    }
+
+    #[test]
+    fn test_server_batch_window() {
+        let config = CollisionMonitorConfig {
+            batch_window_ms: Some(20),
+            num_agents: Some(3),
+            ..test_config()
+        };
+        let collision_monitor = CollisionMonitor::new(config.clone());
+        let mut pending = HashMap::new();
+        let round_started = Instant::now();
+
+        // an empty buffer never closes the window.
+        assert!(!Server::batch_window_elapsed(&config, 0, round_started));
+        assert_eq!(
+            Server::poll_timeout(&config, 0, round_started),
+            SHUTDOWN_POLL_INTERVAL
+        );
+
+        for (device_id, x) in [("robot0", 0.0), ("robot1", 10.0)] {
+            Server::buffer_state(
+                &mut pending,
+                Robot {
+                    device_id: device_id.to_string(),
+                    x,
+                    ..Default::default()
+                },
+                format!("reply_{}", device_id),
+                format!("corr_{}", device_id),
+            );
+        }
+        assert!(!Server::batch_window_elapsed(
+            &config,
+            pending.len(),
+            round_started
+        ));
+        assert!(
+            Server::poll_timeout(&config, pending.len(), round_started)
+                <= Duration::from_millis(20)
+        );
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(Server::batch_window_elapsed(
+            &config,
+            pending.len(),
+            round_started
+        ));
+        assert_eq!(
+            Server::poll_timeout(&config, pending.len(), round_started),
+            Duration::ZERO
+        );
+
+        // the robot not heard from within the window is left out of the round.
+        let (robot_states, _, _) = Server::round_batch(&pending);
+        let updated_states = collision_monitor
+            .trigger_collision_monitor(robot_states)
+            .unwrap();
+        assert_eq!(
+            updated_states
+                .iter()
+                .map(|robot| robot.device_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["robot0", "robot1"]
+        );
+
+        // without a window the loop waits for the full agent set.
+        let counted_config = CollisionMonitorConfig {
+            batch_window_ms: None,
+            ..config
+        };
+        assert!(!Server::batch_window_elapsed(
+            &counted_config,
+            pending.len(),
+            round_started
+        ));
+    }
+
+    #[test]
+    fn test_server_receives_from_every_zone_queue() {
+        let (first_sender, first_receiver) = crossbeam_channel::unbounded();
+        let (second_sender, second_receiver) = crossbeam_channel::unbounded();
+        let receivers = [&first_receiver, &second_receiver];
+
+        second_sender.send("zone-b").unwrap();
+        assert_eq!(
+            Server::receive(&receivers, Duration::from_millis(10)),
+            Ok((1, "zone-b"))
+        );
+        first_sender.send("zone-a").unwrap();
+        assert_eq!(
+            Server::receive(&receivers, Duration::from_millis(10)),
+            Ok((0, "zone-a"))
+        );
+
+        // nothing queued on any zone times out.
+        assert_eq!(
+            Server::receive(&receivers, Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_server_stale_robots_do_not_count_as_agents() {
+        let collision_monitor = CollisionMonitor::new(CollisionMonitorConfig {
+            num_agents: Some(2),
+            stale_after_ms: Some(1_000),
+            ..test_config()
+        });
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let robot = |device_id: &str, x: f64| Robot {
+            device_id: device_id.to_string(),
+            x,
+            ..Default::default()
+        };
+        let store = |robot: &Robot| {
+            db.insert(&robot.device_id, serde_json::to_vec(robot).unwrap())
+                .unwrap();
+        };
+
+        // a seeded robot that never reports and one that went silent a minute ago.
+        store(&robot("seeded", 20.0));
+        store(&robot("silent", 10.0));
+        let a_minute_ago = chrono::Utc::now().timestamp_millis() - 60_000;
+        staleness::touch(&db, "silent", a_minute_ago).unwrap();
+
+        let mut pending = HashMap::new();
+        Server::buffer_state(
+            &mut pending,
+            robot("robot0", 0.0),
+            "reply0".to_string(),
+            "corr0".to_string(),
+        );
+        let (robot_states, _, _) = Server::round_batch(&pending);
+        assert!(matches!(
+            Server::resolve_batch(&collision_monitor, &db, &pending, robot_states, false),
+            Err(MonitorError::IncompleteAgentSet {
+                received: 1,
+                expected: 2
+            })
+        ));
+
+        Server::buffer_state(
+            &mut pending,
+            robot("robot1", 5.0),
+            "reply1".to_string(),
+            "corr1".to_string(),
+        );
+        let (robot_states, _, _) = Server::round_batch(&pending);
+        let updated_states =
+            Server::resolve_batch(&collision_monitor, &db, &pending, robot_states, false).unwrap();
+        let mut device_ids: Vec<&str> = updated_states
+            .iter()
+            .map(|state| state.device_id.as_str())
+            .collect();
+        device_ids.sort();
+        assert_eq!(device_ids, ["robot0", "robot1", "silent"]);
+    }
+
+    #[test]
+    fn test_server_resolves_round_in_centimeters() {
+        // robot1 drives away from robot2, which has the lower priority and waits.
+        let states = |scale: f64| {
+            [("robot1", 0.0, -2.0, 10), ("robot2", 0.5, 2.5, 1)]
+                .iter()
+                .map(|&(device_id, from, to, priority)| {
+                    serde_json::to_vec(&Robot {
+                        x: from * scale,
+                        path: vec![
+                            Path {
+                                x: from * scale,
+                                y: 0.0,
+                                theta: 0.0,
+                            },
+                            Path {
+                                x: to * scale,
+                                y: 0.0,
+                                theta: 0.0,
+                            },
+                        ],
+                        device_id: device_id.to_string(),
+                        state: MotionState::Resume,
+                        priority,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        let round = |config: CollisionMonitorConfig, scale: f64| {
+            let collision_monitor = CollisionMonitor::new(config);
+            let db = sled::Config::new().temporary(true).open().unwrap();
+            let mut pending = HashMap::new();
+            for (idx, body) in states(scale).iter().enumerate() {
+                let robot_state = Server::decode_state(&collision_monitor.config, body).unwrap();
+                Server::buffer_state(
+                    &mut pending,
+                    robot_state,
+                    format!("reply{}", idx),
+                    format!("corr{}", idx),
+                );
             }
+            let (robot_states, _, _) = Server::round_batch(&pending);
+            Server::resolve_batch(&collision_monitor, &db, &pending, robot_states, false)
+                .unwrap()
+                .iter()
+                .map(|state| {
+                    let (payload, body) =
+                        Server::encode_reply(&collision_monitor.config, state).unwrap();
+                    let sent: Robot = serde_json::from_slice(&payload).unwrap();
+                    let stored: Robot = serde_json::from_str(&body).unwrap();
+                    (sent.device_id, sent.state, sent.x, stored.x)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let meters = CollisionMonitorConfig {
+            resolution_strategy: ResolutionStrategy::PauseLowerPriority,
+            ..test_config()
+        };
+        let mut centimeters = CollisionMonitorConfig {
+            width: 100.0,
+            height: 100.0,
+            units: Units::Centimeters,
+            ..meters.clone()
+        };
+        centimeters.normalize_units();
+
+        let expected = vec![
+            ("robot1".to_string(), MotionState::Resume, -2.0, -2.0),
+            ("robot2".to_string(), MotionState::Pause, 0.5, 0.5),
+        ];
+        assert_eq!(round(meters, 1.0), expected);
+        // robots report and are answered in centimeters, while the monitor stores meters.
+        assert_eq!(
+            round(centimeters, 100.0),
+            vec![
+                ("robot1".to_string(), MotionState::Resume, -200.0, -2.0),
+                ("robot2".to_string(), MotionState::Pause, 50.0, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_reporting_interval() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let mut pending = HashMap::new();
+        let robot = |device_id: &str, timestamp: i64| Robot {
+            device_id: device_id.to_string(),
+            timestamp,
+            ..Default::default()
+        };
+
+        // the first state of a robot has nothing to be compared with.
+        assert_eq!(
+            Server::reporting_interval(&db, &pending, &robot("robot0", 1_000)),
+            None
+        );
+
+        let first = robot("robot0", 1_000);
+        db.insert("robot0", serde_json::to_vec(&first).unwrap())
+            .unwrap();
+        let mut second = robot("robot0", 1_250);
+        second.expected_interval_ms = Server::reporting_interval(&db, &pending, &second);
+        assert_eq!(second.expected_interval_ms, Some(250));
+
+        // a state still buffered in the round is newer than the stored one.
+        Server::buffer_state(
+            &mut pending,
+            second,
+            "reply0".to_string(),
+            "corr0".to_string(),
+        );
+        assert_eq!(
+            Server::reporting_interval(&db, &pending, &robot("robot0", 1_350)),
+            Some(100)
+        );
+        // a state replayed out of order keeps the interval derived before.
+        assert_eq!(
+            Server::reporting_interval(&db, &pending, &robot("robot0", 1_250)),
+            Some(250)
+        );
+
+        // robots reporting far slower than their peers are flagged.
+        let reporting_intervals = HashMap::from([
+            ("robot0".to_string(), 250),
+            ("robot1".to_string(), 100),
+            ("robot2".to_string(), 120),
+        ]);
+        assert_eq!(
+            Server::interval_outlier(&reporting_intervals, "robot0", 150),
+            None
+        );
+        assert_eq!(
+            Server::interval_outlier(&reporting_intervals, "robot0", 500),
+            Some(120)
+        );
+
+        // robot1 was evicted and robot2 went silent a minute ago, so only robot0 is kept.
+        let mut reporting_intervals = reporting_intervals;
+        let now = chrono::Utc::now().timestamp_millis();
+        staleness::touch(&db, "robot0", now).unwrap();
+        db.insert("robot2", serde_json::to_vec(&robot("robot2", 0)).unwrap())
+            .unwrap();
+        staleness::touch(&db, "robot2", now - 60_000).unwrap();
+        Server::prune_reporting_intervals(&db, &mut reporting_intervals, Some(1_000));
+        assert_eq!(
+            reporting_intervals,
+            HashMap::from([("robot0".to_string(), 250)])
+        );
+    }
+
+    #[test]
+    fn test_server_skips_duplicate_correlation_id() {
+        let mut recent_correlation_ids = RecentCorrelationIds::new(16);
+        let mut pending = HashMap::new();
+
+        // robot0 retries its publish under the same correlation id after a client-side timeout.
+        for timestamp in [100, 200] {
+            if Server::is_duplicate(Some(&mut recent_correlation_ids), "corr0") {
+                continue;
+            }
+            Server::buffer_state(
+                &mut pending,
+                Robot {
+                    device_id: "robot0".to_string(),
+                    timestamp,
+                    ..Default::default()
+                },
+                "reply0".to_string(),
+                "corr0".to_string(),
+            );
         }
 
-        connection.close()
+        let (robot_states, _, _) = Server::round_batch(&pending);
+        assert_eq!(
+            robot_states
+                .iter()
+                .map(|robot| (robot.device_id.as_str(), robot.timestamp))
+                .collect::<Vec<_>>(),
+            vec![("robot0", 100)]
+        );
+
+        // without deduplication every delivery is processed.
+        assert!(!Server::is_duplicate(None, "corr0"));
+    }
+
+    #[test]
+    fn test_server_halt_pauses_every_state() {
+        let halt = AtomicBool::new(false);
+        let robots = || {
+            [
+                MotionState::Resume,
+                MotionState::Reroute,
+                MotionState::Pause,
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, state)| Robot {
+                device_id: format!("robot{}", idx),
+                state,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>()
+        };
+
+        let mut updated_states = robots();
+        assert!(!Server::apply_halt(&halt, &mut updated_states));
+        assert_eq!(updated_states[0].state, MotionState::Resume);
+
+        halt.store(true, Ordering::SeqCst);
+        let mut updated_states = robots();
+        assert!(Server::apply_halt(&halt, &mut updated_states));
+        assert!(updated_states
+            .iter()
+            .all(|robot| robot.state == MotionState::Pause));
+
+        // the halt is sticky until cleared.
+        let mut updated_states = robots();
+        assert!(Server::apply_halt(&halt, &mut updated_states));
+        halt.store(false, Ordering::SeqCst);
+        let mut updated_states = robots();
+        assert!(!Server::apply_halt(&halt, &mut updated_states));
+        assert_eq!(updated_states[1].state, MotionState::Reroute);
     }
 }
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+/// [SlowStart] caps the rate at which queued robot messages are processed for a short window
+/// after (re)connecting to the hub, so that a backlog built up during an outage drains gradually.
+#[derive(Debug)]
+pub(crate) struct SlowStart {
+    // how long after connecting the rate cap applies
+    window: Duration,
+    // minimum spacing between two processed messages while the cap applies
+    min_interval: Duration,
+    // when the connection was (re)established
+    connected_at: Instant,
+    // when the previous message was allowed through
+    last_processed: Option<Instant>,
+}
+
+impl SlowStart {
+    /// `new` starts a slow-start window at `connected_at` processing at most `max_rate` messages
+    /// per second.
+    pub(crate) fn new(window: Duration, max_rate: u32, connected_at: Instant) -> Self {
+        SlowStart {
+            window,
+            min_interval: Duration::from_secs(1) / max_rate.max(1),
+            connected_at,
+            last_processed: None,
+        }
+    }
+
+    /// `delay` returns how long to wait before processing a message arriving at `now`. It is zero
+    /// once the slow-start window has passed.
+    pub(crate) fn delay(&mut self, now: Instant) -> Duration {
+        if now.saturating_duration_since(self.connected_at) >= self.window {
+            return Duration::ZERO;
+        }
+
+        let earliest = match self.last_processed {
+            Some(last) => (last + self.min_interval).max(now),
+            None => now,
+        };
+        self.last_processed = Some(earliest);
+
+        earliest - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_start_throttles_after_reconnect() {
+        let reconnected_at = Instant::now();
+        let mut slow_start = SlowStart::new(Duration::from_secs(2), 10, reconnected_at);
+
+        // a backlog arriving right after the reconnect is spaced out to 10 messages per second.
+        assert_eq!(slow_start.delay(reconnected_at), Duration::ZERO);
+        assert_eq!(slow_start.delay(reconnected_at), Duration::from_millis(100));
+        assert_eq!(slow_start.delay(reconnected_at), Duration::from_millis(200));
+
+        // messages arriving slower than the cap are not held back.
+        let later = reconnected_at + Duration::from_secs(1);
+        assert_eq!(slow_start.delay(later), Duration::ZERO);
+
+        // once the window has passed, processing is back to full rate.
+        let after_window = reconnected_at + Duration::from_secs(2);
+        for _ in 0..5 {
+            assert_eq!(slow_start.delay(after_window), Duration::ZERO);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::collision_monitor::{Path, Robot};
+
+/// [PositionFilter] keeps an exponential moving average of the positions reported by each robot,
+/// so that localization noise does not show up as spurious conflicts.
+#[derive(Debug)]
+pub(crate) struct PositionFilter {
+    // weight given to the newest reading, in (0.0, 1.0]
+    alpha: f64,
+    // smoothed position per device id
+    positions: HashMap<String, Path>,
+}
+
+impl PositionFilter {
+    /// `new` creates a filter with smoothing factor `alpha`, clamped to (0.0, 1.0].
+    pub(crate) fn new(alpha: f64) -> Self {
+        PositionFilter {
+            alpha: alpha.clamp(f64::EPSILON, 1.0),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// `apply` folds the reported position of `robot` into its moving average and stores the
+    /// result in `smoothed_position`. The reported x, y and theta are left untouched.
+    pub(crate) fn apply(&mut self, robot: &mut Robot) {
+        let alpha = self.alpha;
+        let smoothed = self
+            .positions
+            .entry(robot.device_id.clone())
+            .and_modify(|previous| {
+                previous.x += alpha * (robot.x - previous.x);
+                previous.y += alpha * (robot.y - previous.y);
+                // average headings on the unit circle so that they wrap around correctly
+                let sin = (1.0 - alpha) * previous.theta.sin() + alpha * robot.theta.sin();
+                let cos = (1.0 - alpha) * previous.theta.cos() + alpha * robot.theta.cos();
+                previous.theta = sin.atan2(cos);
+            })
+            .or_insert(Path {
+                x: robot.x,
+                y: robot.y,
+                theta: robot.theta,
+            });
+
+        robot.smoothed_position = Some(smoothed.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision_monitor::CollisionMonitor;
+    use crate::config::test_config;
+
+    fn robot(device_id: &str, x: f64) -> Robot {
+        Robot {
+            device_id: device_id.to_string(),
+            x,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_position_filter_smooths_noise_and_reduces_conflicts() {
+        let monitor = CollisionMonitor::new(test_config());
+        let mut filter = PositionFilter::new(0.2);
+        let neighbour = [robot("neighbour", 1.2)];
+        // a stationary robot whose localization jitters by +/- 0.3 around x = 0.0
+        let noise = [-0.3, 0.3, -0.3, 0.3, -0.3, 0.3, -0.3, 0.3, -0.3, 0.3];
+
+        let (mut raw_conflicts, mut smoothed_conflicts) = (0, 0);
+        let (mut raw_jump, mut smoothed_jump) = (0.0_f64, 0.0_f64);
+        let (mut last_raw, mut last_smoothed) = (noise[0], noise[0]);
+        for reading in noise {
+            let mut noisy = robot("noisy", reading);
+            if !monitor.conflicts_with(&noisy, &neighbour).is_empty() {
+                raw_conflicts += 1;
+            }
+
+            filter.apply(&mut noisy);
+            if !monitor.conflicts_with(&noisy, &neighbour).is_empty() {
+                smoothed_conflicts += 1;
+            }
+
+            let smoothed_x = noisy.smoothed_position.as_ref().unwrap().x;
+            raw_jump = raw_jump.max((noisy.x - last_raw).abs());
+            smoothed_jump = smoothed_jump.max((smoothed_x - last_smoothed).abs());
+            last_raw = noisy.x;
+            last_smoothed = smoothed_x;
+        }
+
+        assert_eq!(raw_conflicts, 5);
+        assert_eq!(smoothed_conflicts, 0);
+        assert!(smoothed_jump < raw_jump / 4.0);
+    }
+
+    #[test]
+    fn test_position_filter_tracks_real_movement() {
+        let mut filter = PositionFilter::new(0.5);
+
+        let mut moving = robot("moving", 0.0);
+        for _ in 0..20 {
+            moving.x = 10.0;
+            filter.apply(&mut moving);
+        }
+
+        let smoothed = moving.smoothed_position.unwrap();
+        assert!((smoothed.x - 10.0).abs() < 1e-3);
+        assert_eq!(moving.x, 10.0);
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::{HashMap, HashSet};
+
+/// [BoundingBox] defines an axis-aligned box as (min_x, min_y, max_x, max_y).
+pub(crate) type BoundingBox = (f64, f64, f64, f64);
+
+/// [SpatialGrid] buckets robots into uniform square cells by the bounding box of the space they
+/// may occupy, so that only robots sharing a cell need a collision check.
+#[derive(Debug)]
+pub(crate) struct SpatialGrid {
+    // side of a cell
+    cell_size: f64,
+    // robot indices per cell
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    /// `new` buckets the robot indices by their bounding boxes into cells of side `cell_size`.
+    pub(crate) fn new(boxes: &[(usize, BoundingBox)], cell_size: f64) -> Self {
+        let mut grid = SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+        };
+
+        for &(idx, (min_x, min_y, max_x, max_y)) in boxes {
+            let (min_column, min_row) = grid.cell(min_x, min_y);
+            let (max_column, max_row) = grid.cell(max_x, max_y);
+            for column in min_column..=max_column {
+                for row in min_row..=max_row {
+                    grid.cells.entry((column, row)).or_default().push(idx);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// `cell` returns the (column, row) of the cell holding the point (x, y).
+    fn cell(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            (x / self.cell_size).floor() as i64,
+            (y / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// `candidate_pairs` returns every pair of robot indices sharing at least one cell, ordered
+    /// with the lower index first and sorted.
+    pub(crate) fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+        for indices in self.cells.values() {
+            for (position, &idx) in indices.iter().enumerate() {
+                for &jdx in &indices[position + 1..] {
+                    pairs.insert((idx.min(jdx), idx.max(jdx)));
+                }
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spatial_grid_candidate_pairs() {
+        let grid = SpatialGrid::new(
+            &[
+                (0, (0.0, 0.0, 0.5, 0.5)),
+                (1, (0.9, 0.9, 1.1, 1.1)),
+                (2, (5.0, 5.0, 5.5, 5.5)),
+                // a box spanning several cells meets the robots in each of them.
+                (3, (-0.5, 0.2, 5.2, 0.4)),
+            ],
+            1.0,
+        );
+
+        assert_eq!(grid.candidate_pairs(), vec![(0, 1), (0, 3), (1, 3)]);
+    }
+}
@@ -0,0 +1,61 @@
+use serde_derive::Serialize;
+
+use crate::collision_monitor::Robot;
+
+/// `LAST_SEEN_TREE` names the sled tree holding when each device's state was last received.
+const LAST_SEEN_TREE: &str = "last_seen";
+
+/// [StaleRobot] defines a stored robot the monitor has not heard from within `stale_after_ms`.
+#[derive(Debug, Serialize)]
+pub(crate) struct StaleRobot {
+    pub device_id: String,
+    // unix time in ms the last state was received, none if never received by the monitor
+    pub last_seen_ms: Option<i64>,
+}
+
+/// `touch` records that a state from a device was received at `now_ms`.
+pub(crate) fn touch(db: &sled::Db, device_id: &str, now_ms: i64) -> sled::Result<()> {
+    db.open_tree(LAST_SEEN_TREE)?
+        .insert(device_id, &now_ms.to_be_bytes())?;
+
+    Ok(())
+}
+
+/// `forget` drops the last-seen time of a device.
+pub(crate) fn forget(db: &sled::Db, device_id: &str) -> sled::Result<()> {
+    db.open_tree(LAST_SEEN_TREE)?.remove(device_id)?;
+
+    Ok(())
+}
+
+/// `stale` returns every robot stored in the DB whose last state was received more than
+/// `stale_after_ms` before `now_ms`, along with when it was last seen. Stored robots the monitor
+/// never received a state from, such as seeded ones, are stale too.
+pub(crate) fn stale(
+    db: &sled::Db,
+    now_ms: i64,
+    stale_after_ms: u64,
+) -> sled::Result<Vec<(Robot, Option<i64>)>> {
+    let last_seen = db.open_tree(LAST_SEEN_TREE)?;
+
+    let mut stale = Vec::new();
+    for value in db.iter().values() {
+        let robot = match serde_json::from_slice::<Robot>(&value?) {
+            Ok(robot) => robot,
+            Err(_) => continue,
+        };
+        let last_seen_ms = last_seen
+            .get(&robot.device_id)?
+            .and_then(|value| value.as_ref().try_into().ok())
+            .map(i64::from_be_bytes);
+
+        let fresh = last_seen_ms
+            .map(|last_seen_ms| now_ms.saturating_sub(last_seen_ms) <= stale_after_ms as i64)
+            .unwrap_or(false);
+        if !fresh {
+            stale.push((robot, last_seen_ms));
+        }
+    }
+
+    Ok(stale)
+}
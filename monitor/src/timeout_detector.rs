@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [TimeoutDetector] declares a deadlock once a set of robots has been paused in place for longer
+/// than `deadlock_abort_ms`, regardless of what the conflicts seen in a single round suggest.
+#[derive(Debug)]
+pub(crate) struct TimeoutDetector {
+    // how long robots may stay paused in place before the deadlock is aborted
+    abort_after: Duration,
+    // when each paused device was first seen paused at its current position
+    paused_since: HashMap<String, (Instant, f64, f64)>,
+}
+
+impl TimeoutDetector {
+    /// `new` creates a detector aborting deadlocks after `abort_after`.
+    pub(crate) fn new(abort_after: Duration) -> Self {
+        TimeoutDetector {
+            abort_after,
+            paused_since: HashMap::new(),
+        }
+    }
+
+    /// `observe` records the states robots ended a round in at `now`. It returns the indices of the
+    /// robots that have all been paused in place past the timeout, when there are at least two of
+    /// them, and restarts their timers.
+    pub(crate) fn observe(&mut self, robots: &[Robot], now: Instant) -> Vec<usize> {
+        let mut stalled = Vec::new();
+
+        for (idx, robot) in robots.iter().enumerate() {
+            if robot.state != MotionState::Pause {
+                self.paused_since.remove(&robot.device_id);
+                continue;
+            }
+
+            let (since, x, y) = self
+                .paused_since
+                .entry(robot.device_id.clone())
+                .or_insert((now, robot.x, robot.y));
+
+            // a robot that moved since it was paused has made progress.
+            if *x != robot.x || *y != robot.y {
+                *since = now;
+                *x = robot.x;
+                *y = robot.y;
+            }
+
+            if now.saturating_duration_since(*since) >= self.abort_after {
+                stalled.push(idx);
+            }
+        }
+
+        // a single paused robot is simply waiting for someone else to move.
+        if stalled.len() < 2 {
+            return Vec::new();
+        }
+
+        for &idx in &stalled {
+            self.paused_since.remove(&robots[idx].device_id);
+        }
+
+        stalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision_monitor::{CollisionMonitor, Path};
+    use crate::config::test_config;
+
+    fn paused_robot(device_id: &str, x: f64) -> Robot {
+        Robot {
+            device_id: device_id.to_string(),
+            x,
+            state: MotionState::Pause,
+            path: vec![
+                Path {
+                    x,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+                Path {
+                    x: x + 5.0,
+                    y: 0.0,
+                    theta: 0.0,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_timeout_detector_aborts_robots_paused_past_timeout() {
+        let monitor = CollisionMonitor::new(test_config());
+        let mut detector = TimeoutDetector::new(Duration::from_millis(500));
+        let mut robots = vec![paused_robot("robot1", 0.0), paused_robot("robot2", 0.5)];
+        let start = Instant::now();
+
+        assert!(detector.observe(&robots, start).is_empty());
+        assert!(detector
+            .observe(&robots, start + Duration::from_millis(499))
+            .is_empty());
+
+        let stalled = detector.observe(&robots, start + Duration::from_millis(500));
+        assert_eq!(stalled, vec![0, 1]);
+
+        monitor.abort_deadlock(&mut robots, &stalled);
+        assert_eq!(robots[0].state, MotionState::Pause);
+        assert_eq!(robots[1].state, MotionState::Resume);
+
+        // timers restart once the deadlock has been aborted.
+        assert!(detector
+            .observe(&robots, start + Duration::from_millis(600))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_timeout_detector_ignores_moving_robots() {
+        let mut detector = TimeoutDetector::new(Duration::from_millis(500));
+        let mut robots = vec![paused_robot("robot1", 0.0), paused_robot("robot2", 0.5)];
+        let start = Instant::now();
+
+        assert!(detector.observe(&robots, start).is_empty());
+
+        robots[1].x = 1.0;
+        assert!(detector
+            .observe(&robots, start + Duration::from_millis(500))
+            .is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::collision_monitor::{MotionState, Robot};
+
+/// [Utilization] keeps a rolling window of the motion states each device ended its recent rounds in.
+#[derive(Debug, Default)]
+pub(crate) struct Utilization {
+    // motion states of the most recent rounds per device, oldest first
+    recent_states: HashMap<String, VecDeque<MotionState>>,
+}
+
+impl Utilization {
+    /// `record_round` stores the motion state every device ended a round in.
+    pub(crate) fn record_round(&mut self, updated_states: &[Robot], window: usize) {
+        for robot in updated_states {
+            let states = self
+                .recent_states
+                .entry(robot.device_id.clone())
+                .or_default();
+
+            states.push_back(robot.state);
+            while states.len() > window {
+                states.pop_front();
+            }
+        }
+    }
+
+    /// `report` computes the fraction of recent rounds spent in each motion state, per device and
+    /// across the fleet.
+    pub(crate) fn report(&self) -> UtilizationReport {
+        let mut fleet_counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut fleet_rounds = 0;
+        let mut devices = BTreeMap::new();
+
+        for (device_id, states) in &self.recent_states {
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for state in states {
+                *counts.entry(state.to_string()).or_insert(0) += 1;
+                *fleet_counts.entry(state.to_string()).or_insert(0) += 1;
+            }
+            fleet_rounds += states.len();
+
+            devices.insert(device_id.clone(), Self::fractions(&counts, states.len()));
+        }
+
+        UtilizationReport {
+            fleet: Self::fractions(&fleet_counts, fleet_rounds),
+            devices,
+        }
+    }
+
+    /// `fractions` turns per-state round counts into fractions of `total` rounds.
+    fn fractions(counts: &BTreeMap<String, usize>, total: usize) -> BTreeMap<String, f64> {
+        counts
+            .iter()
+            .map(|(state, count)| (state.clone(), *count as f64 / total as f64))
+            .collect()
+    }
+}
+
+/// [UtilizationReport] defines the JSON body returned by the `/stats/utilization` endpoint.
+#[derive(Debug, Serialize)]
+pub(crate) struct UtilizationReport {
+    /// fraction of recent rounds spent in each motion state across the fleet
+    pub fleet: BTreeMap<String, f64>,
+    /// fraction of recent rounds spent in each motion state per device
+    pub devices: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utilization_known_sequence() {
+        let robot = |device_id: &str, state: MotionState| Robot {
+            device_id: device_id.to_string(),
+            state,
+            ..Default::default()
+        };
+
+        let mut utilization = Utilization::default();
+        // robot1 resumes, pauses, resumes, resumes; only the last 3 rounds are kept.
+        for (robot1_state, robot2_state) in [
+            (MotionState::Resume, MotionState::Pause),
+            (MotionState::Pause, MotionState::Pause),
+            (MotionState::Resume, MotionState::Pause),
+            (MotionState::Resume, MotionState::Resume),
+        ] {
+            utilization.record_round(
+                &[robot("robot1", robot1_state), robot("robot2", robot2_state)],
+                3,
+            );
+        }
+
+        let report = utilization.report();
+
+        assert_eq!(report.devices["robot1"]["resume"], 2.0 / 3.0);
+        assert_eq!(report.devices["robot1"]["pause"], 1.0 / 3.0);
+        assert_eq!(report.devices["robot2"]["resume"], 1.0 / 3.0);
+        assert_eq!(report.devices["robot2"]["pause"], 2.0 / 3.0);
+        assert_eq!(report.fleet["resume"], 0.5);
+        assert_eq!(report.fleet["pause"], 0.5);
+    }
+}
@@ -0,0 +1,116 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+
+use crate::collision_monitor::Robot;
+
+/// [WalEntry] defines a robot state received in the current round along with where to reply to it.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct WalEntry {
+    // queue the updated state is published to
+    pub reply_to: String,
+    // correlation id the updated state is published with
+    pub correlation_id: String,
+    // robot state received from the robot
+    pub state: Robot,
+}
+
+/// [RoundWal] defines a write-ahead log of the states received in the current round, so that a
+/// round in flight when the monitor went down can be completed after a restart.
+#[derive(Debug)]
+pub(crate) struct RoundWal {
+    file: File,
+}
+
+impl RoundWal {
+    /// `open` opens the log at `path`, creating it if needed, and returns the entries of the round
+    /// left in flight. A trailing entry cut short by a crash is skipped.
+    pub(crate) fn open(path: &str) -> io::Result<(Self, Vec<WalEntry>)> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(&file).lines() {
+            match serde_json::from_str::<WalEntry>(&line?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!("Skipping unreadable WAL entry in {}: {:?}", path, e),
+            }
+        }
+
+        Ok((RoundWal { file }, entries))
+    }
+
+    /// `append` durably records a state received in the current round.
+    pub(crate) fn append(&mut self, entry: &WalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry).expect("Could not serialize");
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+
+    /// `clear` empties the log once the round has completed.
+    pub(crate) fn clear(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(device_id: &str) -> WalEntry {
+        WalEntry {
+            reply_to: format!("{}-queue", device_id),
+            correlation_id: format!("{}-corr", device_id),
+            state: Robot {
+                device_id: device_id.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_round_wal_recovers_partial_round() {
+        let path = std::env::temp_dir().join(format!("{}_round.wal", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let (mut wal, entries) = RoundWal::open(path).unwrap();
+        assert!(entries.is_empty());
+        wal.append(&entry("robot1")).unwrap();
+        wal.append(&entry("robot2")).unwrap();
+        // the monitor crashes while writing the third state.
+        wal.file.write_all(b"{\"reply_to\":\"robot3-qu").unwrap();
+        drop(wal);
+
+        let (mut wal, entries) = RoundWal::open(path).unwrap();
+        let recovered: Vec<(&str, &str, &str)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.state.device_id.as_str(),
+                    entry.reply_to.as_str(),
+                    entry.correlation_id.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            recovered,
+            vec![
+                ("robot1", "robot1-queue", "robot1-corr"),
+                ("robot2", "robot2-queue", "robot2-corr")
+            ]
+        );
+
+        // once the round completes, nothing is left to recover.
+        wal.clear().unwrap();
+        drop(wal);
+        let (_, entries) = RoundWal::open(path).unwrap();
+        assert!(entries.is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,22 +1,75 @@
 use amiquip::{
     AmqpProperties, Channel, Consumer, ConsumerMessage, ConsumerOptions, Exchange, Publish, Queue,
-    QueueDeclareOptions, Result,
+    QueueDeclareOptions,
 };
+use crossbeam_channel::Receiver;
+use serde::{de, Deserialize as _, Deserializer};
 use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+use crate::config::WireFormat;
+
+/// [ClientError] defines why publishing a state to the hub failed.
+#[derive(Debug)]
+pub enum ClientError {
+    /// the connection to the hub failed
+    Amqp(amiquip::Error),
+    /// the hub did not reply within the configured timeout
+    Timeout,
+    /// the TLS connection to the hub could not be set up
+    Tls(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Amqp(e) => write!(f, "Connection to hub failed: {}", e),
+            ClientError::Timeout => write!(f, "Hub did not reply in time"),
+            ClientError::Tls(e) => write!(f, "TLS setup failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<amiquip::Error> for ClientError {
+    fn from(e: amiquip::Error) -> Self {
+        ClientError::Amqp(e)
+    }
+}
+
 /// [RobotRpcClient] defines current RPC client for sending/receiving to/from the server.
 pub struct RobotRpcClient<'a> {
     queue: Queue<'a>,
     consumer: Consumer<'a>,
     exchange: Exchange<'a>,
+    wire_format: WireFormat,
+    rpc_queue: String,
 }
 
 impl<'a> RobotRpcClient<'a> {
-    // `new` creates a new client
-    pub fn new(channel: &Channel) -> Result<RobotRpcClient> {
-        let exchange = Exchange::direct(&channel);
+    // `new` creates a new client exchanging states with the hub in `wire_format`, publishing them
+    // to `rpc_queue`
+    pub fn new(
+        channel: &Channel,
+        wire_format: WireFormat,
+        rpc_queue: String,
+    ) -> Result<RobotRpcClient<'_>, ClientError> {
+        let (queue, consumer) = Self::declare_reply_queue(channel)?;
 
+        Ok(RobotRpcClient {
+            exchange: Exchange::direct(channel),
+            queue,
+            consumer,
+            wire_format,
+            rpc_queue,
+        })
+    }
+
+    // `declare_reply_queue` declares the exclusive queue the hub replies on and starts consuming it.
+    fn declare_reply_queue(channel: &Channel) -> Result<(Queue<'_>, Consumer<'_>), ClientError> {
         let queue = channel.queue_declare(
             "",
             QueueDeclareOptions {
@@ -29,50 +82,78 @@ impl<'a> RobotRpcClient<'a> {
             ..ConsumerOptions::default()
         })?;
 
-        Ok(RobotRpcClient {
-            exchange,
-            queue,
-            consumer,
-        })
+        Ok((queue, consumer))
     }
 
     // `publish_current_state` publishes its current state to the server
-    // after reply is received it updates its current state on k-v store
-    pub fn publish_current_state(&self, robot_state: &Robot) -> Result<Robot> {
+    // after reply is received it updates its current state on k-v store.
+    // It fails with `ClientError::Timeout` if no reply arrives within `timeout`.
+    pub fn publish_current_state(
+        &self,
+        robot_state: &Robot,
+        timeout: Duration,
+    ) -> Result<Robot, ClientError> {
         let correlation_id = format!("{}", Uuid::new_v4());
 
         self.exchange.publish(Publish::with_properties(
-            serde_json::to_string(&robot_state)
-                .expect("Could not deserialize")
-                .as_bytes(),
-            "rpc_queue",
+            &self
+                .wire_format
+                .encode(robot_state)
+                .expect("Could not serialize"),
+            self.rpc_queue.as_str(),
             AmqpProperties::default()
                 .with_reply_to(self.queue.name().to_string())
                 .with_correlation_id(correlation_id.to_string()),
         ))?;
 
-        for message in self.consumer.receiver().iter() {
-            match message {
-                ConsumerMessage::Delivery(delivery) => {
-                    if delivery.properties.correlation_id().as_ref() == Some(&correlation_id) {
-                        let updated_robot_state: Robot =
-                            serde_json::from_slice(&delivery.body).expect("Could not deserialize");
-
-                        if updated_robot_state.device_id == robot_state.device_id {
-                            log::info!("Received data from Hub {:?}", updated_robot_state);
-                            return Ok(updated_robot_state);
-                        } else {
-                            continue;
-                        }
+        let reply = await_reply(
+            self.consumer.receiver(),
+            &correlation_id,
+            &robot_state.device_id,
+            self.wire_format,
+            timeout,
+        )?;
+
+        Ok(reply.unwrap_or_else(|| robot_state.clone()))
+    }
+}
+
+/// `await_reply` waits up to `timeout` for the hub's reply carrying `correlation_id`, decoded from
+/// `wire_format`. It returns `None` if the consumer ends before a reply arrives.
+fn await_reply(
+    receiver: &Receiver<ConsumerMessage>,
+    correlation_id: &str,
+    device_id: &str,
+    wire_format: WireFormat,
+    timeout: Duration,
+) -> Result<Option<Robot>, ClientError> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let message =
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(message) => message,
+                Err(e) if e.is_timeout() => return Err(ClientError::Timeout),
+                Err(_) => return Ok(None),
+            };
+
+        match message {
+            ConsumerMessage::Delivery(delivery) => {
+                if delivery.properties.correlation_id().as_deref() == Some(correlation_id) {
+                    let updated_robot_state: Robot = wire_format
+                        .decode(&delivery.body)
+                        .expect("Could not deserialize");
+
+                    if updated_robot_state.device_id == device_id {
+                        log::info!("Received data from Hub {:?}", updated_robot_state);
+                        return Ok(Some(updated_robot_state));
                     }
                 }
-                _ => {
-                    break;
-                }
             }
+            ConsumerMessage::ServerClosedChannel(e)
+            | ConsumerMessage::ServerClosedConnection(e) => return Err(e.into()),
+            _ => return Ok(None),
         }
-
-        Ok(robot_state.clone())
     }
 }
 
@@ -92,12 +173,79 @@ pub struct Robot {
     pub timestamp: i64,
     /// path of the robot
     pub path: Vec<Path>,
+    /// index of the robot's current point on its path, tracked by the hub
+    #[serde(default)]
+    pub path_index: usize,
     /// device id of the robot
     pub device_id: String,
-    /// state of the robot: resume | pending
+    /// state of the robot: pause | resume | reroute | stopped, as a name or a numeric code
+    #[serde(deserialize_with = "deserialize_state")]
     pub state: String,
     /// current battery level of the robot
     pub battery_level: f64,
+    /// width of the robot, if it differs from the one configured on the hub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f64>,
+    /// height of the robot, if it differs from the one configured on the hub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f64>,
+    /// shape of the robot's footprint, a rectangle of `width` and `height` when not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<Geometry>,
+    /// localization quality of the robot: 0.0 (unknown) to 1.0 (exact)
+    #[serde(default = "default_position_confidence")]
+    pub position_confidence: f64,
+    /// publish interval in milliseconds suggested by the hub under load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_publish_interval_ms: Option<u64>,
+    /// version of the client software running on the robot
+    #[serde(default)]
+    pub client_version: Option<String>,
+    /// round of the state sent by the hub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u64>,
+    /// round of the last state the robot applied, acknowledging it to the hub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acked_round: Option<u64>,
+    /// set while the robot has detected a local hazard and the fleet must stop around it
+    #[serde(default)]
+    pub emergency_stop: bool,
+    /// velocity of the robot along the x-axis, in units per second
+    #[serde(default)]
+    pub vx: f64,
+    /// velocity of the robot along the y-axis, in units per second
+    #[serde(default)]
+    pub vy: f64,
+    /// priority of the robot in deadlocks: the higher one keeps moving
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// `deserialize_state` accepts a motion state either by name or by the numeric code the hub sends
+/// when compact state encoding is enabled.
+fn deserialize_state<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum EncodedState {
+        Name(String),
+        Code(u8),
+    }
+
+    match EncodedState::deserialize(deserializer)? {
+        EncodedState::Name(name) => Ok(name),
+        EncodedState::Code(0) => Ok("pause".to_string()),
+        EncodedState::Code(1) => Ok("resume".to_string()),
+        EncodedState::Code(2) => Ok("reroute".to_string()),
+        EncodedState::Code(3) => Ok("stopped".to_string()),
+        EncodedState::Code(code) => Err(de::Error::custom(format!(
+            "unknown motion state code {}",
+            code
+        ))),
+    }
+}
+
+fn default_position_confidence() -> f64 {
+    1.0
 }
 
 /// [Path] defines attributes which define a
@@ -111,3 +259,70 @@ pub struct Path {
     /// angle of inclination to y-axis in radians
     pub theta: f64,
 }
+
+/// [Geometry] defines the shape of the robot's footprint, centred on its position.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase")]
+pub enum Geometry {
+    /// a rectangle turned with the robot's heading
+    Rect { width: f64, height: f64 },
+    /// a disk, the same whichever way the robot faces
+    Circle { radius: f64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_times_out_without_reply() {
+        let (_sender, receiver) = crossbeam_channel::unbounded::<ConsumerMessage>();
+
+        let started = Instant::now();
+        let reply = await_reply(
+            &receiver,
+            "correlation",
+            "robot1",
+            WireFormat::Json,
+            Duration::from_millis(50),
+        );
+
+        assert!(matches!(reply, Err(ClientError::Timeout)));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_client_awaits_reply_on_consumer_of_new_session() {
+        // the consumer of the lost connection ends as soon as its channel closes.
+        let (old_sender, old_receiver) = crossbeam_channel::unbounded::<ConsumerMessage>();
+        old_sender
+            .send(ConsumerMessage::ClientClosedChannel)
+            .unwrap();
+        assert!(matches!(
+            await_reply(
+                &old_receiver,
+                "correlation",
+                "robot1",
+                WireFormat::Json,
+                Duration::from_secs(5)
+            ),
+            Ok(None)
+        ));
+
+        // the client of the session started on reconnect waits on its own consumer for the full
+        // timeout.
+        let (_new_sender, new_receiver) = crossbeam_channel::unbounded::<ConsumerMessage>();
+        let started = Instant::now();
+        assert!(matches!(
+            await_reply(
+                &new_receiver,
+                "correlation",
+                "robot1",
+                WireFormat::Json,
+                Duration::from_millis(50)
+            ),
+            Err(ClientError::Timeout)
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -1,12 +1,15 @@
 use clap::Parser;
 use serde_derive::{Deserialize, Serialize};
-use std::fs;
+use std::{fmt::Display, fs, str::FromStr};
 
 #[derive(Parser, Debug)]
 pub struct CLIArguments {
     /// path to configuration file
     #[clap(long, value_parser)]
     pub config_path: String,
+    /// load and validate the configuration file, print OK or why it is rejected, then exit
+    #[clap(long, action)]
+    pub validate: bool,
 }
 
 /// [RobotConfig] defines attributes for current RobotConfig
@@ -24,32 +27,357 @@ pub struct RobotConfig {
     pub lower_soc_limit: f64,
     // time difference in milliseconds between two messages
     pub timeout: u64,
+    // battery SOC consumed every loop iteration, to simulate drain on a standalone robot
+    #[serde(default)]
+    pub drain_per_tick: f64,
+    // factor applied to `drain_per_tick` while the robot is loaded
+    #[serde(default = "default_loaded_drain_factor")]
+    pub loaded_drain_factor: f64,
     // rabbit_mq hub hostname
     pub hostname: String,
     // listening port for hub
     pub hub_listening_port: u64,
+    // connect to the hub over TLS (amqps://)
+    #[serde(default)]
+    pub use_tls: bool,
+    // optional PEM file of the CA trusted for the hub's certificate, instead of the system roots
+    pub ca_cert_path: Option<String>,
     // queue name
     pub logs_dir: String,
     // path to init state JSON file
     pub init_state_path: String,
+    // consecutive failed connections to the hub after which the robot gives up
+    #[serde(default = "default_max_reconnect_attempts")]
+    pub max_reconnect_attempts: u32,
+    // time in milliseconds to wait for the hub's reply to a published state
+    #[serde(default = "default_reply_timeout_ms")]
+    pub reply_timeout_ms: u64,
+    // longest wait in milliseconds before publishing again after the hub did not reply
+    #[serde(default = "default_max_publish_backoff_ms")]
+    pub max_publish_backoff_ms: u64,
+    // encoding of robot states exchanged with the hub, as configured on the monitor: json | msgpack
+    #[serde(default)]
+    pub wire_format: WireFormat,
+    // zone of the facility the robot operates in, selecting the queue it publishes to
+    pub zone: Option<String>,
 }
 
-/// `load_config` loads collision monitoring configuration into memory.
-pub(crate) fn load_config(config_path: &str) -> std::result::Result<RobotConfig, String> {
-    match fs::read_to_string(config_path) {
-        Ok(file_str) => {
-            let ret: RobotConfig = match toml::from_str(&file_str) {
-                Ok(r) => r,
-                Err(_) => return Err(format!("config.toml is not a proper toml file.")),
-            };
-            return Ok(ret);
+impl RobotConfig {
+    /// `rpc_queue` returns the name of the queue the robot publishes its states to: `rpc_queue`,
+    /// or `rpc_queue.<zone>` when it operates in a zone.
+    pub(crate) fn rpc_queue(&self) -> String {
+        match &self.zone {
+            Some(zone) => format!("rpc_queue.{}", zone),
+            None => "rpc_queue".to_string(),
+        }
+    }
+}
+
+/// [WireFormat] defines how robot states are encoded in the messages between robots and the
+/// monitor. Both sides must be configured with the same one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    /// JSON text
+    #[default]
+    Json,
+    /// MessagePack, a more compact binary encoding
+    Msgpack,
+}
+
+impl WireFormat {
+    /// `encode` serializes `value` in the wire format. MessagePack keeps field names, so that
+    /// fields left out when empty are understood on the other side.
+    pub(crate) fn encode<T: serde::Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            WireFormat::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            WireFormat::Msgpack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// `decode` deserializes a value encoded in the wire format.
+    pub(crate) fn decode<T: serde::de::DeserializeOwned>(self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            WireFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            WireFormat::Msgpack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
         }
-        Err(e) => {
+    }
+}
+
+fn default_loaded_drain_factor() -> f64 {
+    2.0
+}
+
+fn default_max_reconnect_attempts() -> u32 {
+    5
+}
+
+fn default_reply_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_max_publish_backoff_ms() -> u64 {
+    5000
+}
+
+impl RobotConfig {
+    /// `validate` rejects configuration values the robot cannot run with, naming the offending
+    /// field.
+    pub(crate) fn validate(&self) -> std::result::Result<(), String> {
+        if !(0.0..=100.0).contains(&self.lower_soc_limit) {
             return Err(format!(
-                "Error: Config file (config.toml) is not found in the correct directory. 
-        Please ensure that the configuration directory: \"{}\" exists. ERROR: {:?}",
-                config_path, e
-            ))
+                "lower_soc_limit must be between 0 and 100, got {}",
+                self.lower_soc_limit
+            ));
+        }
+        if self.drain_per_tick < 0.0 {
+            return Err(format!(
+                "drain_per_tick must not be negative, got {}",
+                self.drain_per_tick
+            ));
         }
+        if self.hostname.trim().is_empty() {
+            return Err("hostname must not be empty".to_string());
+        }
+        if self.id.trim().is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// `apply_env_overrides` overlays the `ROBOT_*` environment variables set on top of the
+    /// values parsed from the file, returning the fields they overrode.
+    pub(crate) fn apply_env_overrides(&mut self) -> std::result::Result<Vec<String>, String> {
+        let mut overridden = Vec::new();
+        env_override("ROBOT_ID", "id", &mut self.id, &mut overridden)?;
+        env_override(
+            "ROBOT_HOSTNAME",
+            "hostname",
+            &mut self.hostname,
+            &mut overridden,
+        )?;
+        env_override(
+            "ROBOT_HUB_PORT",
+            "hub_listening_port",
+            &mut self.hub_listening_port,
+            &mut overridden,
+        )?;
+        env_override(
+            "ROBOT_HUB_USER",
+            "queue_hub_user",
+            &mut self.queue_hub_user,
+            &mut overridden,
+        )?;
+        env_override(
+            "ROBOT_HUB_PW",
+            "queue_hub_pw",
+            &mut self.queue_hub_pw,
+            &mut overridden,
+        )?;
+        env_override(
+            "ROBOT_DB_PATH",
+            "db_path",
+            &mut self.db_path,
+            &mut overridden,
+        )?;
+        env_override(
+            "ROBOT_LOGS_DIR",
+            "logs_dir",
+            &mut self.logs_dir,
+            &mut overridden,
+        )?;
+        env_override(
+            "ROBOT_INIT_STATE_PATH",
+            "init_state_path",
+            &mut self.init_state_path,
+            &mut overridden,
+        )?;
+
+        Ok(overridden)
+    }
+}
+
+/// `env_override` replaces `field` with the value of the environment variable `var`, if it is set.
+fn env_override<T>(
+    var: &str,
+    name: &str,
+    field: &mut T,
+    overridden: &mut Vec<String>,
+) -> std::result::Result<(), String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Ok(value) = std::env::var(var) {
+        *field = value
+            .parse()
+            .map_err(|e| format!("{} from {} is invalid: {}", name, var, e))?;
+        overridden.push(format!("{} ({})", name, var));
+    }
+
+    Ok(())
+}
+
+/// `load_config` loads collision monitoring configuration into memory, with environment variables
+/// taking precedence over the file. It also returns the fields overridden from the environment.
+pub(crate) fn load_config(
+    config_path: &str,
+) -> std::result::Result<(RobotConfig, Vec<String>), String> {
+    match fs::read_to_string(config_path) {
+        Ok(file_str) => parse_config(&file_str),
+        Err(e) => Err(format!(
+            "Error: Config file (config.toml) is not found in the correct directory. 
+        Please ensure that the configuration directory: \"{}\" exists. ERROR: {:?}",
+            config_path, e
+        )),
+    }
+}
+
+/// `validate_config` loads the configuration at `config_path` the way the service does on start,
+/// without connecting to anything, and returns why it would be rejected, if at all.
+pub(crate) fn validate_config(config_path: &str) -> std::result::Result<(), String> {
+    load_config(config_path).map(|_| ())
+}
+
+/// `parse_config` parses the contents of a configuration file, overlays the environment and
+/// validates the result.
+fn parse_config(file_str: &str) -> std::result::Result<(RobotConfig, Vec<String>), String> {
+    let mut ret: RobotConfig = match toml::from_str(file_str) {
+        Ok(r) => r,
+        Err(_) => return Err("config.toml is not a proper toml file.".to_string()),
     };
+    let overridden = ret.apply_env_overrides()?;
+    ret.validate()?;
+
+    Ok((ret, overridden))
+}
+
+/// `test_config` returns a small configuration used across unit tests.
+#[cfg(test)]
+pub(crate) fn test_config() -> RobotConfig {
+    RobotConfig {
+        id: "robot1".to_string(),
+        db_path: String::new(),
+        queue_hub_pw: String::new(),
+        queue_hub_user: String::new(),
+        lower_soc_limit: 20.0,
+        timeout: 10,
+        drain_per_tick: 0.0,
+        loaded_drain_factor: default_loaded_drain_factor(),
+        hostname: "localhost".to_string(),
+        hub_listening_port: 5672,
+        use_tls: false,
+        ca_cert_path: None,
+        logs_dir: String::new(),
+        init_state_path: String::new(),
+        max_reconnect_attempts: default_max_reconnect_attempts(),
+        reply_timeout_ms: default_reply_timeout_ms(),
+        max_publish_backoff_ms: default_max_publish_backoff_ms(),
+        wire_format: WireFormat::Json,
+        zone: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// `ENV_LOCK` serializes the tests reading the process environment.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn env_lock() -> MutexGuard<'static, ()> {
+        ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    const VALID_CONFIG: &str = r#"
+        id = "robot1"
+        db_path = "/tmp/robot/db"
+        queue_hub_pw = "guest"
+        queue_hub_user = "guest"
+        hub_listening_port = 5672
+        lower_soc_limit = 20.5
+        timeout = 10
+        hostname = "rabbitmq"
+        logs_dir = "/tmp/robot/logs"
+        init_state_path = "/tmp/robot/init_state.json"
+    "#;
+
+    #[test]
+    fn test_parse_config_accepts_valid_values() {
+        let _env = env_lock();
+        assert!(parse_config(VALID_CONFIG).is_ok());
+    }
+
+    #[test]
+    fn test_validate_config_reports_errors() {
+        let _env = env_lock();
+        let path = |name: &str| {
+            std::env::temp_dir().join(format!("robot_config_{}_{}.toml", std::process::id(), name))
+        };
+        let (good, bad) = (path("good"), path("bad"));
+        std::fs::write(&good, VALID_CONFIG).unwrap();
+        std::fs::write(
+            &bad,
+            VALID_CONFIG.replace("lower_soc_limit = 20.5", "lower_soc_limit = 120.0"),
+        )
+        .unwrap();
+
+        assert_eq!(validate_config(good.to_str().unwrap()), Ok(()));
+        assert_eq!(
+            validate_config(bad.to_str().unwrap()),
+            Err("lower_soc_limit must be between 0 and 100, got 120".to_string())
+        );
+        assert!(validate_config("/nonexistent/config.toml").is_err());
+
+        std::fs::remove_file(good).unwrap();
+        std::fs::remove_file(bad).unwrap();
+    }
+
+    #[test]
+    fn test_parse_config_rejects_invalid_values() {
+        let _env = env_lock();
+        assert_eq!(
+            parse_config(
+                &VALID_CONFIG.replace("lower_soc_limit = 20.5", "lower_soc_limit = 100.5")
+            )
+            .unwrap_err(),
+            "lower_soc_limit must be between 0 and 100, got 100.5"
+        );
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("lower_soc_limit = 20.5", "lower_soc_limit = -1.0"))
+                .unwrap_err(),
+            "lower_soc_limit must be between 0 and 100, got -1"
+        );
+        assert_eq!(
+            parse_config(&VALID_CONFIG.replace("\"rabbitmq\"", "\"\"")).unwrap_err(),
+            "hostname must not be empty"
+        );
+    }
+
+    #[test]
+    fn test_parse_config_applies_env_overrides() {
+        let _env = env_lock();
+        std::env::set_var("ROBOT_ID", "robot7");
+        std::env::set_var("ROBOT_HUB_PORT", "5673");
+
+        let result = parse_config(VALID_CONFIG);
+
+        std::env::remove_var("ROBOT_ID");
+        std::env::remove_var("ROBOT_HUB_PORT");
+
+        let (config, overridden) = result.unwrap();
+        assert_eq!(config.id, "robot7");
+        assert_eq!(config.hub_listening_port, 5673);
+        assert_eq!(config.hostname, "rabbitmq");
+        assert_eq!(
+            config.max_reconnect_attempts,
+            default_max_reconnect_attempts()
+        );
+        assert_eq!(
+            overridden,
+            vec!["id (ROBOT_ID)", "hub_listening_port (ROBOT_HUB_PORT)"]
+        );
+    }
 }
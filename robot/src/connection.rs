@@ -0,0 +1,67 @@
+use amiquip::{Auth, Connection, ConnectionOptions, ConnectionTuning};
+use std::{fs, net::TcpStream};
+
+use crate::client::ClientError;
+use crate::config::RobotConfig;
+
+/// `connect` opens a connection to the hub. With `use_tls` the connection goes over `amqps://`,
+/// trusting the CA at `ca_cert_path` if one is configured and the system roots otherwise.
+pub(crate) fn connect(config: &RobotConfig) -> Result<Connection, ClientError> {
+    if !config.use_tls {
+        return Ok(Connection::insecure_open(&format!(
+            "amqp://{}:{}@{}:{}",
+            config.queue_hub_user, config.queue_hub_pw, config.hostname, config.hub_listening_port
+        ))?);
+    }
+
+    let ca_cert_path = match &config.ca_cert_path {
+        Some(path) => path,
+        None => {
+            return Ok(Connection::open(&format!(
+                "amqps://{}:{}@{}:{}",
+                config.queue_hub_user,
+                config.queue_hub_pw,
+                config.hostname,
+                config.hub_listening_port
+            ))?)
+        }
+    };
+
+    let connector = tls_connector(ca_cert_path)?;
+    let stream = TcpStream::connect(format!("{}:{}", config.hostname, config.hub_listening_port))
+        .and_then(|stream| stream.set_nonblocking(true).map(|_| stream))
+        .map_err(|e| {
+            ClientError::Tls(format!(
+                "could not reach {}:{}: {}",
+                config.hostname, config.hub_listening_port, e
+            ))
+        })?;
+
+    Ok(Connection::open_tls_stream(
+        connector,
+        &config.hostname,
+        mio::net::TcpStream::from_std(stream),
+        ConnectionOptions::<Auth>::default().auth(Auth::Plain {
+            username: config.queue_hub_user.clone(),
+            password: config.queue_hub_pw.clone(),
+        }),
+        ConnectionTuning::default(),
+    )?)
+}
+
+/// `tls_connector` builds a TLS connector trusting the PEM-encoded CA certificate at `ca_cert_path`.
+fn tls_connector(ca_cert_path: &str) -> Result<native_tls::TlsConnector, ClientError> {
+    let pem = fs::read(ca_cert_path).map_err(|e| {
+        ClientError::Tls(format!(
+            "could not read CA certificate {}: {}",
+            ca_cert_path, e
+        ))
+    })?;
+    let certificate = native_tls::Certificate::from_pem(&pem)
+        .map_err(|e| ClientError::Tls(format!("invalid CA certificate {}: {}", ca_cert_path, e)))?;
+
+    native_tls::TlsConnector::builder()
+        .add_root_certificate(certificate)
+        .build()
+        .map_err(|e| ClientError::Tls(e.to_string()))
+}
@@ -1,25 +1,37 @@
 mod client;
 mod config;
+mod connection;
 mod server;
 
-use amiquip::Error;
 use clap::Parser;
 use humantime::Timestamp;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::SystemTime;
 
-use crate::config::{load_config, CLIArguments};
+use crate::client::ClientError;
+use crate::config::{load_config, validate_config, CLIArguments};
 use crate::server::Server;
 
-fn main() -> Result<(), Error> {
+fn main() -> Result<(), ClientError> {
     ///////////////////////////////
     // 1.Load system configuration.
     ///////////////////////////////
 
     let cli_args = CLIArguments::parse();
 
-    let config = load_config(cli_args.config_path.as_str())
+    if cli_args.validate {
+        match validate_config(cli_args.config_path.as_str()) {
+            Ok(()) => println!("OK"),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let (config, env_overrides) = load_config(cli_args.config_path.as_str())
         .expect("Irrecoverable error: failed to load config.toml");
 
     ///////////////////
@@ -48,6 +60,10 @@ fn main() -> Result<(), Error> {
         .apply()
         .expect("could not set up logger");
 
+    for field in env_overrides {
+        log::info!("Config field {} overridden from the environment", field);
+    }
+
     ///////////////////
     // 3. Open Sled DB.
     ///////////////////
@@ -1,27 +1,54 @@
-use amiquip::{Connection, Result};
 use std::{path::Path, sync::Arc, thread, time::Duration};
 
-use crate::client::{Robot, RobotRpcClient};
+use crate::client::{ClientError, Robot, RobotRpcClient};
 use crate::config::RobotConfig;
+use crate::connection;
+
+/// time to wait before reconnecting to the hub after the connection is lost
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
 pub(crate) struct Server;
 
-impl Server {
-    pub(crate) fn start(config: RobotConfig, db: Arc<sled::Db>) -> Result<()> {
-        // open connection.
-        let mut connection = Connection::insecure_open(&format!(
-            "amqp://{}:{}@{}:{}",
-            config.queue_hub_user, config.queue_hub_pw, config.hostname, config.hub_listening_port
-        ))?;
+/// [Backoff] defines the exponentially growing wait between publishes the hub did not reply to.
+struct Backoff {
+    // first wait after a success
+    initial: Duration,
+    // longest wait
+    max: Duration,
+    // next wait
+    current: Duration,
+}
 
-        // open a channel - None says let the library choose the channel ID.
-        let channel = connection.open_channel(None)?;
+impl Backoff {
+    /// `new` creates a backoff starting at `initial`, at least a millisecond, and doubling up to
+    /// `max`.
+    fn new(initial: Duration, max: Duration) -> Self {
+        let initial = initial.max(Duration::from_millis(1)).min(max);
+        Backoff {
+            initial,
+            max,
+            current: initial,
+        }
+    }
 
-        // instantiate rpc client
-        let rpc_client = RobotRpcClient::new(&channel)?;
+    /// `next` returns how long to wait after another failure, doubling the wait after it.
+    fn next(&mut self) -> Duration {
+        let wait = self.current;
+        self.current = (self.current * 2).min(self.max);
 
+        wait
+    }
+
+    /// `reset` brings the wait back to its initial value after a success.
+    fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+impl Server {
+    pub(crate) fn start(config: RobotConfig, db: Arc<sled::Db>) -> Result<(), ClientError> {
         // get init state and save it to DB.
-        let init_state = Self::read_init_state_from_file(config.init_state_path);
+        let init_state = Self::read_init_state_from_file(config.init_state_path.clone());
         let mut current_battery_level: f64 = init_state.battery_level;
 
         db.insert(
@@ -33,42 +60,165 @@ impl Server {
         )
         .expect("Failed to insert record");
 
+        // reconnect on failures, resuming from the last state persisted in the DB.
+        Self::run_reconnecting(
+            config.max_reconnect_attempts,
+            RECONNECT_DELAY,
+            |published| Self::session(&config, &db, &mut current_battery_level, published),
+        )
+    }
+
+    /// `run_reconnecting` runs `session` until it ends, reconnecting after each failure. A session
+    /// that published a state before failing resets the count of consecutive failures; once
+    /// `max_reconnect_attempts` sessions have failed in a row the last error is returned.
+    fn run_reconnecting<E, S>(
+        max_reconnect_attempts: u32,
+        backoff: Duration,
+        mut session: S,
+    ) -> Result<(), E>
+    where
+        E: std::fmt::Debug,
+        S: FnMut(&mut bool) -> Result<(), E>,
+    {
+        let mut failures = 0;
+        loop {
+            let mut published = false;
+            let e = match session(&mut published) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            failures = if published { 1 } else { failures + 1 };
+            if failures >= max_reconnect_attempts {
+                log::error!(
+                    "Connection to hub failed {} times in a row: {:?}, giving up",
+                    failures,
+                    e
+                );
+                return Err(e);
+            }
+
+            log::warn!("Connection to hub failed: {:?}, reconnecting", e);
+            thread::sleep(backoff);
+        }
+    }
+
+    /// `session` connects to the hub and publishes the robot state until the battery runs low,
+    /// flagging `published` once the hub replied at least once.
+    fn session(
+        config: &RobotConfig,
+        db: &sled::Db,
+        current_battery_level: &mut f64,
+        published: &mut bool,
+    ) -> Result<(), ClientError> {
+        // open connection.
+        let mut connection = connection::connect(config)?;
+
+        // open a channel - None says let the library choose the channel ID.
+        let channel = connection.open_channel(None)?;
+
+        // instantiate rpc client
+        let rpc_client = RobotRpcClient::new(&channel, config.wire_format, config.rpc_queue())?;
+
+        // back off from a hub which stops replying, starting at the normal publish interval.
+        let mut backoff = Backoff::new(
+            Duration::from_millis(config.timeout),
+            Duration::from_millis(config.max_publish_backoff_ms),
+        );
+
         // start the messaging loop
         loop {
-            let current_state: Robot =
-                serde_json::from_slice(&db.get(&config.id).expect("Failed to get record").unwrap())
-                    .expect("Could not deserialize");
+            let mut current_state = Self::drain_battery(config, db);
+            current_state.client_version = Some(env!("CARGO_PKG_VERSION").to_string());
+            // acknowledge the last state received from the hub.
+            current_state.acked_round = current_state.round;
 
-            if let Ok(robot_state) = rpc_client.publish_current_state(&current_state) {
-                if current_battery_level < config.lower_soc_limit {
-                    break;
+            let robot_state = match rpc_client.publish_current_state(
+                &current_state,
+                Duration::from_millis(config.reply_timeout_ms),
+            ) {
+                Ok(robot_state) => robot_state,
+                Err(ClientError::Timeout) => {
+                    let wait = backoff.next();
+                    log::warn!("Hub did not reply in time, publishing again in {:?}", wait);
+                    thread::sleep(wait);
+                    continue;
                 }
-                current_battery_level = robot_state.battery_level;
-
-                db.insert(
-                    &config.id,
-                    serde_json::to_string(&robot_state)
-                        .expect("Could not serialize")
-                        .as_bytes()
-                        .to_vec(),
-                )
-                .expect("Failed to insert record");
-            } else {
-                log::info!("Cannot Broadcast");
-                continue;
+                Err(e) => return Err(e),
+            };
+            *published = true;
+            backoff.reset();
+
+            if *current_battery_level < config.lower_soc_limit {
+                break;
             }
+            *current_battery_level = robot_state.battery_level;
+
+            db.insert(
+                &config.id,
+                serde_json::to_string(&robot_state)
+                    .expect("Could not serialize")
+                    .as_bytes()
+                    .to_vec(),
+            )
+            .expect("Failed to insert record");
 
-            // sleep for 10 milliseconds ( 1 Hz )
-            // before sending the message again
-            thread::sleep(Duration::from_millis(config.timeout));
+            // sleep for 10 milliseconds ( 1 Hz ), or longer if the hub
+            // asks for backpressure, before sending the message again
+            thread::sleep(Duration::from_millis(
+                robot_state
+                    .suggested_publish_interval_ms
+                    .unwrap_or(config.timeout),
+            ));
         }
 
-        connection.close()
+        Ok(connection.close()?)
+    }
+
+    /// `drain_battery` consumes `drain_per_tick` of the persisted state's battery (scaled by
+    /// `loaded_drain_factor` while loaded) and persists the drained state before it is published.
+    fn drain_battery(config: &RobotConfig, db: &sled::Db) -> Robot {
+        Self::update_state(db, &config.id, |mut current_state| {
+            let drain = if current_state.loaded {
+                config.drain_per_tick * config.loaded_drain_factor
+            } else {
+                config.drain_per_tick
+            };
+            current_state.battery_level = (current_state.battery_level - drain).max(0.0);
+
+            current_state
+        })
+    }
+
+    /// `update_state` applies `update` to the state persisted for `id` and persists the result,
+    /// only if the record still holds the state that was read. Otherwise the record was written in
+    /// the meantime, and the update is applied again to the new state so that no write is lost.
+    fn update_state<F>(db: &sled::Db, id: &str, mut update: F) -> Robot
+    where
+        F: FnMut(Robot) -> Robot,
+    {
+        loop {
+            let record = db.get(id).expect("Failed to get record");
+            let current_state: Robot =
+                serde_json::from_slice(record.as_deref().expect("No record for robot"))
+                    .expect("Could not deserialize");
+
+            let updated_state = update(current_state);
+            let updated_record = serde_json::to_vec(&updated_state).expect("Could not serialize");
+
+            match db
+                .compare_and_swap(id, record, Some(updated_record))
+                .expect("Failed to update record")
+            {
+                Ok(()) => return updated_state,
+                Err(_) => log::info!("State of {} was modified concurrently, retrying", id),
+            }
+        }
     }
 
     // `read_init_state_from_file` reads current state from JSON file.
     fn read_init_state_from_file(path: String) -> Robot {
-        let contents = std::fs::read(&Path::new(&path)).expect("Failed to open file");
+        let contents = std::fs::read(Path::new(&path)).expect("Failed to open file");
 
         let init_state: Robot =
             serde_json::from_slice(&contents).expect("Failed to deserialize JSON");
@@ -76,3 +226,128 @@ impl Server {
         init_state
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_config;
+
+    #[test]
+    fn test_server_recovers_after_failed_connections() {
+        let mut attempts = 0;
+        let result = Server::run_reconnecting(3, Duration::ZERO, |_| {
+            attempts += 1;
+            if attempts <= 2 {
+                Err("broker unavailable")
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_server_gives_up_after_max_reconnect_attempts() {
+        let mut attempts = 0;
+        let result = Server::run_reconnecting(3, Duration::ZERO, |published| {
+            attempts += 1;
+            // the third session publishes before failing, which restarts the count of failures.
+            *published = attempts == 3;
+            Err::<(), _>("broker unavailable")
+        });
+
+        assert_eq!(result, Err("broker unavailable"));
+        assert_eq!(attempts, 5);
+    }
+
+    #[test]
+    fn test_server_backoff_grows_until_success() {
+        let mut backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(50));
+
+        // every publish left unanswered doubles the wait, up to the cap.
+        let waits: Vec<Duration> = (0..5).map(|_| backoff.next()).collect();
+        assert_eq!(
+            waits,
+            [10, 20, 40, 50, 50].map(Duration::from_millis).to_vec()
+        );
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Duration::from_millis(10));
+
+        // a zero publish interval still backs off.
+        let mut backoff = Backoff::new(Duration::ZERO, Duration::from_millis(50));
+        assert_eq!(backoff.next(), Duration::from_millis(1));
+        assert_eq!(backoff.next(), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_server_drains_battery_every_tick() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let config = RobotConfig {
+            drain_per_tick: 1.5,
+            ..test_config()
+        };
+        let state = |loaded: bool| {
+            serde_json::json!({
+                "x": 0.0,
+                "y": 0.0,
+                "theta": 0.0,
+                "loaded": loaded,
+                "timestamp": 0,
+                "path": [],
+                "device_id": "robot1",
+                "state": "resume",
+                "battery_level": 50.0,
+            })
+            .to_string()
+        };
+
+        db.insert(&config.id, state(false).as_bytes()).unwrap();
+        let levels: Vec<f64> = (0..4)
+            .map(|_| Server::drain_battery(&config, &db).battery_level)
+            .collect();
+        assert_eq!(levels, vec![48.5, 47.0, 45.5, 44.0]);
+
+        db.insert(&config.id, state(true).as_bytes()).unwrap();
+        assert_eq!(Server::drain_battery(&config, &db).battery_level, 47.0);
+    }
+
+    #[test]
+    fn test_server_update_state_retries_on_concurrent_write() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let state = |battery_level: f64| {
+            serde_json::json!({
+                "x": 0.0,
+                "y": 0.0,
+                "theta": 0.0,
+                "loaded": false,
+                "timestamp": 0,
+                "path": [],
+                "device_id": "robot1",
+                "state": "resume",
+                "battery_level": battery_level,
+            })
+            .to_string()
+        };
+        db.insert("robot1", state(50.0).as_bytes()).unwrap();
+
+        let mut attempts = 0;
+        let updated = Server::update_state(&db, "robot1", |mut robot| {
+            attempts += 1;
+            // another writer charges the battery while the first update is under way.
+            if attempts == 1 {
+                db.insert("robot1", state(80.0).as_bytes()).unwrap();
+            }
+            robot.battery_level -= 1.5;
+            robot
+        });
+
+        // the update is applied on top of the concurrent write instead of overwriting it.
+        assert_eq!(attempts, 2);
+        assert_eq!(updated.battery_level, 78.5);
+        let stored: Robot = serde_json::from_slice(&db.get("robot1").unwrap().unwrap()).unwrap();
+        assert_eq!(stored.battery_level, 78.5);
+    }
+}